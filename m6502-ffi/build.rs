@@ -0,0 +1,19 @@
+//! Generates the C header from the exported functions into `OUT_DIR`. Set
+//! `M6502_REGENERATE_HEADER` to also write it over `include/m6502.h`; the
+//! `c_api` test checks that the checked-in header is up to date.
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let bindings = cbindgen::generate(&crate_dir).expect("cbindgen failed to parse the crate");
+    bindings.write_to_file(out_dir.join("m6502.h"));
+    if env::var_os("M6502_REGENERATE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include/m6502.h"));
+    }
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=M6502_REGENERATE_HEADER");
+}
@@ -0,0 +1,175 @@
+#ifndef M6502_H
+#define M6502_H
+
+/* Generated by cbindgen from m6502-ffi/src/lib.rs; do not edit. */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * An NMOS 6502 with 64K of RAM and any number of I/O regions. Undocumented
+ * opcodes are enabled, so a stray opcode cannot abort the host.
+ */
+typedef struct M6502 M6502;
+
+/**
+ * The register file. `status` is laid out as pushed by an interrupt (B clear).
+ */
+typedef struct M6502Registers {
+  uint8_t a;
+  uint8_t x;
+  uint8_t y;
+  uint8_t sp;
+  uint8_t status;
+  uint16_t pc;
+} M6502Registers;
+
+/**
+ * Called for reads from a mapped I/O region; returns the byte on the bus.
+ */
+typedef uint8_t (*M6502ReadFn)(void *user_data, uint16_t addr);
+
+/**
+ * Called for writes to a mapped I/O region.
+ */
+typedef void (*M6502WriteFn)(void *user_data, uint16_t addr, uint8_t value);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an emulator with zeroed RAM and no I/O regions. Free it with
+ * `m6502_destroy`.
+ */
+struct M6502 *m6502_create(void);
+
+/**
+ * Frees an emulator. Does nothing if `cpu` is null.
+ *
+ * # Safety
+ *
+ * `cpu` must be null or come from `m6502_create`, and must not be used afterwards.
+ */
+void m6502_destroy(struct M6502 *cpu);
+
+/**
+ * Clears the registers and starts from the reset vector at $FFFC, read over
+ * the bus so it may come from an I/O region.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+void m6502_reset(struct M6502 *cpu);
+
+/**
+ * Runs one instruction, or takes a pending interrupt, and returns the cycles
+ * it took.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+uint32_t m6502_step(struct M6502 *cpu);
+
+/**
+ * Runs whole instructions until at least `cycles` have passed or the CPU
+ * jams, and returns the cycles used. The last instruction runs to the end,
+ * so the result may exceed `cycles` by at most one instruction; it stops at
+ * `UINT32_MAX` rather than wrapping.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+uint32_t m6502_run(struct M6502 *cpu, uint32_t cycles);
+
+/**
+ * Drives the level-sensitive IRQ input; it is sampled at instruction boundaries.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+void m6502_set_irq(struct M6502 *cpu, bool asserted);
+
+/**
+ * Signals an NMI, taken at the next instruction boundary.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+void m6502_nmi(struct M6502 *cpu);
+
+/**
+ * True once a JAM opcode has locked up the CPU; `m6502_reset` clears it.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+bool m6502_jammed(const struct M6502 *cpu);
+
+/**
+ * Copies the registers into `registers`.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create` and `registers` must be valid
+ * for writes.
+ */
+void m6502_get_registers(const struct M6502 *cpu, struct M6502Registers *registers);
+
+/**
+ * Loads the registers from `registers`. Only valid between instructions, which
+ * is always the case outside the calls above.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create` and `registers` must be valid
+ * for reads.
+ */
+void m6502_set_registers(struct M6502 *cpu, const struct M6502Registers *registers);
+
+/**
+ * Reads RAM directly, bypassing the I/O callbacks.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+uint8_t m6502_read(const struct M6502 *cpu, uint16_t addr);
+
+/**
+ * Writes RAM directly, bypassing the I/O callbacks.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`.
+ */
+void m6502_write(struct M6502 *cpu, uint16_t addr, uint8_t value);
+
+/**
+ * Hands CPU accesses to `start..=end` to `read` and `write`, which receive
+ * `user_data` back. Either callback may be null to leave that direction on RAM.
+ * Returns false, mapping nothing, if `start` is above `end`.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle from `m6502_create`. The callbacks and
+ * `user_data` must stay valid for as long as the handle is used.
+ */
+bool m6502_map_io(struct M6502 *cpu,
+                  uint16_t start,
+                  uint16_t end,
+                  M6502ReadFn read,
+                  M6502WriteFn write,
+                  void *user_data);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* M6502_H */
@@ -0,0 +1,279 @@
+//! C bindings for the m6502 emulator, built as a static and a shared library.
+//!
+//! `include/m6502.h` is generated by cbindgen from this file; build with
+//! `M6502_REGENERATE_HEADER=1` to update it after changing the API.
+//! The emulator is driven cycle by cycle through the NMOS tick engine, so
+//! memory-mapped I/O callbacks see the same bus accesses as the real chip,
+//! dummy reads and double writes included.
+//!
+//! ```c
+//! M6502 *cpu = m6502_create();
+//! m6502_write(cpu, 0xFFFC, 0x00);
+//! m6502_write(cpu, 0xFFFD, 0x80);
+//! m6502_reset(cpu);
+//! m6502_run(cpu, 1000);
+//! m6502_destroy(cpu);
+//! ```
+
+use core::ffi::c_void;
+use m6502::{Bus, Byte, Cpu, Memory, StatusFlags, Word};
+
+/// Called for reads from a mapped I/O region; returns the byte on the bus.
+pub type M6502ReadFn = Option<unsafe extern "C" fn(user_data: *mut c_void, addr: u16) -> u8>;
+
+/// Called for writes to a mapped I/O region.
+pub type M6502WriteFn = Option<unsafe extern "C" fn(user_data: *mut c_void, addr: u16, value: u8)>;
+
+/// An NMOS 6502 with 64K of RAM and any number of I/O regions. Undocumented
+/// opcodes are enabled, so a stray opcode cannot abort the host.
+pub struct M6502 {
+    cpu: Cpu,
+    memory: Box<Memory>,
+    io: Vec<IoRegion>,
+}
+
+/// The register file. `status` is laid out as pushed by an interrupt (B clear).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct M6502Registers {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub status: u8,
+    pub pc: u16,
+}
+
+/// An address range, inclusive at both ends, handed to callbacks instead of RAM.
+struct IoRegion {
+    start: Word,
+    end: Word,
+    read: M6502ReadFn,
+    write: M6502WriteFn,
+    user_data: *mut c_void,
+}
+
+/// RAM with the I/O regions laid over it. The first region mapped at an
+/// address wins; a region without a callback for a direction falls through to
+/// RAM for it.
+struct IoBus<'a> {
+    memory: &'a mut Memory,
+    io: &'a [IoRegion],
+}
+
+impl IoBus<'_> {
+    fn region(&self, addr: Word) -> Option<&IoRegion> {
+        self.io.iter().find(|region| (region.start..=region.end).contains(&addr))
+    }
+}
+
+impl Bus for IoBus<'_> {
+    fn read(&mut self, addr: Word) -> Byte {
+        match self.region(addr) {
+            // SAFETY: m6502_map_io's caller keeps the callback and user data valid
+            Some(IoRegion { read: Some(read), user_data, .. }) => unsafe { read(*user_data, addr) },
+            _ => self.memory.data[addr as usize],
+        }
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        match self.region(addr) {
+            // SAFETY: as for read
+            Some(IoRegion { write: Some(write), user_data, .. }) => unsafe { write(*user_data, addr, value) },
+            _ => self.memory.data[addr as usize] = value,
+        }
+    }
+}
+
+impl M6502 {
+    fn bus(&mut self) -> (&mut Cpu, IoBus<'_>) {
+        (&mut self.cpu, IoBus { memory: &mut self.memory, io: &self.io })
+    }
+}
+
+// --- Lifetime ---
+
+/// Creates an emulator with zeroed RAM and no I/O regions. Free it with
+/// `m6502_destroy`.
+#[unsafe(no_mangle)]
+pub extern "C" fn m6502_create() -> *mut M6502 {
+    let mut cpu = Cpu::new();
+    cpu.illegal_opcodes = true;
+    let emulator = M6502 { cpu, memory: Box::new(Memory { data: [0; 1024 * 64] }), io: Vec::new() };
+    Box::into_raw(Box::new(emulator))
+}
+
+/// Frees an emulator. Does nothing if `cpu` is null.
+///
+/// # Safety
+///
+/// `cpu` must be null or come from `m6502_create`, and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_destroy(cpu: *mut M6502) {
+    if !cpu.is_null() {
+        drop(unsafe { Box::from_raw(cpu) });
+    }
+}
+
+// --- Execution ---
+
+/// Clears the registers and starts from the reset vector at $FFFC, read over
+/// the bus so it may come from an I/O region.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_reset(cpu: *mut M6502) {
+    let emulator = unsafe { &mut *cpu };
+    emulator.cpu.reset(&mut emulator.memory);
+    let (cpu, mut bus) = emulator.bus();
+    cpu.pc = bus.read(0xFFFC) as Word | (bus.read(0xFFFD) as Word) << 8;
+}
+
+/// Runs one instruction, or takes a pending interrupt, and returns the cycles
+/// it took.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_step(cpu: *mut M6502) -> u32 {
+    let (cpu, mut bus) = unsafe { &mut *cpu }.bus();
+    cpu.tick_instruction(&mut bus) as u32
+}
+
+/// Runs whole instructions until at least `cycles` have passed or the CPU
+/// jams, and returns the cycles used. The last instruction runs to the end,
+/// so the result may exceed `cycles` by at most one instruction; it stops at
+/// `UINT32_MAX` rather than wrapping.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_run(cpu: *mut M6502, cycles: u32) -> u32 {
+    let (cpu, mut bus) = unsafe { &mut *cpu }.bus();
+    let mut used = 0;
+    while used < cycles && !cpu.jammed {
+        used = used.saturating_add(cpu.tick_instruction(&mut bus) as u32);
+    }
+    used
+}
+
+/// Drives the level-sensitive IRQ input; it is sampled at instruction boundaries.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_set_irq(cpu: *mut M6502, asserted: bool) {
+    unsafe { &mut *cpu }.cpu.set_irq_line(asserted);
+}
+
+/// Signals an NMI, taken at the next instruction boundary.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_nmi(cpu: *mut M6502) {
+    unsafe { &mut *cpu }.cpu.trigger_nmi();
+}
+
+/// True once a JAM opcode has locked up the CPU; `m6502_reset` clears it.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_jammed(cpu: *const M6502) -> bool {
+    unsafe { &*cpu }.cpu.jammed
+}
+
+// --- Registers ---
+
+/// Copies the registers into `registers`.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create` and `registers` must be valid
+/// for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_get_registers(cpu: *const M6502, registers: *mut M6502Registers) {
+    let cpu = &unsafe { &*cpu }.cpu;
+    let value = M6502Registers {
+        a: cpu.reg_a,
+        x: cpu.reg_x,
+        y: cpu.reg_y,
+        sp: cpu.sp,
+        status: cpu.status.to_byte(false),
+        pc: cpu.pc,
+    };
+    unsafe { registers.write(value) };
+}
+
+/// Loads the registers from `registers`. Only valid between instructions, which
+/// is always the case outside the calls above.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create` and `registers` must be valid
+/// for reads.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_set_registers(cpu: *mut M6502, registers: *const M6502Registers) {
+    let cpu = &mut unsafe { &mut *cpu }.cpu;
+    let registers = unsafe { registers.read() };
+    cpu.reg_a = registers.a;
+    cpu.reg_x = registers.x;
+    cpu.reg_y = registers.y;
+    cpu.sp = registers.sp;
+    cpu.status = StatusFlags::from_byte(registers.status);
+    cpu.pc = registers.pc;
+}
+
+// --- Memory ---
+
+/// Reads RAM directly, bypassing the I/O callbacks.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_read(cpu: *const M6502, addr: u16) -> u8 {
+    unsafe { &*cpu }.memory.data[addr as usize]
+}
+
+/// Writes RAM directly, bypassing the I/O callbacks.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_write(cpu: *mut M6502, addr: u16, value: u8) {
+    unsafe { &mut *cpu }.memory.data[addr as usize] = value;
+}
+
+/// Hands CPU accesses to `start..=end` to `read` and `write`, which receive
+/// `user_data` back. Either callback may be null to leave that direction on RAM.
+/// Returns false, mapping nothing, if `start` is above `end`.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle from `m6502_create`. The callbacks and
+/// `user_data` must stay valid for as long as the handle is used.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn m6502_map_io(
+    cpu: *mut M6502,
+    start: u16,
+    end: u16,
+    read: M6502ReadFn,
+    write: M6502WriteFn,
+    user_data: *mut c_void,
+) -> bool {
+    if start > end {
+        return false;
+    }
+    unsafe { &mut *cpu }.io.push(IoRegion { start, end, read, write, user_data });
+    true
+}
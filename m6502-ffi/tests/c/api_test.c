@@ -0,0 +1,184 @@
+/* Drives the C API end to end; built and run by tests/c_api.rs. */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "m6502.h"
+
+static int failures;
+
+#define CHECK(cond)                                                     \
+  do {                                                                  \
+    if (!(cond)) {                                                      \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      failures++;                                                       \
+    }                                                                   \
+  } while (0)
+
+/* A device with a counter at $D000 and an output latch at $D001 */
+struct device {
+  uint8_t counter;
+  uint8_t latch;
+  int writes;
+};
+
+static uint8_t device_read(void *user_data, uint16_t addr) {
+  struct device *device = user_data;
+  return addr == 0xD000 ? device->counter++ : 0xFF;
+}
+
+static void device_write(void *user_data, uint16_t addr, uint8_t value) {
+  struct device *device = user_data;
+  if (addr == 0xD001) {
+    device->latch = value;
+  }
+  device->writes++;
+}
+
+static void load(M6502 *cpu, uint16_t addr, const uint8_t *bytes, size_t len) {
+  for (size_t i = 0; i < len; i++) {
+    m6502_write(cpu, (uint16_t)(addr + i), bytes[i]);
+  }
+}
+
+static void test_reset_and_step(void) {
+  static const uint8_t program[] = {
+      0xA2, 0x05,       /* LDX #$05 */
+      0x8A,             /* TXA */
+      0x9D, 0x00, 0x02, /* STA $0200,X */
+      0xCA,             /* DEX */
+      0xD0, 0xF9,       /* BNE $8002 */
+      0x02,             /* JAM */
+  };
+  M6502 *cpu = m6502_create();
+  load(cpu, 0x8000, program, sizeof program);
+  m6502_write(cpu, 0xFFFC, 0x00);
+  m6502_write(cpu, 0xFFFD, 0x80);
+  m6502_reset(cpu);
+
+  M6502Registers regs;
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.pc == 0x8000);
+  CHECK(m6502_step(cpu) == 2);
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.x == 0x05 && regs.pc == 0x8002);
+
+  /* Five passes of TXA, STA, DEX and BNE, the last branch not taken */
+  CHECK(m6502_run(cpu, 5 * (2 + 5 + 2 + 3) - 1) == 5 * (2 + 5 + 2 + 3) - 1);
+  for (int i = 1; i <= 5; i++) {
+    CHECK(m6502_read(cpu, (uint16_t)(0x0200 + i)) == i);
+  }
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.pc == 0x8009);
+  CHECK((regs.status & 0x02) != 0);
+
+  /* The JMP at the end finishes although only one cycle was asked for */
+  m6502_write(cpu, 0x8009, 0x4C); /* JMP $8009 */
+  m6502_write(cpu, 0x800A, 0x09);
+  m6502_write(cpu, 0x800B, 0x80);
+  CHECK(m6502_run(cpu, 1) == 3);
+  m6502_write(cpu, 0x8009, 0x02); /* JAM */
+
+  CHECK(!m6502_jammed(cpu));
+  m6502_run(cpu, 100);
+  CHECK(m6502_jammed(cpu));
+  m6502_reset(cpu);
+  CHECK(!m6502_jammed(cpu));
+  m6502_destroy(cpu);
+}
+
+static void test_set_registers(void) {
+  M6502 *cpu = m6502_create();
+  M6502Registers regs = {.a = 0x12, .x = 0x34, .y = 0x56, .sp = 0xF0, .status = 0x81, .pc = 0x0400};
+  m6502_set_registers(cpu, &regs);
+  m6502_write(cpu, 0x0400, 0x48); /* PHA */
+  CHECK(m6502_step(cpu) == 3);
+  CHECK(m6502_read(cpu, 0x01F0) == 0x12);
+
+  M6502Registers after;
+  m6502_get_registers(cpu, &after);
+  CHECK(after.a == 0x12 && after.x == 0x34 && after.y == 0x56);
+  CHECK(after.sp == 0xEF && after.pc == 0x0401);
+  CHECK((after.status & 0x81) == 0x81);
+  m6502_destroy(cpu);
+}
+
+static void test_memory_mapped_io(void) {
+  static const uint8_t program[] = {
+      0xAD, 0x00, 0xD0, /* LDA $D000 */
+      0x8D, 0x01, 0xD0, /* STA $D001 */
+      0xEE, 0x01, 0xD0, /* INC $D001 */
+      0x4C, 0x00, 0x04, /* JMP $0400 */
+  };
+  struct device device = {.counter = 0x40};
+  M6502 *cpu = m6502_create();
+  CHECK(!m6502_map_io(cpu, 0xD001, 0xD000, device_read, device_write, &device));
+  CHECK(m6502_map_io(cpu, 0xD000, 0xD0FF, device_read, device_write, &device));
+  load(cpu, 0x0400, program, sizeof program);
+  M6502Registers regs = {.sp = 0xFD, .pc = 0x0400};
+  m6502_set_registers(cpu, &regs);
+
+  m6502_step(cpu);
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.a == 0x40 && device.counter == 0x41);
+  m6502_step(cpu);
+  CHECK(device.latch == 0x40 && device.writes == 1);
+
+  /* INC writes the unmodified value back before the result */
+  m6502_step(cpu);
+  CHECK(device.writes == 3);
+  CHECK(m6502_read(cpu, 0xD001) == 0x00);
+
+  /* A read-only region leaves writes on RAM */
+  CHECK(m6502_map_io(cpu, 0x0300, 0x0300, device_read, NULL, &device));
+  m6502_write(cpu, 0x0400, 0x8D); /* STA $0300 */
+  m6502_write(cpu, 0x0401, 0x00);
+  m6502_write(cpu, 0x0402, 0x03);
+  regs.pc = 0x0400;
+  m6502_set_registers(cpu, &regs);
+  m6502_step(cpu);
+  CHECK(m6502_read(cpu, 0x0300) == 0x40);
+  m6502_destroy(cpu);
+}
+
+static void test_interrupts(void) {
+  M6502 *cpu = m6502_create();
+  m6502_write(cpu, 0x0400, 0xEA); /* NOP */
+  m6502_write(cpu, 0xFFFA, 0x00); /* NMI vector */
+  m6502_write(cpu, 0xFFFB, 0x90);
+  m6502_write(cpu, 0xFFFE, 0x00); /* IRQ vector */
+  m6502_write(cpu, 0xFFFF, 0xA0);
+
+  M6502Registers regs = {.sp = 0xFD, .status = 0x04, .pc = 0x0400};
+  m6502_set_registers(cpu, &regs);
+  m6502_set_irq(cpu, true);
+  CHECK(m6502_step(cpu) == 2); /* masked */
+
+  regs.status = 0x00;
+  regs.pc = 0x0400;
+  m6502_set_registers(cpu, &regs);
+  CHECK(m6502_step(cpu) == 7);
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.pc == 0xA000);
+  m6502_set_irq(cpu, false);
+
+  m6502_nmi(cpu);
+  CHECK(m6502_step(cpu) == 7);
+  m6502_get_registers(cpu, &regs);
+  CHECK(regs.pc == 0x9000);
+  m6502_destroy(cpu);
+}
+
+int main(void) {
+  test_reset_and_step();
+  test_set_registers();
+  test_memory_mapped_io();
+  test_interrupts();
+  m6502_destroy(NULL);
+  if (failures) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return EXIT_FAILURE;
+  }
+  puts("all checks passed");
+  return EXIT_SUCCESS;
+}
@@ -0,0 +1,58 @@
+//! Builds `tests/c/api_test.c` against the static library and the checked-in
+//! header, then runs it. Set `CC` to use a compiler other than `cc`.
+//!
+//! `cargo test` only builds the rlib, so the test asks cargo for the static
+//! library first.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `target/<profile>`, where cargo leaves the static library next to the `deps`
+/// directory holding this test.
+fn profile_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().and_then(Path::parent).unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program_passes() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let profile_dir = profile_dir();
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo.args(["build", "--lib", "-p", "m6502-ffi"]);
+    if profile_dir.ends_with("release") {
+        cargo.arg("--release");
+    }
+    assert!(cargo.status().unwrap().success(), "building the static library failed");
+    let library = profile_dir.join("libm6502_ffi.a");
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("api_test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg(crate_dir.join("tests/c/api_test.c"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run {}: {}", compiler, err));
+    assert!(status.success(), "compiling the C test failed");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C test failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let checked_in = include_str!("../include/m6502.h");
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/m6502.h"));
+    assert!(checked_in == generated, "include/m6502.h is stale; rebuild with M6502_REGENERATE_HEADER=1");
+}
@@ -0,0 +1,422 @@
+//! Python bindings for the m6502 emulator, for testing 6502 routines from pytest.
+//!
+//! ```python
+//! from m6502 import Cpu
+//!
+//! cpu = Cpu()
+//! cpu.memory[0x0600:0x0603] = bytes([0xA9, 0x42, 0x00])  # LDA #$42, BRK
+//! cpu.pc = 0x0600
+//! cpu.step()
+//! assert cpu.a == 0x42 and not cpu.flags.zero
+//! ```
+//!
+//! Build the module with `maturin develop` in this directory. The tests embed
+//! the interpreter, so `cargo test` needs only a Python 3 installation.
+
+use std::collections::BTreeMap;
+
+use m6502::{Byte, CpuVariant, StatusFlags, Word};
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PySlice};
+
+const MEMORY_SIZE: usize = 1024 * 64;
+
+/// Cycles `run_until` allows before giving up, unless told otherwise.
+const DEFAULT_MAX_CYCLES: u64 = 10_000_000;
+
+// --- Memory ---
+
+/// 64K of RAM. Indexes like a `bytearray`: `memory[addr]` is an int and
+/// `memory[start:stop]` is `bytes`, and both can be assigned.
+#[pyclass(module = "m6502")]
+pub struct Memory {
+    inner: Box<m6502::Memory>,
+}
+
+impl Memory {
+    fn index(&self, index: isize) -> PyResult<usize> {
+        let addr = if index < 0 { index + MEMORY_SIZE as isize } else { index };
+        if (0..MEMORY_SIZE as isize).contains(&addr) {
+            Ok(addr as usize)
+        } else {
+            Err(PyIndexError::new_err("memory index out of range"))
+        }
+    }
+
+    /// The addresses a slice selects, in order.
+    fn slice(slice: &Bound<'_, PySlice>) -> PyResult<Vec<usize>> {
+        let indices = slice.indices(MEMORY_SIZE as isize)?;
+        Ok((0..indices.slicelength).map(|i| (indices.start + i as isize * indices.step) as usize).collect())
+    }
+}
+
+#[pymethods]
+impl Memory {
+    #[new]
+    fn new() -> Self {
+        Memory { inner: Box::new(m6502::Memory { data: [0; MEMORY_SIZE] }) }
+    }
+
+    fn __len__(&self) -> usize {
+        MEMORY_SIZE
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, index: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        if let Ok(slice) = index.cast::<PySlice>() {
+            let bytes: Vec<Byte> = Memory::slice(slice)?.into_iter().map(|addr| self.inner.data[addr]).collect();
+            return Ok(PyBytes::new(py, &bytes).into_any());
+        }
+        let addr = self.index(index.extract()?)?;
+        Ok(self.inner.data[addr].into_pyobject(py)?.into_any())
+    }
+
+    fn __setitem__(&mut self, index: &Bound<'_, PyAny>, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        if let Ok(slice) = index.cast::<PySlice>() {
+            let addrs = Memory::slice(slice)?;
+            let bytes: Vec<Byte> = value.extract()?;
+            if bytes.len() != addrs.len() {
+                return Err(PyValueError::new_err(format!(
+                    "cannot assign {} bytes to a slice of {}",
+                    bytes.len(),
+                    addrs.len()
+                )));
+            }
+            for (addr, byte) in addrs.into_iter().zip(bytes) {
+                self.inner.data[addr] = byte;
+            }
+            return Ok(());
+        }
+        let addr = self.index(index.extract()?)?;
+        self.inner.data[addr] = value.extract()?;
+        Ok(())
+    }
+
+    /// Copies `data` to `addr`, wrapping at the end of memory.
+    fn load(&mut self, addr: Word, data: Vec<Byte>) {
+        for (offset, byte) in data.into_iter().enumerate() {
+            self.inner.data[addr.wrapping_add(offset as Word) as usize] = byte;
+        }
+    }
+
+    /// The little-endian word at `addr`.
+    fn read_word(&self, addr: Word) -> Word {
+        self.inner.data[addr as usize] as Word | (self.inner.data[addr.wrapping_add(1) as usize] as Word) << 8
+    }
+
+    fn write_word(&mut self, addr: Word, value: Word) {
+        self.inner.data[addr as usize] = value as Byte;
+        self.inner.data[addr.wrapping_add(1) as usize] = (value >> 8) as Byte;
+    }
+}
+
+// --- Cpu ---
+
+/// A 6502 attached to a `Memory`. Undocumented NMOS opcodes are enabled by
+/// default; turn `illegal_opcodes` off to make them panic instead.
+#[pyclass(module = "m6502")]
+pub struct Cpu {
+    cpu: m6502::Cpu,
+    memory: Py<Memory>,
+    /// Breakpoint addresses and their callbacks.
+    breakpoints: BTreeMap<Word, Option<Py<PyAny>>>,
+    cycles: u64,
+}
+
+/// Why a run stopped.
+enum Stop {
+    Budget,
+    Target,
+    Breakpoint,
+    Halted,
+}
+
+fn parse_variant(name: &str) -> PyResult<CpuVariant> {
+    match name.to_ascii_lowercase().as_str() {
+        "nmos" | "6502" => Ok(CpuVariant::Nmos6502),
+        "65c02" => Ok(CpuVariant::Cmos65C02),
+        "r65c02" => Ok(CpuVariant::Rockwell65C02),
+        "w65c02s" => Ok(CpuVariant::Wdc65C02),
+        "2a03" => Ok(CpuVariant::Ricoh2A03),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown variant {:?}, expected nmos, 65c02, r65c02, w65c02s or 2a03",
+            name
+        ))),
+    }
+}
+
+impl Cpu {
+    fn halted(&self) -> bool {
+        self.cpu.jammed || self.cpu.waiting || self.cpu.stopped
+    }
+
+    /// Runs instructions until `budget` cycles have passed, PC reaches `target`,
+    /// or a breakpoint stops execution. Neither stops the first instruction, so
+    /// a run can resume from a breakpoint.
+    fn run_loop(slf: &Bound<'_, Self>, budget: u64, target: Option<Word>) -> PyResult<(u64, Stop)> {
+        let py = slf.py();
+        let mut used = 0;
+        let mut first = true;
+        while used < budget {
+            let callback = {
+                let this = slf.borrow();
+                if this.halted() {
+                    return Ok((used, Stop::Halted));
+                }
+                let pc = this.cpu.pc;
+                if !first && target == Some(pc) {
+                    return Ok((used, Stop::Target));
+                }
+                match this.breakpoints.get(&pc) {
+                    Some(callback) if !first => Some(callback.as_ref().map(|callback| callback.clone_ref(py))),
+                    _ => None,
+                }
+            };
+            // The callback may inspect or change the Cpu, so no borrow is held
+            match callback {
+                Some(None) => return Ok((used, Stop::Breakpoint)),
+                Some(Some(callback)) if callback.call1(py, (slf,))?.is_truthy(py)? => {
+                    return Ok((used, Stop::Breakpoint));
+                }
+                _ => {}
+            }
+            first = false;
+            used += slf.borrow_mut().step(py) as u64;
+        }
+        Ok((used, Stop::Budget))
+    }
+}
+
+#[pymethods]
+impl Cpu {
+    /// A CPU at $FFFC with its own zeroed memory unless one is given. `variant`
+    /// is one of nmos, 65c02, r65c02, w65c02s or 2a03.
+    #[new]
+    #[pyo3(signature = (memory=None, variant="nmos"))]
+    fn new(py: Python<'_>, memory: Option<Py<Memory>>, variant: &str) -> PyResult<Self> {
+        let mut cpu = m6502::Cpu::with_variant(parse_variant(variant)?);
+        cpu.illegal_opcodes = true;
+        let memory = match memory {
+            Some(memory) => memory,
+            None => Py::new(py, Memory::new())?,
+        };
+        Ok(Cpu { cpu, memory, breakpoints: BTreeMap::new(), cycles: 0 })
+    }
+
+    #[getter]
+    fn memory(&self, py: Python<'_>) -> Py<Memory> {
+        self.memory.clone_ref(py)
+    }
+
+    /// Clears the registers and starts from the reset vector at $FFFC.
+    fn reset(&mut self, py: Python<'_>) {
+        let mut memory = self.memory.borrow_mut(py);
+        self.cpu.reset(&mut memory.inner);
+    }
+
+    /// Runs one instruction and returns the cycles it took, or 0 if the CPU is
+    /// jammed, waiting or stopped.
+    fn step(&mut self, py: Python<'_>) -> u32 {
+        let mut memory = self.memory.borrow_mut(py);
+        let cycles = self.cpu.execute(1, &mut memory.inner) as u32;
+        self.cycles += cycles as u64;
+        cycles
+    }
+
+    /// Runs whole instructions until at least `cycles` have passed, the CPU
+    /// halts, or a breakpoint stops it. Returns the cycles used.
+    fn run(slf: &Bound<'_, Self>, cycles: u64) -> PyResult<u64> {
+        Cpu::run_loop(slf, cycles, None).map(|(used, _)| used)
+    }
+
+    /// Runs until PC reaches `addr` or a breakpoint stops execution, and returns
+    /// the cycles used. Raises `TimeoutError` after `max_cycles` and
+    /// `RuntimeError` if the CPU halts first.
+    #[pyo3(signature = (addr, max_cycles=DEFAULT_MAX_CYCLES))]
+    fn run_until(slf: &Bound<'_, Self>, addr: Word, max_cycles: u64) -> PyResult<u64> {
+        let (used, stop) = Cpu::run_loop(slf, max_cycles, Some(addr))?;
+        let pc = slf.borrow().cpu.pc;
+        match stop {
+            Stop::Target | Stop::Breakpoint => Ok(used),
+            Stop::Budget => Err(PyTimeoutError::new_err(format!(
+                "PC did not reach ${:04X} within {} cycles (at ${:04X})",
+                addr, max_cycles, pc
+            ))),
+            Stop::Halted => Err(PyRuntimeError::new_err(format!("CPU halted at ${:04X} before reaching ${:04X}", pc, addr))),
+        }
+    }
+
+    /// Stops `run` and `run_until` when PC reaches `addr`. With a callback, it is
+    /// called with the Cpu instead, and execution only stops if it returns a
+    /// true value.
+    #[pyo3(signature = (addr, callback=None))]
+    fn add_breakpoint(&mut self, addr: Word, callback: Option<Py<PyAny>>) {
+        self.breakpoints.insert(addr, callback);
+    }
+
+    fn remove_breakpoint(&mut self, addr: Word) -> PyResult<()> {
+        match self.breakpoints.remove(&addr) {
+            Some(_) => Ok(()),
+            None => Err(PyKeyError::new_err(format!("no breakpoint at ${:04X}", addr))),
+        }
+    }
+
+    fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Breakpoint addresses in ascending order.
+    #[getter]
+    fn breakpoints(&self) -> Vec<Word> {
+        self.breakpoints.keys().copied().collect()
+    }
+
+    // --- Registers ---
+
+    #[getter]
+    fn a(&self) -> Byte {
+        self.cpu.reg_a
+    }
+
+    #[setter]
+    fn set_a(&mut self, value: Byte) {
+        self.cpu.reg_a = value;
+    }
+
+    #[getter]
+    fn x(&self) -> Byte {
+        self.cpu.reg_x
+    }
+
+    #[setter]
+    fn set_x(&mut self, value: Byte) {
+        self.cpu.reg_x = value;
+    }
+
+    #[getter]
+    fn y(&self) -> Byte {
+        self.cpu.reg_y
+    }
+
+    #[setter]
+    fn set_y(&mut self, value: Byte) {
+        self.cpu.reg_y = value;
+    }
+
+    #[getter]
+    fn sp(&self) -> Byte {
+        self.cpu.sp
+    }
+
+    #[setter]
+    fn set_sp(&mut self, value: Byte) {
+        self.cpu.sp = value;
+    }
+
+    #[getter]
+    fn pc(&self) -> Word {
+        self.cpu.pc
+    }
+
+    #[setter]
+    fn set_pc(&mut self, value: Word) {
+        self.cpu.pc = value;
+    }
+
+    /// The status register as pushed by an interrupt (B clear).
+    #[getter]
+    fn status(&self) -> Byte {
+        self.cpu.status.to_byte(false)
+    }
+
+    #[setter]
+    fn set_status(&mut self, value: Byte) {
+        self.cpu.status = StatusFlags::from_byte(value);
+    }
+
+    /// The individual status flags, e.g. `cpu.flags.carry = True`.
+    #[getter]
+    fn flags(slf: Bound<'_, Self>) -> Flags {
+        Flags { cpu: slf.unbind() }
+    }
+
+    /// Cycles run since the Cpu was created.
+    #[getter]
+    fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Set once a JAM opcode has locked up the CPU; `reset` clears it.
+    #[getter]
+    fn jammed(&self) -> bool {
+        self.cpu.jammed
+    }
+
+    #[getter]
+    fn illegal_opcodes(&self) -> bool {
+        self.cpu.illegal_opcodes
+    }
+
+    #[setter]
+    fn set_illegal_opcodes(&mut self, value: bool) {
+        self.cpu.illegal_opcodes = value;
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Cpu(pc=${:04X}, a=${:02X}, x=${:02X}, y=${:02X}, sp=${:02X}, flags={})",
+            self.cpu.pc, self.cpu.reg_a, self.cpu.reg_x, self.cpu.reg_y, self.cpu.sp, self.cpu.status
+        )
+    }
+}
+
+// --- Flags ---
+
+/// A live view of a Cpu's status flags.
+#[pyclass(module = "m6502")]
+pub struct Flags {
+    cpu: Py<Cpu>,
+}
+
+/// A readable and writable property for each flag.
+macro_rules! flags {
+    ($($name:ident, $setter:ident => $field:ident;)*) => {
+        #[pymethods]
+        impl Flags {
+            $(
+                #[getter]
+                fn $name(&self, py: Python<'_>) -> bool {
+                    self.cpu.borrow(py).cpu.status.$field
+                }
+
+                #[setter]
+                fn $setter(&self, py: Python<'_>, value: bool) {
+                    self.cpu.borrow_mut(py).cpu.status.$field = value;
+                }
+            )*
+
+            fn __repr__(&self, py: Python<'_>) -> String {
+                format!("Flags({})", self.cpu.borrow(py).cpu.status)
+            }
+        }
+    };
+}
+
+flags! {
+    carry, set_carry => carry;
+    zero, set_zero => zero;
+    interrupt_disable, set_interrupt_disable => interrupt_disable;
+    decimal, set_decimal => decimal_mode;
+    overflow, set_overflow => overflow;
+    negative, set_negative => negative;
+}
+
+/// The `m6502` Python module.
+#[pymodule]
+#[pyo3(name = "m6502")]
+pub fn m6502_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Cpu>()?;
+    module.add_class::<Memory>()?;
+    module.add_class::<Flags>()?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Runs every `test_*` function in `tests/test_m6502.py` in an embedded
+//! interpreter, so the Python tests run under `cargo test` without pytest.
+
+use std::ffi::CString;
+
+use m6502_py::m6502_module;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+const TESTS: &str = include_str!("test_m6502.py");
+
+#[test]
+fn python_tests_pass() {
+    pyo3::append_to_inittab!(m6502_module);
+    Python::attach(|py| {
+        let globals = PyDict::new(py);
+        py.run(&CString::new(TESTS).unwrap(), Some(&globals), None).unwrap();
+
+        let mut ran = 0;
+        let mut failures = Vec::new();
+        for (name, test) in globals.iter() {
+            let name: String = name.extract().unwrap();
+            if !name.starts_with("test_") {
+                continue;
+            }
+            ran += 1;
+            if let Err(err) = test.call0() {
+                let traceback = err.traceback(py).and_then(|traceback| traceback.format().ok()).unwrap_or_default();
+                failures.push(format!("{}: {}\n{}", name, err, traceback));
+            }
+        }
+        assert!(ran > 0, "no tests found");
+        assert!(failures.is_empty(), "{} of {} tests failed:\n{}", failures.len(), ran, failures.join("\n"));
+    });
+}
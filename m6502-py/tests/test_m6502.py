@@ -0,0 +1,166 @@
+"""Tests for the m6502 module. Run with pytest after `maturin develop`; `cargo
+test` runs them too, through tests/python.rs."""
+
+from m6502 import Cpu, Memory
+
+# Sums the bytes of a zero-terminated string at $0300 into $10
+SUM = bytes([
+    0xA0, 0x00,        # LDY #$00
+    0x84, 0x10,        # STY $10
+    0xB9, 0x00, 0x03,  # LDA $0300,Y
+    0xF0, 0x09,        # BEQ $0612
+    0x18,              # CLC
+    0x65, 0x10,        # ADC $10
+    0x85, 0x10,        # STA $10
+    0xC8,              # INY
+    0x4C, 0x04, 0x06,  # JMP $0604
+    0x60,              # RTS
+])
+
+
+def cpu_with(program, addr=0x0600):
+    cpu = Cpu()
+    cpu.memory.load(addr, program)
+    cpu.pc = addr
+    return cpu
+
+
+def test_registers_and_flags():
+    cpu = cpu_with(bytes([0xA9, 0x80]))  # LDA #$80
+    assert cpu.step() == 2
+    assert cpu.a == 0x80 and cpu.pc == 0x0602
+    assert cpu.flags.negative and not cpu.flags.zero
+    cpu.flags.carry = True
+    assert cpu.status & 0x01
+    cpu.status = 0x02
+    assert cpu.flags.zero and not cpu.flags.carry and not cpu.flags.negative
+    cpu.x, cpu.y, cpu.sp = 1, 2, 0xF0
+    assert (cpu.x, cpu.y, cpu.sp) == (1, 2, 0xF0)
+    assert cpu.cycles == 2
+    assert repr(cpu.flags) == "Flags(nv-bdiZc)"
+
+
+def test_memory_indexing_and_slicing():
+    memory = Memory()
+    assert len(memory) == 0x10000
+    memory[0x10] = 0x42
+    assert memory[0x10] == 0x42
+    memory[0x0200:0x0204] = b"\x01\x02\x03\x04"
+    assert memory[0x0200:0x0204] == b"\x01\x02\x03\x04"
+    assert memory[0x0200:0x0204:2] == b"\x01\x03"
+    assert memory[-1] == 0
+    memory.write_word(0xFFFC, 0x8000)
+    assert memory[0xFFFC:] == b"\x00\x80\x00\x00"
+    assert memory.read_word(0xFFFC) == 0x8000
+    for bad in (lambda: memory[0x10000], lambda: memory.__setitem__(0, 256),
+                lambda: memory.__setitem__(slice(0, 2), b"\x01")):
+        try:
+            bad()
+        except (IndexError, OverflowError, ValueError):
+            pass
+        else:
+            raise AssertionError("expected an error")
+
+
+def test_shared_memory_and_reset():
+    memory = Memory()
+    memory.write_word(0xFFFC, 0x0600)
+    memory[0x0600] = 0xE8  # INX
+    cpu = Cpu(memory)
+    cpu.reset()
+    assert cpu.pc == 0x0600
+    cpu.step()
+    assert cpu.x == 1
+    assert cpu.memory[0x0600] == 0xE8
+
+
+def test_run_until_returns_from_subroutine():
+    cpu = cpu_with(SUM)
+    cpu.memory[0x0300:0x0304] = bytes([1, 2, 3, 0])
+    # Call the routine the way a test harness would: return to a known address
+    cpu.memory.load(0x0700, bytes([0x20, 0x00, 0x06]))  # JSR $0600
+    cpu.pc = 0x0700
+    cycles = cpu.run_until(0x0703)
+    assert cpu.memory[0x10] == 6
+    assert cpu.sp == 0xFD
+    assert cycles == cpu.cycles
+
+
+def test_run_until_times_out():
+    cpu = cpu_with(bytes([0x4C, 0x00, 0x06]))  # JMP $0600
+    try:
+        cpu.run_until(0x1234, max_cycles=100)
+    except TimeoutError:
+        pass
+    else:
+        raise AssertionError("expected TimeoutError")
+
+
+def test_run_stops_on_jam():
+    cpu = cpu_with(bytes([0xE8, 0x02]))  # INX, JAM
+    cpu.run(1000)
+    assert cpu.jammed and cpu.x == 1
+    try:
+        cpu.run_until(0x1234)
+    except RuntimeError:
+        pass
+    else:
+        raise AssertionError("expected RuntimeError")
+
+
+def test_breakpoints():
+    cpu = cpu_with(SUM)
+    cpu.memory[0x0300:0x0303] = bytes([5, 6, 0])
+    cpu.add_breakpoint(0x060E)  # INY
+    cpu.run(1000)
+    assert cpu.pc == 0x060E and cpu.memory[0x10] == 5
+    # Resuming from a breakpoint runs past it
+    cpu.run(1000)
+    assert cpu.pc == 0x060E and cpu.memory[0x10] == 11
+    assert cpu.breakpoints == [0x060E]
+    cpu.remove_breakpoint(0x060E)
+    assert cpu.breakpoints == []
+
+
+def test_breakpoint_callbacks():
+    cpu = cpu_with(SUM)
+    cpu.memory[0x0300:0x0304] = bytes([1, 2, 3, 0])
+    seen = []
+
+    def record(cpu):
+        seen.append(cpu.a)
+
+    def stop_on_zero(cpu):
+        return cpu.a == 0
+
+    cpu.add_breakpoint(0x0607, record)  # BEQ
+    cpu.add_breakpoint(0x0612, stop_on_zero)  # RTS
+    cpu.run(10000)
+    assert seen == [1, 2, 3, 0]
+    assert cpu.pc == 0x0612
+
+
+def test_breakpoint_callback_can_change_state():
+    cpu = cpu_with(bytes([0xE8, 0x4C, 0x00, 0x06]))  # INX, JMP $0600
+
+    def skip_ahead(cpu):
+        cpu.x = 0x7F
+        cpu.clear_breakpoints()
+
+    cpu.add_breakpoint(0x0600, skip_ahead)
+    assert cpu.run(2 + 3 + 2) == 7
+    assert cpu.x == 0x80
+
+
+def test_variants():
+    cpu = Cpu(variant="65c02")
+    cpu.memory.load(0x0600, bytes([0x1A]))  # INC A
+    cpu.pc = 0x0600
+    cpu.step()
+    assert cpu.a == 1
+    try:
+        Cpu(variant="z80")
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("expected ValueError")
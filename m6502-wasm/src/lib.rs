@@ -0,0 +1,138 @@
+//! JavaScript bindings for the m6502 emulator, built for `wasm32-unknown-unknown`
+//! with wasm-bindgen.
+//!
+//! ```js
+//! const emulator = new Emulator();
+//! emulator.loadProgram(new Uint8Array([0xA9, 0x42, 0x8D, 0x00, 0x02]), 0x0600);
+//! emulator.step();
+//! console.log(emulator.disassemble(emulator.pc), emulator.a);
+//! ```
+//!
+//! The tests run natively with `cargo test`, and under node with
+//! `cargo test -p m6502-wasm --target wasm32-unknown-unknown`, which needs
+//! `wasm-bindgen-test-runner` from wasm-bindgen-cli on the PATH.
+
+use m6502::{Byte, Cpu, Memory, Word};
+use wasm_bindgen::prelude::*;
+
+/// An NMOS 6502 with 64K of RAM. Undocumented opcodes are enabled, so any
+/// program runs instead of stopping the module on an unknown opcode.
+#[wasm_bindgen]
+pub struct Emulator {
+    cpu: Cpu,
+    memory: Box<Memory>,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Emulator {
+        let mut cpu = Cpu::new();
+        cpu.illegal_opcodes = true;
+        Emulator { cpu, memory: Box::new(Memory { data: [0; 1024 * 64] }) }
+    }
+
+    /// Copies `program` to `addr`, wrapping at the end of memory, and points PC at it.
+    #[wasm_bindgen(js_name = loadProgram)]
+    pub fn load_program(&mut self, program: &[u8], addr: Word) {
+        for (offset, &byte) in program.iter().enumerate() {
+            self.memory.data[addr.wrapping_add(offset as Word) as usize] = byte;
+        }
+        self.cpu.pc = addr;
+    }
+
+    /// Clears the registers and starts from the reset vector at $FFFC.
+    pub fn reset(&mut self) {
+        self.cpu.reset(&mut self.memory);
+    }
+
+    /// Runs one instruction and returns the cycles it took.
+    pub fn step(&mut self) -> u32 {
+        self.cpu.execute(1, &mut self.memory) as u32
+    }
+
+    /// Runs whole instructions until at least `cycles` have passed and returns
+    /// the cycles used.
+    pub fn run(&mut self, cycles: u32) -> u32 {
+        self.cpu.execute(cycles.min(i32::MAX as u32) as i32, &mut self.memory) as u32
+    }
+
+    // --- Registers ---
+
+    #[wasm_bindgen(getter)]
+    pub fn pc(&self) -> Word {
+        self.cpu.pc
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_pc(&mut self, pc: Word) {
+        self.cpu.pc = pc;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn a(&self) -> Byte {
+        self.cpu.reg_a
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> Byte {
+        self.cpu.reg_x
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> Byte {
+        self.cpu.reg_y
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn sp(&self) -> Byte {
+        self.cpu.sp
+    }
+
+    /// The status register as pushed by an interrupt (B clear).
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> Byte {
+        self.cpu.status.to_byte(false)
+    }
+
+    /// Set once a JAM opcode has locked up the CPU; `reset` clears it.
+    #[wasm_bindgen(getter)]
+    pub fn jammed(&self) -> bool {
+        self.cpu.jammed
+    }
+
+    // --- Memory ---
+
+    pub fn read(&self, addr: Word) -> Byte {
+        self.memory.data[addr as usize]
+    }
+
+    pub fn write(&mut self, addr: Word, value: Byte) {
+        self.memory.data[addr as usize] = value;
+    }
+
+    /// `len` bytes from `addr`, wrapping at the end of memory.
+    #[wasm_bindgen(js_name = readRange)]
+    pub fn read_range(&self, addr: Word, len: u32) -> Vec<u8> {
+        (0..len.min(0x10000)).map(|offset| self.memory.data[addr.wrapping_add(offset as Word) as usize]).collect()
+    }
+
+    // --- Disassembly ---
+
+    /// The instruction at `addr`, e.g. `LDA $0300,X`.
+    pub fn disassemble(&self, addr: Word) -> String {
+        m6502::disassemble(&self.memory, addr).0
+    }
+
+    /// Length in bytes of the instruction at `addr`.
+    #[wasm_bindgen(js_name = instructionLength)]
+    pub fn instruction_length(&self, addr: Word) -> Word {
+        m6502::disassemble(&self.memory, addr).1
+    }
+}
@@ -0,0 +1,72 @@
+//! Exercises the exported API. Runs natively under `cargo test` and in node
+//! under `cargo test --target wasm32-unknown-unknown`.
+
+use m6502_wasm::Emulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const PROGRAM: &[u8] = &[
+    0xA2, 0x05,       // LDX #$05
+    0x8A,             // TXA
+    0x9D, 0x00, 0x02, // STA $0200,X
+    0xCA,             // DEX
+    0xD0, 0xF9,       // BNE $0602
+    0x00,             // BRK
+];
+
+#[wasm_bindgen_test(unsupported = test)]
+fn loads_and_steps_a_program() {
+    let mut emulator = Emulator::new();
+    emulator.load_program(PROGRAM, 0x0600);
+    assert_eq!(emulator.pc(), 0x0600);
+    assert_eq!(emulator.step(), 2);
+    assert_eq!((emulator.pc(), emulator.x()), (0x0602, 0x05));
+    assert_eq!(emulator.step(), 2);
+    assert_eq!(emulator.a(), 0x05);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn runs_cycles_and_reads_memory() {
+    let mut emulator = Emulator::new();
+    emulator.load_program(PROGRAM, 0x0600);
+    let sp = emulator.sp();
+    // LDX, then five passes of TXA, STA, DEX and BNE, the last one not taken
+    let cycles = 2 + 5 * (2 + 5 + 2 + 3) - 1;
+    assert_eq!(emulator.run(cycles), cycles);
+    assert_eq!(emulator.pc(), 0x0609);
+    assert_eq!(emulator.read_range(0x0201, 5), vec![1, 2, 3, 4, 5]);
+    assert_eq!(emulator.status() & 0x02, 0x02, "Z set by the final DEX");
+    assert_eq!(emulator.sp(), sp);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn writes_memory_and_resets() {
+    let mut emulator = Emulator::new();
+    emulator.write(0xFFFC, 0x00);
+    emulator.write(0xFFFD, 0x80);
+    emulator.write(0x8000, 0xE8); // INX
+    assert_eq!(emulator.read(0x8000), 0xE8);
+    emulator.reset();
+    assert_eq!(emulator.pc(), 0x8000);
+    emulator.step();
+    assert_eq!(emulator.x(), 1);
+    emulator.set_pc(0x1234);
+    assert_eq!(emulator.pc(), 0x1234);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn disassembles() {
+    let mut emulator = Emulator::new();
+    emulator.load_program(PROGRAM, 0x0600);
+    assert_eq!(emulator.disassemble(0x0603), "STA $0200,X");
+    assert_eq!(emulator.instruction_length(0x0603), 3);
+    assert_eq!(emulator.disassemble(0x0607), "BNE $0602");
+    assert_eq!(emulator.instruction_length(0x0609), 1);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn locks_up_on_jam() {
+    let mut emulator = Emulator::new();
+    emulator.load_program(&[0x02], 0x0600);
+    emulator.step();
+    assert!(emulator.jammed());
+}
@@ -0,0 +1,44 @@
+//! Cost of opcode dispatch in `Cpu::execute`, measured on Klaus Dormann's
+//! functional and decimal tests, which between them use every documented opcode.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use m6502::{Cpu, Memory, Word};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const FUNCTIONAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_functional_test.bin");
+const DECIMAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_decimal_test.bin");
+
+const CYCLES: i32 = 1_000_000;
+
+/// Runs `CYCLES` of the program from a freshly loaded image on each iteration;
+/// only `execute` is timed.
+fn bench_program(c: &mut Criterion, name: &str, image: &[u8], load_addr: usize, start: Word) {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+    group.bench_function(name, |b| {
+        b.iter_custom(|iterations| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iterations {
+                memory.initialise();
+                memory.data[load_addr..load_addr + image.len()].copy_from_slice(image);
+                let mut cpu = Cpu::new();
+                cpu.pc = start;
+                let started = Instant::now();
+                black_box(cpu.execute(CYCLES, &mut memory));
+                elapsed += started.elapsed();
+            }
+            elapsed
+        })
+    });
+    group.finish();
+}
+
+fn dispatch(c: &mut Criterion) {
+    bench_program(c, "functional_test", FUNCTIONAL_TEST, 0x0000, 0x0400);
+    bench_program(c, "decimal_test", DECIMAL_TEST, 0x0200, 0x0200);
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,220 @@
+//! Emulation speed of `Cpu::execute` on small representative workloads.
+//!
+//! Each workload is timed twice: the `cycles` group reports emulated cycles per
+//! second, so its Melem/s reads directly as emulated MHz, and the `instructions`
+//! group reports instructions per second. The `cached_cycles` group runs the same
+//! workloads through `Cpu::execute_cached` with a warm block cache.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use m6502::{BlockCache, Byte, Cpu, Memory, Word};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Emulated cycles per iteration.
+const CYCLES: i32 = 1_000_000;
+
+const START: Word = 0x0200;
+
+struct Workload {
+    name: &'static str,
+    /// Code and data, as `(address, bytes)`.
+    segments: &'static [(Word, &'static [Byte])],
+    /// Cycles between IRQs, if the workload asserts IRQ.
+    irq_every: Option<i32>,
+}
+
+const WORKLOADS: &[Workload] = &[
+    // DEX/BNE countdown from 256
+    Workload {
+        name: "tight_loop",
+        segments: &[(START, &[
+            0xA2, 0x00,       // LDX #$00
+            0xCA,             // DEX
+            0xD0, 0xFD,       // BNE $0202
+            0x4C, 0x00, 0x02, // JMP $0200
+        ])],
+        irq_every: None,
+    },
+    // Copies 16 pages from $1000 to $4000 through (zp),Y pointers
+    Workload {
+        name: "memcpy",
+        segments: &[(START, &[
+            0xA9, 0x00,       // LDA #$00
+            0x85, 0xFB,       // STA $FB
+            0x85, 0xFD,       // STA $FD
+            0xA9, 0x10,       // LDA #$10
+            0x85, 0xFC,       // STA $FC
+            0xA9, 0x40,       // LDA #$40
+            0x85, 0xFE,       // STA $FE
+            0xA2, 0x10,       // LDX #$10
+            0xA0, 0x00,       // LDY #$00
+            0xB1, 0xFB,       // LDA ($FB),Y
+            0x91, 0xFD,       // STA ($FD),Y
+            0xC8,             // INY
+            0xD0, 0xF9,       // BNE $0212
+            0xE6, 0xFC,       // INC $FC
+            0xE6, 0xFE,       // INC $FE
+            0xCA,             // DEX
+            0xD0, 0xF0,       // BNE $0210
+            0x4C, 0x00, 0x02, // JMP $0200
+        ])],
+        irq_every: None,
+    },
+    // Adds $1234 to one 16-bit BCD counter and subtracts it from another
+    Workload {
+        name: "bcd",
+        segments: &[(START, &[
+            0xF8,             // SED
+            0x18,             // CLC
+            0xA5, 0x10,       // LDA $10
+            0x69, 0x34,       // ADC #$34
+            0x85, 0x10,       // STA $10
+            0xA5, 0x11,       // LDA $11
+            0x69, 0x12,       // ADC #$12
+            0x85, 0x11,       // STA $11
+            0x38,             // SEC
+            0xA5, 0x12,       // LDA $12
+            0xE9, 0x34,       // SBC #$34
+            0x85, 0x12,       // STA $12
+            0xA5, 0x13,       // LDA $13
+            0xE9, 0x12,       // SBC #$12
+            0x85, 0x13,       // STA $13
+            0x4C, 0x01, 0x02, // JMP $0201
+        ])],
+        irq_every: None,
+    },
+    // Two levels of short subroutines
+    Workload {
+        name: "jsr_heavy",
+        segments: &[
+            (START, &[
+                0x20, 0x10, 0x02, // JSR $0210
+                0x4C, 0x00, 0x02, // JMP $0200
+            ]),
+            (0x0210, &[
+                0x20, 0x20, 0x02, // JSR $0220
+                0x20, 0x20, 0x02, // JSR $0220
+                0x60,             // RTS
+            ]),
+            (0x0220, &[
+                0x48,             // PHA
+                0xE8,             // INX
+                0x68,             // PLA
+                0x60,             // RTS
+            ]),
+        ],
+        irq_every: None,
+    },
+    // A counting loop interrupted every 100 cycles by a handler that saves A and X
+    Workload {
+        name: "interrupt_storm",
+        segments: &[
+            (START, &[
+                0x58,             // CLI
+                0xE8,             // INX
+                0x4C, 0x01, 0x02, // JMP $0201
+            ]),
+            (0x0300, &[
+                0x48,             // PHA
+                0x8A,             // TXA
+                0x48,             // PHA
+                0xE6, 0x20,       // INC $20
+                0x68,             // PLA
+                0xAA,             // TAX
+                0x68,             // PLA
+                0x40,             // RTI
+            ]),
+            (0xFFFE, &[0x00, 0x03]),
+        ],
+        irq_every: Some(100),
+    },
+];
+
+impl Workload {
+    fn load(&self, memory: &mut Memory) -> Cpu {
+        memory.initialise();
+        for &(addr, bytes) in self.segments {
+            let addr = addr as usize;
+            memory.data[addr..addr + bytes.len()].copy_from_slice(bytes);
+        }
+        let mut cpu = Cpu::new();
+        cpu.pc = START;
+        cpu
+    }
+
+    /// Runs `CYCLES` cycles, calling `step` with the cycles it may use until the
+    /// next IRQ, and returns the cycles actually used.
+    fn run(&self, cpu: &mut Cpu, memory: &mut Memory, mut step: impl FnMut(&mut Cpu, &mut Memory, i32) -> i32) -> i32 {
+        let slice = self.irq_every.unwrap_or(CYCLES);
+        let mut used = 0;
+        while used < CYCLES {
+            used += step(cpu, memory, slice.min(CYCLES - used));
+            if self.irq_every.is_some() {
+                used += cpu.irq(memory);
+            }
+        }
+        used
+    }
+
+    /// Instructions executed in one run; the workloads are deterministic, so
+    /// this is the same on every iteration.
+    fn instructions(&self, memory: &mut Memory) -> u64 {
+        let mut cpu = self.load(memory);
+        let mut instructions = 0;
+        self.run(&mut cpu, memory, |cpu, memory, budget| {
+            let mut used = 0;
+            while used < budget {
+                used += cpu.execute(1, memory);
+                instructions += 1;
+            }
+            used
+        });
+        instructions
+    }
+}
+
+/// Times `iterations` runs of `workload`, through `execute_cached` if a cache is given.
+fn time_runs(workload: &Workload, memory: &mut Memory, iterations: u64, mut cache: Option<&mut BlockCache>) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..iterations {
+        let mut cpu = workload.load(memory);
+        if let Some(cache) = cache.as_deref_mut() {
+            cache.invalidate(0x0000, 0xFFFF);
+        }
+        let started = Instant::now();
+        black_box(workload.run(&mut cpu, memory, |cpu, memory, budget| match cache.as_deref_mut() {
+            Some(cache) => cpu.execute_cached(budget, memory, cache),
+            None => cpu.execute(budget, memory),
+        }));
+        elapsed += started.elapsed();
+    }
+    elapsed
+}
+
+fn throughput(c: &mut Criterion) {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    let mut cache = BlockCache::new();
+    for workload in WORKLOADS {
+        let instructions = workload.instructions(&mut memory);
+        let cycles = {
+            let mut cpu = workload.load(&mut memory);
+            workload.run(&mut cpu, &mut memory, |cpu, memory, budget| cpu.execute(budget, memory))
+        };
+        for (group, elements, cached) in
+            [("cycles", cycles as u64, false), ("instructions", instructions, false), ("cached_cycles", cycles as u64, true)]
+        {
+            let mut group = c.benchmark_group(group);
+            group.throughput(Throughput::Elements(elements));
+            group.bench_function(BenchmarkId::from_parameter(workload.name), |b| {
+                b.iter_custom(|iterations| {
+                    let cache = if cached { Some(&mut cache) } else { None };
+                    time_runs(workload, &mut memory, iterations, cache)
+                })
+            });
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
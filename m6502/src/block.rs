@@ -0,0 +1,485 @@
+use crate::dispatch::{HANDLERS, Handler};
+use crate::instructions::*;
+use crate::trace::{Mode, opcode_info};
+use crate::{Byte, Cpu, MAX_MEM, Memory, Word};
+
+/// Most instructions decoded into one block.
+const MAX_BLOCK_LEN: usize = 64;
+
+/// Most cycles any documented instruction takes.
+const MAX_OP_CYCLES: i32 = 7;
+
+/// Instructions that leave for an address only known when they run. `JMP` and
+/// `JSR` to an absolute address, and `RTS` from a subroutine the block called,
+/// are followed instead.
+const ENDS_BLOCK: &[&str] = &["JMP", "RTS", "RTI", "BRK"];
+
+/// Instructions that store to their operand, unless it is the accumulator.
+const STORES: &[&str] = &["STA", "STX", "STY", "INC", "DEC", "ASL", "LSR", "ROL", "ROR"];
+
+/// Undocumented instructions that store to their operand.
+const UNDOCUMENTED_STORES: &[&str] = &["SLO", "RLA", "SRE", "RRA", "DCP", "ISC", "SAX"];
+
+/// Undocumented stores whose high address byte is replaced by the stored value
+/// when indexing crosses a page.
+const UNSTABLE_STORES: &[&str] = &["SHA", "SHX", "SHY", "TAS"];
+
+/// Where an instruction writes to memory.
+#[derive(Clone, Copy)]
+enum Writes {
+    Nothing,
+    /// Pushes this many bytes.
+    Stack(u8),
+    /// Stores to the operand's effective address.
+    Operand(Mode),
+    /// Stores through the 65C02's `(zp)` pointer.
+    ZeroPageIndirect,
+    /// May store anywhere.
+    Anywhere,
+}
+
+impl Writes {
+    fn of(mnemonic: &str, mode: Mode) -> Writes {
+        match mnemonic {
+            "PHA" | "PHP" => Writes::Stack(1),
+            "JSR" => Writes::Stack(2),
+            "BRK" => Writes::Stack(3),
+            _ if STORES.contains(&mnemonic) && mode != Mode::Accumulator => Writes::Operand(mode),
+            _ => Writes::Nothing,
+        }
+    }
+
+    /// Where an opcode without a table handler writes, which is either a 65C02
+    /// instruction or an undocumented NMOS one.
+    fn of_fallback(cpu: &Cpu, opcode: Byte) -> Writes {
+        if cpu.variant.is_cmos() {
+            return match opcode {
+                INS_PHX | INS_PHY => Writes::Stack(1),
+                INS_STZ_ZP | INS_TSB_ZP | INS_TRB_ZP => Writes::Operand(Mode::ZeroPage),
+                INS_STZ_ZPX => Writes::Operand(Mode::ZeroPageX),
+                INS_STZ_ABS | INS_TSB_ABS | INS_TRB_ABS => Writes::Operand(Mode::Absolute),
+                INS_STZ_ABSX => Writes::Operand(Mode::AbsoluteX),
+                INS_STA_INDZP => Writes::ZeroPageIndirect,
+                // RMBn/SMBn
+                _ if opcode & 0x0F == 0x07 && cpu.variant.has_bit_instructions() => Writes::Operand(Mode::ZeroPage),
+                _ => Writes::Nothing,
+            };
+        }
+        let (mnemonic, mode) = opcode_info(opcode);
+        if UNSTABLE_STORES.contains(&mnemonic) {
+            Writes::Anywhere
+        } else if UNDOCUMENTED_STORES.contains(&mnemonic) {
+            Writes::Operand(mode)
+        } else {
+            Writes::Nothing
+        }
+    }
+
+    /// Records in `code` the stores the instruction at PC is about to make.
+    /// Returns whether any of them lands on cached code.
+    #[inline(never)]
+    fn record(self, cpu: &Cpu, memory: &Memory, code: &mut Code) -> bool {
+        let byte = |addr: Word| memory.data[addr as usize] as Word;
+        let word = |addr: Word| byte(addr) | byte(addr.wrapping_add(1)) << 8;
+        let zero_page_word = |addr: Byte| byte(addr as Word) | byte(addr.wrapping_add(1) as Word) << 8;
+        let operand = cpu.pc.wrapping_add(1);
+        let target = match self {
+            Writes::Nothing => return false,
+            Writes::Anywhere => {
+                code.overwrite(0, code.written.len() - 1);
+                return true;
+            }
+            Writes::Stack(len) => {
+                return (0..len).fold(false, |hit, i| code.store(0x0100 | cpu.sp.wrapping_sub(i) as Word) | hit);
+            }
+            Writes::ZeroPageIndirect => zero_page_word(byte(operand) as Byte),
+            Writes::Operand(Mode::ZeroPage) => byte(operand),
+            Writes::Operand(Mode::ZeroPageX) => (byte(operand) as Byte).wrapping_add(cpu.reg_x) as Word,
+            Writes::Operand(Mode::ZeroPageY) => (byte(operand) as Byte).wrapping_add(cpu.reg_y) as Word,
+            Writes::Operand(Mode::AbsoluteX) => word(operand).wrapping_add(cpu.reg_x as Word),
+            Writes::Operand(Mode::AbsoluteY) => word(operand).wrapping_add(cpu.reg_y as Word),
+            Writes::Operand(Mode::IndirectX) => zero_page_word((byte(operand) as Byte).wrapping_add(cpu.reg_x)),
+            Writes::Operand(Mode::IndirectY) => zero_page_word(byte(operand) as Byte).wrapping_add(cpu.reg_y as Word),
+            Writes::Operand(_) => word(operand),
+        };
+        code.store(target)
+    }
+}
+
+/// Flags in `Code::gates`, after one for each page saying whether it holds
+/// cached code and one for each page saying whether it or the next one does.
+const NEVER: u16 = 0x200;
+const ALWAYS: u16 = 0x201;
+/// Set in a gate whose low byte is the zero page address of a pointer, for the
+/// pages the pointer reaches.
+const POINTER: u16 = 0x400;
+
+impl Writes {
+    /// The gate to check before the instruction runs, from the pages its stores
+    /// can reach as far as is known when the block is decoded. The operand can't
+    /// change while the block runs, since a store into it ends the block.
+    fn gate(self, memory: &Memory, operand: usize) -> u16 {
+        let high = memory.data[(operand + 1) % MAX_MEM] as u16;
+        match self {
+            Writes::Nothing => NEVER,
+            Writes::Stack(_) => 0x01,
+            Writes::Operand(Mode::ZeroPage | Mode::ZeroPageX | Mode::ZeroPageY) => 0x00,
+            Writes::Operand(Mode::Absolute) => high,
+            Writes::Operand(Mode::AbsoluteX | Mode::AbsoluteY) => 0x100 | high,
+            Writes::Operand(Mode::IndirectY) => POINTER | memory.data[operand] as u16,
+            _ => ALWAYS,
+        }
+    }
+}
+
+/// A pre-decoded instruction.
+struct Op {
+    handler: Handler,
+    writes: Writes,
+    /// See `Writes::gate`.
+    gate: u16,
+    /// The address after the opcode, where PC points while the handler runs.
+    operand: Word,
+    /// Where the block goes on after the instruction. Anywhere else leaves it.
+    next: Word,
+}
+
+/// A trace of documented instructions from `start`, up to an instruction whose
+/// target is only known when it runs. It follows jumps and calls to absolute
+/// addresses, returns from the subroutines it called and backward branches,
+/// and goes on past forward branches. Execution leaves the block as soon as an
+/// instruction goes anywhere else.
+struct Block {
+    start: Word,
+    ops: Box<[Op]>,
+    /// The runs of memory the block was decoded from, as start and length.
+    ranges: Box<[(usize, usize)]>,
+    /// The bytes of `ranges`, one after another.
+    source: Box<[Byte]>,
+    /// The pages `ranges` lie on.
+    pages: Box<[usize]>,
+    /// The gates of the block's stores that don't depend on memory.
+    gates: Box<[u16]>,
+    /// Whether any of its stores have gates that do.
+    pointers: bool,
+    /// Stamp of `Code` when `source` last matched memory.
+    checked: u64,
+}
+
+impl Block {
+    /// Decodes the block starting at `start`, or `None` if the instruction there
+    /// has no table handler.
+    fn decode(memory: &Memory, start: Word, stamp: u64) -> Option<Block> {
+        let mut ops = Vec::new();
+        let mut ranges = Vec::new();
+        let mut returns = Vec::new();
+        let mut range_start = start as usize;
+        let mut addr = start as usize;
+        while ops.len() < MAX_BLOCK_LEN {
+            let opcode = memory.data[addr];
+            let Some(handler) = HANDLERS[opcode as usize] else { break };
+            let (mnemonic, mode) = opcode_info(opcode);
+            // Blocks never wrap around the end of memory
+            if addr + mode.len() as usize > MAX_MEM {
+                break;
+            }
+            let writes = Writes::of(mnemonic, mode);
+            let operand = (addr + 1) as Word;
+            addr += mode.len() as usize;
+            let next = match (mnemonic, mode) {
+                ("JMP" | "JSR", Mode::Absolute) => {
+                    if mnemonic == "JSR" {
+                        returns.push(addr);
+                    }
+                    memory.data[addr - 2] as usize | (memory.data[addr - 1] as usize) << 8
+                }
+                // A return from a subroutine the block called
+                ("RTS", _) if !returns.is_empty() => returns.pop().unwrap(),
+                // Backward branches are taken most of the time, since they close loops
+                (_, Mode::Relative) if memory.data[addr - 1] >= 0x80 => {
+                    (addr as Word).wrapping_add(memory.data[addr - 1] as i8 as Word) as usize
+                }
+                _ if ENDS_BLOCK.contains(&mnemonic) => {
+                    ops.push(Op { handler, writes, gate: writes.gate(memory, operand as usize), operand, next: addr as Word });
+                    break;
+                }
+                _ => addr,
+            };
+            ops.push(Op { handler, writes, gate: writes.gate(memory, operand as usize), operand, next: next as Word });
+            if next != addr {
+                ranges.push((range_start, addr - range_start));
+                (range_start, addr) = (next, next);
+            }
+        }
+        if ops.is_empty() {
+            return None;
+        }
+        if addr > range_start {
+            ranges.push((range_start, addr - range_start));
+        }
+        let source: Vec<Byte> = ranges.iter().flat_map(|&(start, len)| memory.data[start..start + len].iter().copied()).collect();
+        let mut pages: Vec<usize> = ranges.iter().flat_map(|&(start, len)| start >> 8..=(start + len - 1) >> 8).collect();
+        pages.sort_unstable();
+        pages.dedup();
+        let mut gates: Vec<u16> = ops.iter().map(|op| op.gate).filter(|&gate| gate != NEVER).collect();
+        let pointers = gates.iter().any(|&gate| gate & POINTER != 0);
+        gates.retain(|&gate| gate & POINTER == 0);
+        gates.sort_unstable();
+        gates.dedup();
+        Some(Block {
+            start,
+            ops: ops.into(),
+            ranges: ranges.into(),
+            source: source.into(),
+            pages: pages.into(),
+            gates: gates.into(),
+            pointers,
+            checked: stamp,
+        })
+    }
+
+    /// Whether the code in memory is still what the block was decoded from.
+    fn is_current(&self, memory: &Memory) -> bool {
+        let mut source = &self.source[..];
+        self.ranges.iter().all(|&(start, len)| {
+            let (bytes, rest) = source.split_at(len);
+            source = rest;
+            memory.data[start..start + len] == *bytes
+        })
+    }
+
+    /// Cycles after which the budget may have run out partway through the block.
+    fn max_cycles(&self) -> i32 {
+        self.ops.len() as i32 * MAX_OP_CYCLES
+    }
+}
+
+/// Which bytes the cached blocks were decoded from, and when each page last
+/// had one of them overwritten.
+struct Code {
+    bytes: Vec<bool>,
+    /// Whether a store to a page, either of two pages, or `NEVER` and `ALWAYS`,
+    /// may land on `bytes`. Sized so that any gate masked to ten bits is in range.
+    gates: [bool; 0x400],
+    /// For each page, the stamp of the last store into code on it.
+    written: Vec<u64>,
+    /// Counts the stores into code.
+    stamp: u64,
+}
+
+impl Code {
+    /// Records a store to `addr`. Returns whether it lands on cached code.
+    #[inline(always)]
+    fn store(&mut self, addr: Word) -> bool {
+        let hit = self.gates[addr as usize >> 8] && self.bytes[addr as usize];
+        if hit {
+            self.stamp += 1;
+            self.written[addr as usize >> 8] = self.stamp;
+        }
+        hit
+    }
+
+    /// Whether an instruction with this gate may store into cached code.
+    #[inline(always)]
+    fn open(&self, gate: u16, memory: &Memory) -> bool {
+        let index = if gate & POINTER != 0 {
+            0x100 | memory.data[(gate as Byte).wrapping_add(1) as usize] as usize
+        } else {
+            gate as usize
+        };
+        self.gates[index & 0x3FF]
+    }
+
+    /// Marks `len` bytes from `start` as cached code.
+    fn cache(&mut self, start: usize, len: usize) {
+        self.bytes[start..start + len].fill(true);
+        let (first, last) = (start >> 8, (start + len - 1) >> 8);
+        self.gates[first..=last].fill(true);
+        for page in 0..0x100 {
+            self.gates[0x100 | page] = self.gates[page] || self.gates[(page + 1) & 0xFF];
+        }
+    }
+
+    /// Marks nothing as cached code.
+    fn clear(&mut self) {
+        self.bytes.fill(false);
+        self.gates.fill(false);
+        self.gates[ALWAYS as usize] = true;
+    }
+
+    /// Marks every page from `first` to `last` as written.
+    fn overwrite(&mut self, first: usize, last: usize) {
+        self.stamp += 1;
+        self.written[first..=last].fill(self.stamp);
+    }
+
+    /// Whether nothing has been stored on the block's pages since it was checked.
+    fn unchanged(&self, block: &Block) -> bool {
+        block.pages.iter().all(|&page| self.written[page] <= block.checked)
+    }
+}
+
+/// Decoded blocks for `Cpu::execute_cached`, keyed by start address.
+///
+/// Stores that `execute_cached` makes into cached code mark that code's page as
+/// written. Blocks on a written page are checked against memory before they
+/// next run and decoded again if their code has changed, so self-modifying code
+/// behaves as it does under `execute`. Memory changed any other way, such as a
+/// program loaded over an old one or an interrupt pushed onto code, must be
+/// reported with `invalidate`.
+pub struct BlockCache {
+    blocks: Vec<Option<Box<Block>>>,
+    code: Code,
+    len: usize,
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        let mut code = Code { bytes: vec![false; MAX_MEM], gates: [false; 0x400], written: vec![0; MAX_MEM >> 8], stamp: 0 };
+        code.clear();
+        BlockCache { blocks: (0..MAX_MEM).map(|_| None).collect(), code, len: 0 }
+    }
+
+    /// Number of blocks currently cached.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reports that memory from `start` to `end` inclusive was written outside
+    /// `execute_cached`. Blocks on those pages are checked before they next run.
+    pub fn invalidate(&mut self, start: Word, end: Word) {
+        self.code.overwrite(start as usize >> 8, end as usize >> 8);
+    }
+
+    /// Drops every cached block.
+    pub fn clear(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+        self.code.clear();
+        self.len = 0;
+    }
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Brings the block in `slot` up to date before it runs at `start`: checks it
+/// against memory if code on its pages has been overwritten since it was last
+/// checked, and decodes it if it has changed or was never decoded.
+#[inline(never)]
+fn refresh<'a>(
+    slot: &'a mut Option<Box<Block>>,
+    code: &mut Code,
+    len: &mut usize,
+    memory: &Memory,
+    start: Word,
+) -> Option<&'a Block> {
+    let stale = match slot {
+        Some(block) if code.unchanged(block) || block.is_current(memory) => {
+            block.checked = code.stamp;
+            false
+        }
+        _ => true,
+    };
+    if stale {
+        *len -= slot.is_some() as usize;
+        *slot = Block::decode(memory, start, code.stamp).map(Box::new);
+        if let Some(block) = slot {
+            for &(start, len) in &block.ranges {
+                code.cache(start, len);
+            }
+            *len += 1;
+        }
+    }
+    slot.as_deref()
+}
+
+impl Cpu {
+    /// Same as `execute`, but runs pre-decoded code from `cache`, decoding each
+    /// block the first time it is reached. Registers, memory and cycle counts
+    /// are identical to `execute`; instructions outside the documented set are
+    /// handed to `execute` one at a time.
+    pub fn execute_cached(&mut self, mut cycles: i32, memory: &mut Memory, cache: &mut BlockCache) -> i32 {
+        let cycles_requested = cycles;
+        let BlockCache { blocks, code, len } = cache;
+        while cycles > 0 && !self.jammed && !self.waiting && !self.stopped {
+            let start = self.pc;
+            let slot = &mut blocks[start as usize];
+            let block = match slot {
+                // Nothing has overwritten code since the block was last checked
+                Some(block) if block.checked == code.stamp => Some(&**block),
+                _ => refresh(slot, code, len, memory, start),
+            };
+            match block {
+                Some(block) => self.run_block(block, memory, &mut cycles, code),
+                None => {
+                    Writes::of_fallback(self, memory.data[start as usize]).record(self, memory, code);
+                    cycles -= self.execute(1, memory);
+                }
+            }
+        }
+        cycles_requested - cycles
+    }
+
+    /// Runs `block` until it ends, leaves it or the cycles run out, and again
+    /// while it loops back to its start. A store into cached code also ends it,
+    /// so that what follows is checked against memory before it runs.
+    #[inline(always)]
+    fn run_block(&mut self, block: &Block, memory: &mut Memory, cycles: &mut i32, code: &mut Code) {
+        // Gates only open when blocks are decoded, so if none of the block's are
+        // open its stores need no checks until it has finished
+        if block.pointers || block.gates.iter().any(|&gate| code.gates[gate as usize]) {
+            self.run_ops::<true>(block, memory, cycles, code);
+        } else {
+            self.run_ops::<false>(block, memory, cycles, code);
+        }
+    }
+
+    #[inline(always)]
+    fn run_ops<const CHECK_STORES: bool>(&mut self, block: &Block, memory: &mut Memory, cycles: &mut i32, code: &mut Code) {
+        let stamp = code.stamp;
+        loop {
+            // With cycles for the whole block left, the budget can't run out inside it
+            let check_budget = *cycles < block.max_cycles();
+            for op in &block.ops {
+                if check_budget && *cycles <= 0 {
+                    return;
+                }
+                // The opcode fetch. Counting the handler's cycles from zero rather
+                // than from `cycles` keeps instructions from waiting on each other.
+                let (left, writes_code) = if CHECK_STORES && code.open(op.gate, memory) {
+                    self.run_store(op, memory, code)
+                } else {
+                    self.pc = op.operand;
+                    let mut left = -1;
+                    (op.handler)(self, memory, &mut left);
+                    (left, false)
+                };
+                *cycles += left;
+                if writes_code || self.pc != op.next {
+                    break;
+                }
+            }
+            if self.pc != block.start || code.stamp != stamp || *cycles <= 0 {
+                return;
+            }
+        }
+    }
+
+    /// Runs an instruction that may store into cached code. Returns the cycles
+    /// it took, negated, and whether it did.
+    #[inline(never)]
+    fn run_store(&mut self, op: &Op, memory: &mut Memory, code: &mut Code) -> (i32, bool) {
+        let writes_code = op.writes.record(self, memory, code);
+        self.pc = op.operand;
+        let mut left = -1;
+        (op.handler)(self, memory, &mut left);
+        (left, writes_code)
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{Byte, Memory, Word};
+
+/// Anything the CPU can read and write over its 16-bit address bus. Every call is
+/// one bus cycle, so implementations can hang memory-mapped devices off it.
+pub trait Bus {
+    fn read(&mut self, addr: Word) -> Byte;
+    fn write(&mut self, addr: Word, value: Byte);
+}
+
+impl Bus for Memory {
+    fn read(&mut self, addr: Word) -> Byte {
+        self.data[addr as usize]
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        self.data[addr as usize] = value;
+    }
+}
@@ -0,0 +1,213 @@
+use crate::instructions::*;
+use crate::{Byte, Cpu, Memory, Word};
+
+impl Cpu {
+    pub(crate) fn bit(&mut self, value: Byte) {
+        self.status.zero = (self.reg_a & value) == 0;
+        self.status.negative = (value & 0x80) != 0;
+        self.status.overflow = (value & 0x40) != 0;
+    }
+
+    /// TSB/TRB: Z is set from A & M, then the bits of A are set in or cleared from M.
+    fn test_and_modify_bits(&mut self, addr: Word, set: bool, memory: &mut Memory, cycles: &mut i32) {
+        let value = self.read_byte(memory, addr, cycles);
+        self.status.zero = (self.reg_a & value) == 0;
+        let result = if set { value | self.reg_a } else { value & !self.reg_a };
+        *cycles -= 1;
+        self.write_byte(memory, addr, result, cycles);
+    }
+
+    /// Executes an opcode that only exists on the 65C02. Opcodes the 65C02 leaves
+    /// unused are NOPs with the length and timing of the real chip.
+    pub(crate) fn execute_cmos(&mut self, opcode: Byte, memory: &mut Memory, cycles: &mut i32) {
+        match opcode {
+            INS_BRA => {
+                self.branch_if(true, true, memory, cycles);
+            }
+
+            // --- Stack ---
+            INS_PHX => {
+                *cycles -= 1;
+                self.push_byte_to_stack(memory, self.reg_x, cycles);
+            }
+            INS_PHY => {
+                *cycles -= 1;
+                self.push_byte_to_stack(memory, self.reg_y, cycles);
+            }
+            INS_PLX => {
+                *cycles -= 2;
+                self.reg_x = self.pull_byte_from_stack(memory, cycles);
+                self.set_zero_and_negative_flags(self.reg_x);
+            }
+            INS_PLY => {
+                *cycles -= 2;
+                self.reg_y = self.pull_byte_from_stack(memory, cycles);
+                self.set_zero_and_negative_flags(self.reg_y);
+            }
+
+            // --- Store Zero ---
+            INS_STZ_ZP => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.write_byte(memory, addr, 0, cycles);
+            }
+            INS_STZ_ZPX => {
+                let addr = self.addr_zero_page_x(cycles, memory);
+                self.write_byte(memory, addr, 0, cycles);
+            }
+            INS_STZ_ABS => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.write_byte(memory, addr, 0, cycles);
+            }
+            INS_STZ_ABSX => {
+                let addr = self.addr_absolute_x_5(cycles, memory);
+                self.write_byte(memory, addr, 0, cycles);
+            }
+
+            // --- Test and Set/Reset Bits ---
+            INS_TSB_ZP => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.test_and_modify_bits(addr, true, memory, cycles);
+            }
+            INS_TSB_ABS => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.test_and_modify_bits(addr, true, memory, cycles);
+            }
+            INS_TRB_ZP => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.test_and_modify_bits(addr, false, memory, cycles);
+            }
+            INS_TRB_ABS => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.test_and_modify_bits(addr, false, memory, cycles);
+            }
+
+            // --- Accumulator Increment/Decrement ---
+            INS_INC_A => {
+                self.reg_a = self.reg_a.wrapping_add(1);
+                self.set_zero_and_negative_flags(self.reg_a);
+                *cycles -= 1;
+            }
+            INS_DEC_A => {
+                self.reg_a = self.reg_a.wrapping_sub(1);
+                self.set_zero_and_negative_flags(self.reg_a);
+                *cycles -= 1;
+            }
+
+            // --- (zp) Addressing ---
+            INS_ORA_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                self.ora(addr, memory, cycles);
+            }
+            INS_AND_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                self.and(addr, memory, cycles);
+            }
+            INS_EOR_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                self.eor(addr, memory, cycles);
+            }
+            INS_ADC_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                let operand = self.read_byte(memory, addr, cycles);
+                self.adc(operand, cycles);
+            }
+            INS_STA_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                self.write_byte(memory, addr, self.reg_a, cycles);
+            }
+            INS_LDA_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                self.load_register_a(addr, memory, cycles);
+            }
+            INS_CMP_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                let operand = self.read_byte(memory, addr, cycles);
+                self.cmp(operand, self.reg_a);
+            }
+            INS_SBC_INDZP => {
+                let addr = self.addr_indirect_zp(cycles, memory);
+                let operand = self.read_byte(memory, addr, cycles);
+                self.sbc(operand, cycles);
+            }
+
+            // --- BIT ---
+            INS_BIT_IM => {
+                // Immediate BIT only affects Z
+                let value = self.fetch_byte(memory, cycles);
+                self.status.zero = (self.reg_a & value) == 0;
+            }
+            INS_BIT_ZPX => {
+                let addr = self.addr_zero_page_x(cycles, memory);
+                let value = self.read_byte(memory, addr, cycles);
+                self.bit(value);
+            }
+            INS_BIT_ABSX => {
+                let addr = self.addr_absolute_x(cycles, memory);
+                let value = self.read_byte(memory, addr, cycles);
+                self.bit(value);
+            }
+
+            INS_JMP_ABSX_IND => {
+                let ptr = self.fetch_word(memory, cycles).wrapping_add(self.reg_x as Word);
+                *cycles -= 1;
+                self.pc = self.read_word(memory, ptr, cycles);
+            }
+
+            // --- Rockwell/WDC bit instructions ---
+            _ if opcode & 0x0F == 0x07 && self.variant.has_bit_instructions() => {
+                // RMBn/SMBn
+                let bit = 1 << ((opcode >> 4) & 0x07);
+                let addr = self.addr_zero_page(cycles, memory);
+                let value = self.read_byte(memory, addr, cycles);
+                let result = if opcode & 0x80 != 0 { value | bit } else { value & !bit };
+                *cycles -= 1;
+                self.write_byte(memory, addr, result, cycles);
+            }
+            _ if opcode & 0x0F == 0x0F && self.variant.has_bit_instructions() => {
+                // BBRn/BBSn zp,rel
+                let bit = 1 << ((opcode >> 4) & 0x07);
+                let addr = self.addr_zero_page(cycles, memory);
+                let value = self.read_byte(memory, addr, cycles);
+                *cycles -= 1;
+                self.branch_if(value & bit != 0, opcode & 0x80 != 0, memory, cycles);
+            }
+
+            // --- WDC wait and stop ---
+            INS_WAI if self.variant.has_wait_and_stop() => {
+                *cycles -= 2;
+                self.waiting = true;
+            }
+            INS_STP if self.variant.has_wait_and_stop() => {
+                *cycles -= 2;
+                self.stopped = true;
+            }
+
+            // --- Unused opcodes ---
+            0x02 | 0x22 | 0x42 | 0x62 | 0x82 | 0xC2 | 0xE2 => {
+                self.fetch_byte(memory, cycles);
+            }
+            0x44 => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            0x54 | 0xD4 | 0xF4 => {
+                let addr = self.addr_zero_page_x(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            0x5C => {
+                self.fetch_word(memory, cycles);
+                *cycles -= 5;
+            }
+            0xDC | 0xFC => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            // x3, x7, xB and xF are single byte, single cycle NOPs
+            _ if opcode & 0x03 == 0x03 => {}
+
+            _ => {
+                panic!("Instruction {:02X} not implemented", opcode);
+            }
+        }
+    }
+}
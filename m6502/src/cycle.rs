@@ -0,0 +1,688 @@
+use crate::instructions::*;
+use crate::{Bus, Byte, Cpu, StatusFlags, Word};
+
+/// One clock of the cycle-stepped engine: the single bus access the NMOS 6502
+/// performs on that cycle. Dummy reads and writes are reported like any other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusAccess {
+    Read { addr: Word, value: Byte },
+    Write { addr: Word, value: Byte },
+}
+
+/// Progress of the instruction being executed by `tick()`.
+#[derive(Default, Clone, Copy, Debug)]
+pub(crate) struct CycleState {
+    opcode: Byte,
+    /// Cycle within the current instruction; 0 fetches the next opcode
+    step: u8,
+    /// First step of the data phase once the effective address is known
+    data_step: u8,
+    /// Address before indexing, and the effective address
+    base: Word,
+    addr: Word,
+    /// Zero page pointer of the indirect modes
+    ptr: Byte,
+    data: Byte,
+    /// Vector of the hardware interrupt being serviced instead of an opcode
+    interrupt: Option<Word>,
+    irq_line: bool,
+    nmi_pending: bool,
+    last: Option<BusAccess>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Implied,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,
+    IndirectY,
+    /// Stack, control flow and JAM opcodes, each with its own sequence
+    Special,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Modify,
+}
+
+/// Decodes addressing mode and access type from the aaabbbcc opcode layout,
+/// which also covers the undocumented opcodes.
+fn decode(opcode: Byte) -> (Mode, Access) {
+    let (a, b, c) = (opcode >> 5, (opcode >> 2) & 0x07, opcode & 0x03);
+    let special = matches!(opcode, INS_BRK | INS_JSR | INS_RTI | INS_RTS | INS_JMP_ABS | INS_JMP_IND
+        | INS_PHP | INS_PLP | INS_PHA | INS_PLA)
+        || (c == 0 && b == 4)
+        || INS_JAM.contains(&opcode);
+    if special {
+        return (Mode::Special, Access::Read);
+    }
+    // STX/LDX/SAX/LAX/SHX and friends index with Y instead of X
+    let index_y = (c == 2 || c == 3) && (a == 4 || a == 5);
+    let mode = match (c & 0x01, b) {
+        (1, 0) => Mode::IndirectX,
+        (1, 2) | (0, 0) => Mode::Immediate,
+        (1, 4) => Mode::IndirectY,
+        (1, 6) => Mode::AbsoluteY,
+        (0, 2) | (0, 6) => Mode::Implied,
+        (_, 1) => Mode::ZeroPage,
+        (_, 3) => Mode::Absolute,
+        (_, 5) if index_y => Mode::ZeroPageY,
+        (_, 5) => Mode::ZeroPageX,
+        _ if index_y => Mode::AbsoluteY,
+        _ => Mode::AbsoluteX,
+    };
+    let access = match c {
+        _ if mode == Mode::Immediate || mode == Mode::Implied => Access::Read,
+        1 if a == 4 => Access::Write,
+        1 => Access::Read,
+        _ if a == 4 => Access::Write,
+        _ if a == 5 => Access::Read,
+        0 => Access::Read,
+        _ => Access::Modify,
+    };
+    (mode, access)
+}
+
+/// Whether the NMOS 6502 documents `opcode`.
+fn is_documented(opcode: Byte) -> bool {
+    let (a, b, c) = (opcode >> 5, (opcode >> 2) & 0x07, opcode & 0x03);
+    match (c, b) {
+        (3, _) => false,
+        (1, 2) => a != 4,
+        (1, _) => true,
+        (2, 0) => a == 5,
+        (2, 4) => false,
+        (2, 6) => a == 4 || a == 5,
+        (2, 7) => a != 4,
+        (2, _) => true,
+        (0, 0) => a != 4,
+        (0, 1) => a == 1 || a >= 4,
+        (0, 3) => a != 0,
+        (0, 5) => a == 4 || a == 5,
+        (0, 7) => a == 5,
+        _ => true,
+    }
+}
+
+impl Cpu {
+    /// Runs a single clock cycle, performing exactly the bus access the NMOS 6502
+    /// does on that cycle, including dummy reads on indexed addressing and the
+    /// double write of read-modify-write instructions.
+    ///
+    /// Interrupts set with `set_irq_line()` and `trigger_nmi()` are polled at
+    /// instruction boundaries. Only the NMOS variant is modelled; mixing `tick()`
+    /// and `execute()` is only valid at an instruction boundary.
+    pub fn tick(&mut self, bus: &mut impl Bus) -> BusAccess {
+        assert!(!self.variant.is_cmos(), "tick() only models the NMOS 6502");
+        if self.jammed {
+            // A jammed CPU keeps reading $FFFF until reset
+            self.bus_read(bus, 0xFFFF);
+        } else {
+            let step = self.cycle.step;
+            self.cycle.step += 1;
+            if step == 0 {
+                self.fetch_cycle(bus);
+            } else {
+                self.instruction_cycle(step, bus);
+            }
+        }
+        self.cycle.last.take().expect("every cycle accesses the bus")
+    }
+
+    /// Ticks until the current instruction completes. Returns the number of cycles.
+    pub fn tick_instruction(&mut self, bus: &mut impl Bus) -> i32 {
+        let mut cycles = 1;
+        self.tick(bus);
+        while !self.at_instruction_boundary() && !self.jammed {
+            self.tick(bus);
+            cycles += 1;
+        }
+        cycles
+    }
+
+    /// True when the next `tick()` fetches an opcode.
+    pub fn at_instruction_boundary(&self) -> bool {
+        self.cycle.step == 0
+    }
+
+    /// Drives the level-sensitive IRQ input for `tick()`.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.cycle.irq_line = asserted;
+    }
+
+    /// Latches an NMI edge for `tick()`; it is taken at the next instruction boundary.
+    pub fn trigger_nmi(&mut self) {
+        self.cycle.nmi_pending = true;
+    }
+
+    fn bus_read(&mut self, bus: &mut impl Bus, addr: Word) -> Byte {
+        let value = bus.read(addr);
+        self.cycle.last = Some(BusAccess::Read { addr, value });
+        value
+    }
+
+    fn bus_write(&mut self, bus: &mut impl Bus, addr: Word, value: Byte) {
+        bus.write(addr, value);
+        self.cycle.last = Some(BusAccess::Write { addr, value });
+    }
+
+    fn fetch_pc(&mut self, bus: &mut impl Bus) -> Byte {
+        let value = self.bus_read(bus, self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    fn push_cycle(&mut self, bus: &mut impl Bus, value: Byte) {
+        self.bus_write(bus, self.sp_to_address(), value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    fn done(&mut self) {
+        self.cycle.step = 0;
+        self.cycle.data_step = 0;
+        self.cycle.interrupt = None;
+    }
+
+    fn fetch_cycle(&mut self, bus: &mut impl Bus) {
+        let vector = if self.cycle.nmi_pending {
+            self.cycle.nmi_pending = false;
+            Some(0xFFFA)
+        } else if self.cycle.irq_line && !self.status.interrupt_disable {
+            Some(0xFFFE)
+        } else {
+            None
+        };
+        if let Some(vector) = vector {
+            // The opcode is read but discarded and PC is not incremented
+            self.bus_read(bus, self.pc);
+            self.cycle.opcode = INS_BRK;
+            self.cycle.interrupt = Some(vector);
+            return;
+        }
+        let opcode = self.fetch_pc(bus);
+        if !is_documented(opcode) && !self.illegal_opcodes {
+            panic!("Instruction {:02X} not implemented", opcode);
+        }
+        self.cycle.opcode = opcode;
+    }
+
+    fn instruction_cycle(&mut self, step: u8, bus: &mut impl Bus) {
+        let opcode = self.cycle.opcode;
+        let (mode, access) = decode(opcode);
+        if mode == Mode::Special {
+            self.special_cycle(opcode, step, bus);
+        } else if self.cycle.data_step != 0 {
+            self.data_cycle(access, step - self.cycle.data_step, bus);
+        } else {
+            self.address_cycle(mode, access, step, bus);
+        }
+    }
+
+    /// Cycles that compute the effective address. The data phase starts on the
+    /// cycle after the last one here.
+    fn address_cycle(&mut self, mode: Mode, access: Access, step: u8, bus: &mut impl Bus) {
+        match (mode, step) {
+            (Mode::Implied, _) => {
+                self.bus_read(bus, self.pc);
+                self.implied_op();
+                self.done();
+                return;
+            }
+            (Mode::Immediate, _) => {
+                let value = self.fetch_pc(bus);
+                self.read_op(value);
+                self.done();
+                return;
+            }
+            (Mode::ZeroPage, _) => {
+                self.cycle.addr = self.fetch_pc(bus) as Word;
+            }
+            (Mode::ZeroPageX | Mode::ZeroPageY | Mode::IndirectX, 1) => {
+                self.cycle.ptr = self.fetch_pc(bus);
+                return;
+            }
+            (Mode::ZeroPageX | Mode::ZeroPageY, _) => {
+                // Dummy read of the unindexed address while the index is added
+                self.bus_read(bus, self.cycle.ptr as Word);
+                let index = if mode == Mode::ZeroPageX { self.reg_x } else { self.reg_y };
+                self.cycle.addr = self.cycle.ptr.wrapping_add(index) as Word;
+            }
+            (Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY, 1) => {
+                self.cycle.base = self.fetch_pc(bus) as Word;
+                return;
+            }
+            (Mode::Absolute, _) => {
+                self.cycle.addr = self.cycle.base | (self.fetch_pc(bus) as Word) << 8;
+            }
+            (Mode::AbsoluteX | Mode::AbsoluteY, 2) => {
+                self.cycle.base |= (self.fetch_pc(bus) as Word) << 8;
+                return;
+            }
+            (Mode::IndirectX, 2) => {
+                self.bus_read(bus, self.cycle.ptr as Word);
+                self.cycle.ptr = self.cycle.ptr.wrapping_add(self.reg_x);
+                return;
+            }
+            (Mode::IndirectX, 3) => {
+                self.cycle.base = self.bus_read(bus, self.cycle.ptr as Word) as Word;
+                return;
+            }
+            (Mode::IndirectX, _) => {
+                let hi = self.bus_read(bus, self.cycle.ptr.wrapping_add(1) as Word) as Word;
+                self.cycle.addr = self.cycle.base | hi << 8;
+            }
+            (Mode::IndirectY, 1) => {
+                self.cycle.ptr = self.fetch_pc(bus);
+                return;
+            }
+            (Mode::IndirectY, 2) => {
+                self.cycle.base = self.bus_read(bus, self.cycle.ptr as Word) as Word;
+                return;
+            }
+            (Mode::IndirectY, 3) => {
+                let hi = self.bus_read(bus, self.cycle.ptr.wrapping_add(1) as Word) as Word;
+                self.cycle.base |= hi << 8;
+                return;
+            }
+            _ => {
+                // Indexed read from the address with the high byte not yet fixed.
+                // Reads without a page crossing use it and finish here.
+                let index = if mode == Mode::AbsoluteX { self.reg_x } else { self.reg_y };
+                let base = self.cycle.base;
+                let addr = base.wrapping_add(index as Word);
+                let unfixed = (base & 0xFF00) | (addr & 0x00FF);
+                let value = self.bus_read(bus, unfixed);
+                self.cycle.addr = addr;
+                if access == Access::Read && unfixed == addr {
+                    self.read_op(value);
+                    self.done();
+                    return;
+                }
+            }
+        }
+        self.cycle.data_step = step + 1;
+    }
+
+    fn data_cycle(&mut self, access: Access, step: u8, bus: &mut impl Bus) {
+        let addr = self.cycle.addr;
+        match (access, step) {
+            (Access::Read, _) => {
+                let value = self.bus_read(bus, addr);
+                self.read_op(value);
+                self.done();
+            }
+            (Access::Write, _) => {
+                let (addr, value) = self.store_value();
+                self.bus_write(bus, addr, value);
+                self.done();
+            }
+            (Access::Modify, 0) => {
+                self.cycle.data = self.bus_read(bus, addr);
+            }
+            (Access::Modify, 1) => {
+                // The unmodified value is written back while the ALU works
+                self.bus_write(bus, addr, self.cycle.data);
+                self.cycle.data = self.modify_op(self.cycle.data);
+            }
+            (Access::Modify, _) => {
+                self.bus_write(bus, addr, self.cycle.data);
+                self.done();
+            }
+        }
+    }
+
+    fn special_cycle(&mut self, opcode: Byte, step: u8, bus: &mut impl Bus) {
+        match opcode {
+            INS_BRK => self.interrupt_cycle(step, bus),
+            INS_JSR => match step {
+                1 => self.cycle.base = self.fetch_pc(bus) as Word,
+                2 => {
+                    self.bus_read(bus, self.sp_to_address());
+                }
+                3 => self.push_cycle(bus, (self.pc >> 8) as Byte),
+                4 => self.push_cycle(bus, self.pc as Byte),
+                _ => {
+                    let hi = self.bus_read(bus, self.pc) as Word;
+                    self.pc = self.cycle.base | hi << 8;
+                    self.done();
+                }
+            },
+            INS_RTS => match step {
+                1 => {
+                    self.bus_read(bus, self.pc);
+                }
+                2 => {
+                    self.bus_read(bus, self.sp_to_address());
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                3 => {
+                    self.cycle.base = self.bus_read(bus, self.sp_to_address()) as Word;
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                4 => {
+                    let hi = self.bus_read(bus, self.sp_to_address()) as Word;
+                    self.pc = self.cycle.base | hi << 8;
+                }
+                _ => {
+                    // The pulled address points at the last byte of the JSR
+                    self.bus_read(bus, self.pc);
+                    self.pc = self.pc.wrapping_add(1);
+                    self.done();
+                }
+            },
+            INS_RTI => match step {
+                1 => {
+                    self.bus_read(bus, self.pc);
+                }
+                2 => {
+                    self.bus_read(bus, self.sp_to_address());
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                3 => {
+                    let status = self.bus_read(bus, self.sp_to_address());
+                    self.status = StatusFlags::from_byte(status);
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                4 => {
+                    self.cycle.base = self.bus_read(bus, self.sp_to_address()) as Word;
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                _ => {
+                    let hi = self.bus_read(bus, self.sp_to_address()) as Word;
+                    self.pc = self.cycle.base | hi << 8;
+                    self.done();
+                }
+            },
+            INS_PHA | INS_PHP => match step {
+                1 => {
+                    self.bus_read(bus, self.pc);
+                }
+                _ => {
+                    let value = if opcode == INS_PHA { self.reg_a } else { self.status.to_byte(true) };
+                    self.push_cycle(bus, value);
+                    self.done();
+                }
+            },
+            INS_PLA | INS_PLP => match step {
+                1 => {
+                    self.bus_read(bus, self.pc);
+                }
+                2 => {
+                    self.bus_read(bus, self.sp_to_address());
+                    self.sp = self.sp.wrapping_add(1);
+                }
+                _ => {
+                    let value = self.bus_read(bus, self.sp_to_address());
+                    if opcode == INS_PLA {
+                        self.reg_a = value;
+                        self.set_zero_and_negative_flags(value);
+                    } else {
+                        self.status = StatusFlags::from_byte(value);
+                    }
+                    self.done();
+                }
+            },
+            INS_JMP_ABS => match step {
+                1 => self.cycle.base = self.fetch_pc(bus) as Word,
+                _ => {
+                    let hi = self.fetch_pc(bus) as Word;
+                    self.pc = self.cycle.base | hi << 8;
+                    self.done();
+                }
+            },
+            INS_JMP_IND => match step {
+                1 => self.cycle.base = self.fetch_pc(bus) as Word,
+                2 => self.cycle.base |= (self.fetch_pc(bus) as Word) << 8,
+                3 => self.cycle.data = self.bus_read(bus, self.cycle.base),
+                _ => {
+                    // The pointer high byte is read without carrying into the next page
+                    let ptr = self.cycle.base;
+                    let hi_addr = (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF);
+                    let hi = self.bus_read(bus, hi_addr) as Word;
+                    self.pc = self.cycle.data as Word | hi << 8;
+                    self.done();
+                }
+            },
+            _ if INS_JAM.contains(&opcode) => {
+                self.bus_read(bus, self.pc);
+                self.pc = self.pc.wrapping_sub(1);
+                self.jammed = true;
+                self.done();
+            }
+            _ => self.branch_cycle(opcode, step, bus),
+        }
+    }
+
+    /// BRK and the hardware interrupt sequence share their last six cycles.
+    fn interrupt_cycle(&mut self, step: u8, bus: &mut impl Bus) {
+        let vector = self.cycle.interrupt.unwrap_or(0xFFFE);
+        match step {
+            1 if self.cycle.interrupt.is_some() => {
+                self.bus_read(bus, self.pc);
+            }
+            1 => {
+                // BRK skips its signature byte
+                self.fetch_pc(bus);
+            }
+            2 => self.push_cycle(bus, (self.pc >> 8) as Byte),
+            3 => self.push_cycle(bus, self.pc as Byte),
+            4 => {
+                let status = self.status.to_byte(self.cycle.interrupt.is_none());
+                self.push_cycle(bus, status);
+            }
+            5 => {
+                self.cycle.data = self.bus_read(bus, vector);
+                self.status.interrupt_disable = true;
+            }
+            _ => {
+                let hi = self.bus_read(bus, vector.wrapping_add(1)) as Word;
+                self.pc = self.cycle.data as Word | hi << 8;
+                self.done();
+            }
+        }
+    }
+
+    fn branch_cycle(&mut self, opcode: Byte, step: u8, bus: &mut impl Bus) {
+        match step {
+            1 => {
+                self.cycle.data = self.fetch_pc(bus);
+                let flag = match opcode >> 6 {
+                    0 => self.status.negative,
+                    1 => self.status.overflow,
+                    2 => self.status.carry,
+                    _ => self.status.zero,
+                };
+                if flag != (opcode & 0x20 != 0) {
+                    self.done();
+                }
+            }
+            2 => {
+                // Dummy read of the next opcode while PCL is adjusted
+                self.bus_read(bus, self.pc);
+                let target = self.pc.wrapping_add(self.cycle.data as i8 as Word);
+                self.cycle.addr = target;
+                self.pc = (self.pc & 0xFF00) | (target & 0x00FF);
+                if self.pc == target {
+                    self.done();
+                }
+            }
+            _ => {
+                self.bus_read(bus, self.pc);
+                self.pc = self.cycle.addr;
+                self.done();
+            }
+        }
+    }
+
+    fn implied_op(&mut self) {
+        let mut cycles = 0;
+        match self.cycle.opcode {
+            INS_ASL => self.reg_a = self.asl(self.reg_a, &mut cycles),
+            INS_ROL => self.reg_a = self.rol(self.reg_a, &mut cycles),
+            INS_LSR => self.reg_a = self.lsr(self.reg_a, &mut cycles),
+            INS_ROR => self.reg_a = self.ror(self.reg_a, &mut cycles),
+            INS_TXA => {
+                self.reg_a = self.reg_x;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            INS_TAX => {
+                self.reg_x = self.reg_a;
+                self.set_zero_and_negative_flags(self.reg_x);
+            }
+            INS_TYA => {
+                self.reg_a = self.reg_y;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            INS_TAY => {
+                self.reg_y = self.reg_a;
+                self.set_zero_and_negative_flags(self.reg_y);
+            }
+            INS_TSX => {
+                self.reg_x = self.sp;
+                self.set_zero_and_negative_flags(self.reg_x);
+            }
+            INS_TXS => self.sp = self.reg_x,
+            INS_DEX => {
+                self.reg_x = self.reg_x.wrapping_sub(1);
+                self.set_zero_and_negative_flags(self.reg_x);
+            }
+            INS_INX => {
+                self.reg_x = self.reg_x.wrapping_add(1);
+                self.set_zero_and_negative_flags(self.reg_x);
+            }
+            INS_DEY => {
+                self.reg_y = self.reg_y.wrapping_sub(1);
+                self.set_zero_and_negative_flags(self.reg_y);
+            }
+            INS_INY => {
+                self.reg_y = self.reg_y.wrapping_add(1);
+                self.set_zero_and_negative_flags(self.reg_y);
+            }
+            INS_CLC => self.status.carry = false,
+            INS_SEC => self.status.carry = true,
+            INS_CLI => self.status.interrupt_disable = false,
+            INS_SEI => self.status.interrupt_disable = true,
+            INS_CLV => self.status.overflow = false,
+            INS_CLD => self.status.decimal_mode = false,
+            INS_SED => self.status.decimal_mode = true,
+            // NOP and the undocumented single byte NOPs
+            _ => {}
+        }
+    }
+
+    fn read_op(&mut self, value: Byte) {
+        let opcode = self.cycle.opcode;
+        let mut cycles = 0;
+        match (opcode & 0x03, opcode >> 5) {
+            (1, 0) => {
+                self.reg_a |= value;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            (1, 1) => {
+                self.reg_a &= value;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            (1, 2) => {
+                self.reg_a ^= value;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            (1, 3) => self.adc(value, &mut cycles),
+            (1, 5) => {
+                self.reg_a = value;
+                self.set_zero_and_negative_flags(value);
+            }
+            (1, 6) => self.cmp(value, self.reg_a),
+            (1, 7) => self.sbc(value, &mut cycles),
+            (2, 5) => {
+                self.reg_x = value;
+                self.set_zero_and_negative_flags(value);
+            }
+            (0, 1) if opcode != 0x34 && opcode != 0x3C => {
+                // BIT; 0x34/0x3C are undocumented NOPs here
+                self.status.zero = (self.reg_a & value) == 0;
+                self.status.negative = (value & 0x80) != 0;
+                self.status.overflow = (value & 0x40) != 0;
+            }
+            (0, 5) => {
+                self.reg_y = value;
+                self.set_zero_and_negative_flags(value);
+            }
+            // CPY/CPX; the zp,X and abs,X slots are undocumented NOPs
+            (0, 6) if opcode & 0x10 == 0 => self.cmp(value, self.reg_y),
+            (0, 7) if opcode & 0x10 == 0 => self.cmp(value, self.reg_x),
+            (3, _) if (opcode >> 2) & 0x07 == 2 => self.immediate_combo(opcode, value),
+            (3, 5) if opcode == INS_LAS_ABSY => {
+                let value = value & self.sp;
+                self.reg_a = value;
+                self.reg_x = value;
+                self.sp = value;
+                self.set_zero_and_negative_flags(value);
+            }
+            (3, 5) => {
+                // LAX
+                self.reg_a = value;
+                self.reg_x = value;
+                self.set_zero_and_negative_flags(value);
+            }
+            // Undocumented NOPs still perform their read
+            _ => {}
+        }
+    }
+
+    fn modify_op(&mut self, value: Byte) -> Byte {
+        let opcode = self.cycle.opcode;
+        let mut cycles = 0;
+        if opcode & 0x03 == 0x03 {
+            return self.rmw_combo(opcode, value);
+        }
+        let result = match opcode >> 5 {
+            0 => return self.asl(value, &mut cycles),
+            1 => return self.rol(value, &mut cycles),
+            2 => return self.lsr(value, &mut cycles),
+            3 => return self.ror(value, &mut cycles),
+            6 => value.wrapping_sub(1),
+            _ => value.wrapping_add(1),
+        };
+        self.set_zero_and_negative_flags(result);
+        result
+    }
+
+    /// Address and value of a write cycle. SHA, SHX, SHY and TAS AND the stored
+    /// value with the base high byte + 1, which also replaces the high byte of the
+    /// address when indexing crossed a page.
+    fn store_value(&mut self) -> (Word, Byte) {
+        let addr = self.cycle.addr;
+        let value = match self.cycle.opcode {
+            INS_SHA_ABSY | INS_SHA_INDY => self.reg_a & self.reg_x,
+            INS_SHX_ABSY => self.reg_x,
+            INS_SHY_ABSX => self.reg_y,
+            INS_TAS_ABSY => {
+                self.sp = self.reg_a & self.reg_x;
+                self.sp
+            }
+            opcode => {
+                return (addr, match opcode & 0x03 {
+                    0 => self.reg_y,
+                    1 => self.reg_a,
+                    2 => self.reg_x,
+                    _ => self.reg_a & self.reg_x,
+                });
+            }
+        };
+        let base = self.cycle.base;
+        let value = value & ((base >> 8) as Byte).wrapping_add(1);
+        if (base & 0xFF00) != (addr & 0xFF00) {
+            (((value as Word) << 8) | (addr & 0x00FF), value)
+        } else {
+            (addr, value)
+        }
+    }
+}
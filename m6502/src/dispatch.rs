@@ -0,0 +1,603 @@
+use crate::instructions::*;
+use crate::{Byte, Cpu, Memory, Word};
+
+/// Runs one documented instruction after its opcode has been fetched.
+pub(crate) type Handler = fn(&mut Cpu, &mut Memory, &mut i32);
+
+/// Handlers for the documented NMOS opcodes, indexed by opcode. Opcodes without
+/// an entry fall through to the CMOS or illegal opcode decoders.
+pub(crate) static HANDLERS: [Option<Handler>; 256] = {
+    let mut table: [Option<Handler>; 256] = [None; 256];
+
+    // --- Load ---
+    table[INS_LDA_IM as usize] = Some(lda::<Immediate>);
+    table[INS_LDA_ZP as usize] = Some(lda::<ZeroPage>);
+    table[INS_LDA_ZPX as usize] = Some(lda::<ZeroPageX>);
+    table[INS_LDA_ABS as usize] = Some(lda::<Absolute>);
+    table[INS_LDA_ABSX as usize] = Some(lda::<AbsoluteX>);
+    table[INS_LDA_ABSY as usize] = Some(lda::<AbsoluteY>);
+    table[INS_LDA_INDX as usize] = Some(lda::<IndirectX>);
+    table[INS_LDA_INDY as usize] = Some(lda::<IndirectY>);
+    table[INS_LDX_IM as usize] = Some(ldx::<Immediate>);
+    table[INS_LDX_ZP as usize] = Some(ldx::<ZeroPage>);
+    table[INS_LDX_ZPY as usize] = Some(ldx::<ZeroPageY>);
+    table[INS_LDX_ABS as usize] = Some(ldx::<Absolute>);
+    table[INS_LDX_ABSY as usize] = Some(ldx::<AbsoluteY>);
+    table[INS_LDY_IM as usize] = Some(ldy::<Immediate>);
+    table[INS_LDY_ZP as usize] = Some(ldy::<ZeroPage>);
+    table[INS_LDY_ZPX as usize] = Some(ldy::<ZeroPageX>);
+    table[INS_LDY_ABS as usize] = Some(ldy::<Absolute>);
+    table[INS_LDY_ABSX as usize] = Some(ldy::<AbsoluteX>);
+
+    // --- Store ---
+    table[INS_STA_ZP as usize] = Some(sta::<ZeroPage>);
+    table[INS_STA_ZPX as usize] = Some(sta::<ZeroPageX>);
+    table[INS_STA_ABS as usize] = Some(sta::<Absolute>);
+    table[INS_STA_ABSX as usize] = Some(sta::<AbsoluteXFixed>);
+    table[INS_STA_ABSY as usize] = Some(sta::<AbsoluteYFixed>);
+    table[INS_STA_INDX as usize] = Some(sta::<IndirectX>);
+    table[INS_STA_INDY as usize] = Some(sta::<IndirectYFixed>);
+    table[INS_STX_ZP as usize] = Some(stx::<ZeroPage>);
+    table[INS_STX_ZPY as usize] = Some(stx::<ZeroPageY>);
+    table[INS_STX_ABS as usize] = Some(stx::<Absolute>);
+    table[INS_STY_ZP as usize] = Some(sty::<ZeroPage>);
+    table[INS_STY_ZPX as usize] = Some(sty::<ZeroPageX>);
+    table[INS_STY_ABS as usize] = Some(sty::<Absolute>);
+
+    // --- Transfers and stack ---
+    table[INS_TAX as usize] = Some(tax);
+    table[INS_TAY as usize] = Some(tay);
+    table[INS_TXA as usize] = Some(txa);
+    table[INS_TYA as usize] = Some(tya);
+    table[INS_TSX as usize] = Some(tsx);
+    table[INS_TXS as usize] = Some(txs);
+    table[INS_PHA as usize] = Some(pha);
+    table[INS_PLA as usize] = Some(pla);
+    table[INS_PHP as usize] = Some(php);
+    table[INS_PLP as usize] = Some(plp);
+
+    // --- Jumps, calls and interrupts ---
+    table[INS_JMP_ABS as usize] = Some(jmp::<Absolute>);
+    table[INS_JMP_IND as usize] = Some(jmp::<Indirect>);
+    table[INS_JSR as usize] = Some(jsr);
+    table[INS_RTS as usize] = Some(rts);
+    table[INS_BRK as usize] = Some(brk);
+    table[INS_RTI as usize] = Some(rti);
+
+    // --- Branches ---
+    table[INS_BPL as usize] = Some(bpl);
+    table[INS_BMI as usize] = Some(bmi);
+    table[INS_BVC as usize] = Some(bvc);
+    table[INS_BVS as usize] = Some(bvs);
+    table[INS_BCC as usize] = Some(bcc);
+    table[INS_BCS as usize] = Some(bcs);
+    table[INS_BNE as usize] = Some(bne);
+    table[INS_BEQ as usize] = Some(beq);
+
+    // --- Logical ---
+    table[INS_AND_IM as usize] = Some(and::<Immediate>);
+    table[INS_AND_ZP as usize] = Some(and::<ZeroPage>);
+    table[INS_AND_ZPX as usize] = Some(and::<ZeroPageX>);
+    table[INS_AND_ABS as usize] = Some(and::<Absolute>);
+    table[INS_AND_ABSX as usize] = Some(and::<AbsoluteX>);
+    table[INS_AND_ABSY as usize] = Some(and::<AbsoluteY>);
+    table[INS_AND_INDX as usize] = Some(and::<IndirectX>);
+    table[INS_AND_INDY as usize] = Some(and::<IndirectY>);
+    table[INS_ORA_IM as usize] = Some(ora::<Immediate>);
+    table[INS_ORA_ZP as usize] = Some(ora::<ZeroPage>);
+    table[INS_ORA_ZPX as usize] = Some(ora::<ZeroPageX>);
+    table[INS_ORA_ABS as usize] = Some(ora::<Absolute>);
+    table[INS_ORA_ABSX as usize] = Some(ora::<AbsoluteX>);
+    table[INS_ORA_ABSY as usize] = Some(ora::<AbsoluteY>);
+    table[INS_ORA_INDX as usize] = Some(ora::<IndirectX>);
+    table[INS_ORA_INDY as usize] = Some(ora::<IndirectY>);
+    table[INS_EOR_IM as usize] = Some(eor::<Immediate>);
+    table[INS_EOR_ZP as usize] = Some(eor::<ZeroPage>);
+    table[INS_EOR_ZPX as usize] = Some(eor::<ZeroPageX>);
+    table[INS_EOR_ABS as usize] = Some(eor::<Absolute>);
+    table[INS_EOR_ABSX as usize] = Some(eor::<AbsoluteX>);
+    table[INS_EOR_ABSY as usize] = Some(eor::<AbsoluteY>);
+    table[INS_EOR_INDX as usize] = Some(eor::<IndirectX>);
+    table[INS_EOR_INDY as usize] = Some(eor::<IndirectY>);
+    table[INS_BIT_ZP as usize] = Some(bit::<ZeroPage>);
+    table[INS_BIT_ABS as usize] = Some(bit::<Absolute>);
+
+    // --- Arithmetic ---
+    table[INS_ADC as usize] = Some(adc::<Immediate>);
+    table[INS_ADC_ZP as usize] = Some(adc::<ZeroPage>);
+    table[INS_ADC_ZPX as usize] = Some(adc::<ZeroPageX>);
+    table[INS_ADC_ABS as usize] = Some(adc::<Absolute>);
+    table[INS_ADC_ABSX as usize] = Some(adc::<AbsoluteX>);
+    table[INS_ADC_ABSY as usize] = Some(adc::<AbsoluteY>);
+    table[INS_ADC_INDX as usize] = Some(adc::<IndirectX>);
+    table[INS_ADC_INDY as usize] = Some(adc::<IndirectY>);
+    table[INS_SBC as usize] = Some(sbc::<Immediate>);
+    table[INS_SBC_ZP as usize] = Some(sbc::<ZeroPage>);
+    table[INS_SBC_ZPX as usize] = Some(sbc::<ZeroPageX>);
+    table[INS_SBC_ABS as usize] = Some(sbc::<Absolute>);
+    table[INS_SBC_ABSX as usize] = Some(sbc::<AbsoluteX>);
+    table[INS_SBC_ABSY as usize] = Some(sbc::<AbsoluteY>);
+    table[INS_SBC_INDX as usize] = Some(sbc::<IndirectX>);
+    table[INS_SBC_INDY as usize] = Some(sbc::<IndirectY>);
+
+    // --- Compare ---
+    table[INS_CMP as usize] = Some(cmp::<Immediate>);
+    table[INS_CMP_ZP as usize] = Some(cmp::<ZeroPage>);
+    table[INS_CMP_ZPX as usize] = Some(cmp::<ZeroPageX>);
+    table[INS_CMP_ABS as usize] = Some(cmp::<Absolute>);
+    table[INS_CMP_ABSX as usize] = Some(cmp::<AbsoluteX>);
+    table[INS_CMP_ABSY as usize] = Some(cmp::<AbsoluteY>);
+    table[INS_CMP_INDX as usize] = Some(cmp::<IndirectX>);
+    table[INS_CMP_INDY as usize] = Some(cmp::<IndirectY>);
+    table[INS_CPX as usize] = Some(cpx::<Immediate>);
+    table[INS_CPX_ZP as usize] = Some(cpx::<ZeroPage>);
+    table[INS_CPX_ABS as usize] = Some(cpx::<Absolute>);
+    table[INS_CPY as usize] = Some(cpy::<Immediate>);
+    table[INS_CPY_ZP as usize] = Some(cpy::<ZeroPage>);
+    table[INS_CPY_ABS as usize] = Some(cpy::<Absolute>);
+
+    // --- Increments and decrements ---
+    table[INS_INX as usize] = Some(inx);
+    table[INS_INY as usize] = Some(iny);
+    table[INS_DEX as usize] = Some(dex);
+    table[INS_DEY as usize] = Some(dey);
+    table[INS_INC_ZP as usize] = Some(inc::<ZeroPage>);
+    table[INS_INC_ZPX as usize] = Some(inc::<ZeroPageX>);
+    table[INS_INC_ABS as usize] = Some(inc::<Absolute>);
+    table[INS_INC_ABSX as usize] = Some(inc::<AbsoluteXFixed>);
+    table[INS_DEC_ZP as usize] = Some(dec::<ZeroPage>);
+    table[INS_DEC_ZPX as usize] = Some(dec::<ZeroPageX>);
+    table[INS_DEC_ABS as usize] = Some(dec::<Absolute>);
+    table[INS_DEC_ABSX as usize] = Some(dec::<AbsoluteXFixed>);
+
+    // --- Shifts and rotates ---
+    table[INS_ASL as usize] = Some(asl_accumulator);
+    table[INS_ASL_ZP as usize] = Some(asl::<ZeroPage>);
+    table[INS_ASL_ZPX as usize] = Some(asl::<ZeroPageX>);
+    table[INS_ASL_ABS as usize] = Some(asl::<Absolute>);
+    table[INS_ASL_ABSX as usize] = Some(asl::<AbsoluteXShift>);
+    table[INS_LSR as usize] = Some(lsr_accumulator);
+    table[INS_LSR_ZP as usize] = Some(lsr::<ZeroPage>);
+    table[INS_LSR_ZPX as usize] = Some(lsr::<ZeroPageX>);
+    table[INS_LSR_ABS as usize] = Some(lsr::<Absolute>);
+    table[INS_LSR_ABSX as usize] = Some(lsr::<AbsoluteXShift>);
+    table[INS_ROL as usize] = Some(rol_accumulator);
+    table[INS_ROL_ZP as usize] = Some(rol::<ZeroPage>);
+    table[INS_ROL_ZPX as usize] = Some(rol::<ZeroPageX>);
+    table[INS_ROL_ABS as usize] = Some(rol::<Absolute>);
+    table[INS_ROL_ABSX as usize] = Some(rol::<AbsoluteXShift>);
+    table[INS_ROR as usize] = Some(ror_accumulator);
+    table[INS_ROR_ZP as usize] = Some(ror::<ZeroPage>);
+    table[INS_ROR_ZPX as usize] = Some(ror::<ZeroPageX>);
+    table[INS_ROR_ABS as usize] = Some(ror::<Absolute>);
+    table[INS_ROR_ABSX as usize] = Some(ror::<AbsoluteXShift>);
+
+    // --- Flags ---
+    table[INS_CLC as usize] = Some(clc);
+    table[INS_SEC as usize] = Some(sec);
+    table[INS_CLD as usize] = Some(cld);
+    table[INS_SED as usize] = Some(sed);
+    table[INS_CLI as usize] = Some(cli);
+    table[INS_SEI as usize] = Some(sei);
+    table[INS_CLV as usize] = Some(clv);
+
+    table[INS_NOP as usize] = Some(nop);
+
+    table
+};
+
+// --- Addressing modes ---
+
+/// Computes the effective address of an operand, charging the cycles the mode
+/// spends on it.
+trait Addressing {
+    fn address(cpu: &mut Cpu, cycles: &mut i32, memory: &Memory) -> Word;
+}
+
+/// The operand byte itself; reading it is the fetch cycle.
+struct Immediate;
+struct ZeroPage;
+struct ZeroPageX;
+struct ZeroPageY;
+struct Absolute;
+/// `abs,X` with the extra cycle only when indexing crosses a page.
+struct AbsoluteX;
+/// `abs,Y` with the extra cycle only when indexing crosses a page.
+struct AbsoluteY;
+/// `abs,X` that always spends the fix-up cycle, as stores and read-modify-write do.
+struct AbsoluteXFixed;
+/// `abs,X` for shifts and rotates, whose fix-up cycle depends on the variant.
+struct AbsoluteXShift;
+/// `abs,Y` that always spends the fix-up cycle.
+struct AbsoluteYFixed;
+struct IndirectX;
+/// `(zp),Y` with the extra cycle only when indexing crosses a page.
+struct IndirectY;
+/// `(zp),Y` that always spends the fix-up cycle.
+struct IndirectYFixed;
+/// The `JMP ($xxxx)` pointer, including the NMOS page wrap bug.
+struct Indirect;
+
+impl Addressing for Immediate {
+    #[inline(always)]
+    fn address(cpu: &mut Cpu, _cycles: &mut i32, _memory: &Memory) -> Word {
+        let addr = cpu.pc;
+        cpu.pc = cpu.pc.wrapping_add(1);
+        addr
+    }
+}
+
+macro_rules! addressing {
+    ($($mode:ident => $helper:ident),* $(,)?) => {
+        $(
+            impl Addressing for $mode {
+                #[inline(always)]
+                fn address(cpu: &mut Cpu, cycles: &mut i32, memory: &Memory) -> Word {
+                    cpu.$helper(cycles, memory)
+                }
+            }
+        )*
+    };
+}
+
+addressing! {
+    ZeroPage => addr_zero_page,
+    ZeroPageX => addr_zero_page_x,
+    ZeroPageY => addr_zero_page_y,
+    Absolute => addr_absolute,
+    AbsoluteX => addr_absolute_x,
+    AbsoluteY => addr_absolute_y,
+    AbsoluteXFixed => addr_absolute_x_5,
+    AbsoluteXShift => addr_absolute_x_shift,
+    AbsoluteYFixed => addr_absolute_y_5,
+    IndirectX => addr_indirect_x,
+    IndirectY => addr_indirect_y,
+    IndirectYFixed => addr_indirect_y_6,
+    Indirect => addr_indirect_mp,
+}
+
+#[inline(always)]
+fn operand<M: Addressing>(cpu: &mut Cpu, memory: &Memory, cycles: &mut i32) -> Byte {
+    let addr = M::address(cpu, cycles, memory);
+    cpu.read_byte(memory, addr, cycles)
+}
+
+#[inline(always)]
+fn store<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, value: Byte, cycles: &mut i32) {
+    let addr = M::address(cpu, cycles, memory);
+    cpu.write_byte(memory, addr, value, cycles);
+}
+
+/// Read-modify-write: reads the operand, applies `op` and writes the result back.
+#[inline(always)]
+fn modify<M: Addressing>(
+    cpu: &mut Cpu,
+    memory: &mut Memory,
+    cycles: &mut i32,
+    op: impl FnOnce(&mut Cpu, Byte, &mut i32) -> Byte,
+) {
+    let addr = M::address(cpu, cycles, memory);
+    let value = cpu.read_byte(memory, addr, cycles);
+    let result = op(cpu, value, cycles);
+    cpu.write_byte(memory, addr, result, cycles);
+}
+
+// --- Load and store ---
+
+fn lda<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+}
+
+fn ldx<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_x = operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_x);
+}
+
+fn ldy<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_y = operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_y);
+}
+
+fn sta<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    store::<M>(cpu, memory, cpu.reg_a, cycles);
+}
+
+fn stx<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    store::<M>(cpu, memory, cpu.reg_x, cycles);
+}
+
+fn sty<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    store::<M>(cpu, memory, cpu.reg_y, cycles);
+}
+
+// --- Transfers and stack ---
+
+fn tax(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_x = cpu.reg_a;
+    cpu.set_zero_and_negative_flags(cpu.reg_x);
+    *cycles -= 1;
+}
+
+fn tay(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_y = cpu.reg_a;
+    cpu.set_zero_and_negative_flags(cpu.reg_y);
+    *cycles -= 1;
+}
+
+fn txa(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.reg_x;
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+    *cycles -= 1;
+}
+
+fn tya(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.reg_y;
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+    *cycles -= 1;
+}
+
+fn tsx(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_x = cpu.sp;
+    cpu.set_zero_and_negative_flags(cpu.reg_x);
+    *cycles -= 1;
+}
+
+fn txs(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.sp = cpu.reg_x;
+    *cycles -= 1;
+}
+
+fn pha(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 1;
+    cpu.push_byte_to_stack(memory, cpu.reg_a, cycles);
+}
+
+fn pla(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 2;
+    cpu.reg_a = cpu.pull_byte_from_stack(memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+}
+
+fn php(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 1;
+    cpu.push_status_to_stack(memory, true, cycles);
+}
+
+fn plp(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 2;
+    cpu.pull_status_from_stack(memory, cycles);
+}
+
+// --- Jumps, calls and interrupts ---
+
+fn jmp<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.pc = M::address(cpu, cycles, memory);
+}
+
+fn jsr(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let addr = Absolute::address(cpu, cycles, memory);
+    *cycles -= 1;
+    cpu.push_word_to_stack(memory, cpu.pc.wrapping_sub(1), cycles);
+    cpu.pc = addr;
+}
+
+fn rts(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 2;
+    let ret_addr = cpu.pull_word_from_stack(memory, cycles);
+    cpu.pc = ret_addr.wrapping_add(1);
+    *cycles -= 1;
+}
+
+fn brk(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.fetch_byte(memory, cycles);
+    cpu.interrupt(memory, 0xFFFE, true, cycles);
+}
+
+fn rti(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 2;
+    cpu.pull_status_from_stack(memory, cycles);
+    cpu.pc = cpu.pull_word_from_stack(memory, cycles);
+}
+
+// --- Branches ---
+
+fn bpl(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.negative, false, memory, cycles);
+}
+
+fn bmi(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.negative, true, memory, cycles);
+}
+
+fn bvc(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.overflow, false, memory, cycles);
+}
+
+fn bvs(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.overflow, true, memory, cycles);
+}
+
+fn bcc(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.carry, false, memory, cycles);
+}
+
+fn bcs(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.carry, true, memory, cycles);
+}
+
+fn bne(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.zero, false, memory, cycles);
+}
+
+fn beq(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.branch_if(cpu.status.zero, true, memory, cycles);
+}
+
+// --- Logical ---
+
+fn and<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a &= operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+}
+
+fn ora<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a |= operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+}
+
+fn eor<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a ^= operand::<M>(cpu, memory, cycles);
+    cpu.set_zero_and_negative_flags(cpu.reg_a);
+}
+
+fn bit<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.bit(value);
+}
+
+// --- Arithmetic and compare ---
+
+fn adc<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.adc(value, cycles);
+}
+
+fn sbc<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.sbc(value, cycles);
+}
+
+fn cmp<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.cmp(value, cpu.reg_a);
+}
+
+fn cpx<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.cmp(value, cpu.reg_x);
+}
+
+fn cpy<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    let value = operand::<M>(cpu, memory, cycles);
+    cpu.cmp(value, cpu.reg_y);
+}
+
+// --- Increments and decrements ---
+
+fn inx(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_x = cpu.reg_x.wrapping_add(1);
+    cpu.set_zero_and_negative_flags(cpu.reg_x);
+    *cycles -= 1;
+}
+
+fn iny(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_y = cpu.reg_y.wrapping_add(1);
+    cpu.set_zero_and_negative_flags(cpu.reg_y);
+    *cycles -= 1;
+}
+
+fn dex(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_x = cpu.reg_x.wrapping_sub(1);
+    cpu.set_zero_and_negative_flags(cpu.reg_x);
+    *cycles -= 1;
+}
+
+fn dey(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_y = cpu.reg_y.wrapping_sub(1);
+    cpu.set_zero_and_negative_flags(cpu.reg_y);
+    *cycles -= 1;
+}
+
+fn inc<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, |cpu, value, cycles| {
+        let result = value.wrapping_add(1);
+        cpu.set_zero_and_negative_flags(result);
+        *cycles -= 1;
+        result
+    });
+}
+
+fn dec<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, |cpu, value, cycles| {
+        let result = value.wrapping_sub(1);
+        cpu.set_zero_and_negative_flags(result);
+        *cycles -= 1;
+        result
+    });
+}
+
+// --- Shifts and rotates ---
+
+fn asl_accumulator(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.asl(cpu.reg_a, cycles);
+}
+
+fn lsr_accumulator(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.lsr(cpu.reg_a, cycles);
+}
+
+fn rol_accumulator(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.rol(cpu.reg_a, cycles);
+}
+
+fn ror_accumulator(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.reg_a = cpu.ror(cpu.reg_a, cycles);
+}
+
+fn asl<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, Cpu::asl);
+}
+
+fn lsr<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, Cpu::lsr);
+}
+
+fn rol<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, Cpu::rol);
+}
+
+fn ror<M: Addressing>(cpu: &mut Cpu, memory: &mut Memory, cycles: &mut i32) {
+    modify::<M>(cpu, memory, cycles, Cpu::ror);
+}
+
+// --- Flags ---
+
+fn clc(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.carry = false;
+    *cycles -= 1;
+}
+
+fn sec(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.carry = true;
+    *cycles -= 1;
+}
+
+fn cld(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.decimal_mode = false;
+    *cycles -= 1;
+}
+
+fn sed(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.decimal_mode = true;
+    *cycles -= 1;
+}
+
+fn cli(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.interrupt_disable = false;
+    *cycles -= 1;
+}
+
+fn sei(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.interrupt_disable = true;
+    *cycles -= 1;
+}
+
+fn clv(cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    cpu.status.overflow = false;
+    *cycles -= 1;
+}
+
+fn nop(_cpu: &mut Cpu, _memory: &mut Memory, cycles: &mut i32) {
+    *cycles -= 1;
+}
@@ -0,0 +1,137 @@
+use crate::loader::LoadError;
+use crate::{Byte, MAX_MEM, Memory, Symbols, Word};
+
+/// `e_machine` of the MOS 6502 family, as used by llvm-mos.
+const EM_MOS: u16 = 6502;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHN_UNDEF: u16 = 0;
+
+/// Symbol types worth naming: `STT_NOTYPE`, `STT_OBJECT` and `STT_FUNC`.
+const NAMED_TYPES: [Byte; 3] = [0, 1, 2];
+
+/// Little-endian fields at byte offsets of the file. Offsets are 64-bit so
+/// that sums of 32-bit header fields cannot overflow.
+struct Reader<'a>(&'a [Byte]);
+
+impl Reader<'_> {
+    fn bytes(&self, offset: u64, len: u64) -> Result<&[Byte], LoadError> {
+        let truncated = LoadError::Truncated { offset: self.0.len() };
+        let start = usize::try_from(offset).map_err(|_| truncated.clone())?;
+        let end = usize::try_from(offset + len).map_err(|_| truncated.clone())?;
+        self.0.get(start..end).ok_or(truncated)
+    }
+
+    fn u8(&self, offset: u64) -> Result<Byte, LoadError> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, LoadError> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, LoadError> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// The zero-terminated string at `offset`.
+    fn str(&self, offset: u64) -> Result<&str, LoadError> {
+        let truncated = LoadError::Truncated { offset: self.0.len() };
+        let tail = usize::try_from(offset).ok().and_then(|offset| self.0.get(offset..)).ok_or(truncated.clone())?;
+        let len = tail.iter().position(|&b| b == 0).ok_or(truncated)?;
+        core::str::from_utf8(&tail[..len]).map_err(|_| LoadError::Invalid("symbol name is not UTF-8".to_string()))
+    }
+}
+
+/// A `PT_LOAD` segment: `data` goes to `addr`, followed by `zeros` zero bytes.
+struct Segment<'a> {
+    addr: Word,
+    data: &'a [Byte],
+    zeros: usize,
+}
+
+/// Loads a 32-bit little-endian 6502 ELF executable, as linked by llvm-mos.
+/// Each `PT_LOAD` segment is copied to its physical address, where the image
+/// holds it before any startup code copies it elsewhere, with its BSS part
+/// cleared, and the named function, object and untyped symbols of `.symtab`
+/// that fit in 16 bits are added to `symbols`. DWARF sections are ignored.
+/// Returns the entry point.
+///
+/// The whole file is checked before memory is written, so on error memory and
+/// `symbols` are left as they were.
+pub fn load_elf(bytes: &[Byte], memory: &mut Memory, symbols: &mut Symbols) -> Result<Word, LoadError> {
+    let file = Reader(bytes);
+    if file.bytes(0, 4)? != b"\x7FELF" {
+        return Err(LoadError::Invalid("not an ELF file".to_string()));
+    }
+    if file.u8(4)? != 1 || file.u8(5)? != 1 {
+        return Err(LoadError::Invalid("only 32-bit little-endian ELF files are supported".to_string()));
+    }
+    let machine = file.u16(18)?;
+    if machine != EM_MOS {
+        return Err(LoadError::Invalid(format!("ELF machine {} is not a 6502", machine)));
+    }
+    let entry = file.u32(24)?;
+    let entry = Word::try_from(entry)
+        .map_err(|_| LoadError::Invalid(format!("entry point ${:X} is outside the 64K address space", entry)))?;
+    let (phoff, shoff) = (file.u32(28)? as u64, file.u32(32)? as u64);
+    let (phentsize, phnum) = (file.u16(42)? as u64, file.u16(44)? as u64);
+    let (shentsize, shnum) = (file.u16(46)? as u64, file.u16(48)? as u64);
+
+    let mut segments = Vec::new();
+    for index in 0..phnum {
+        let header = phoff + index * phentsize;
+        if file.u32(header)? != PT_LOAD {
+            continue;
+        }
+        let (offset, paddr) = (file.u32(header + 4)?, file.u32(header + 12)?);
+        let (filesz, memsz) = (file.u32(header + 16)?, file.u32(header + 20)?);
+        if paddr as u64 + memsz.max(filesz) as u64 > MAX_MEM as u64 {
+            return Err(LoadError::Invalid(format!(
+                "segment of {} bytes at ${:X} is outside the 64K address space",
+                memsz.max(filesz),
+                paddr
+            )));
+        }
+        let data = file.bytes(offset as u64, filesz as u64)?;
+        segments.push(Segment { addr: paddr as Word, data, zeros: memsz.saturating_sub(filesz) as usize });
+    }
+
+    let mut names = Vec::new();
+    for index in 0..shnum {
+        let header = shoff + index * shentsize;
+        if file.u32(header + 4)? != SHT_SYMTAB {
+            continue;
+        }
+        let (offset, size) = (file.u32(header + 16)? as u64, file.u32(header + 20)? as u64);
+        let (link, entsize) = (file.u32(header + 24)? as u64, file.u32(header + 36)?.max(16) as u64);
+        let strtab = file.u32(shoff + link * shentsize + 16)? as u64;
+        // The first entry is the reserved null symbol
+        for symbol in (offset..offset + size).step_by(entsize as usize).skip(1) {
+            let (name, value) = (file.u32(symbol)?, file.u32(symbol + 4)?);
+            let (info, shndx) = (file.u8(symbol + 12)?, file.u16(symbol + 14)?);
+            let name = file.str(strtab + name as u64)?;
+            let named = !name.is_empty() && NAMED_TYPES.contains(&(info & 0x0F)) && shndx != SHN_UNDEF;
+            // Absolute values such as sizes may not fit in 16 bits
+            if let Ok(addr) = Word::try_from(value)
+                && named
+            {
+                names.push((name, addr));
+            }
+        }
+    }
+
+    for segment in segments {
+        let start = segment.addr as usize;
+        let end = start + segment.data.len();
+        memory.data[start..end].copy_from_slice(segment.data);
+        memory.data[end..end + segment.zeros].fill(0);
+    }
+    for (name, addr) in names {
+        symbols.insert(name, addr);
+    }
+    Ok(entry)
+}
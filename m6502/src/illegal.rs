@@ -0,0 +1,257 @@
+use crate::instructions::*;
+use crate::{Byte, Cpu, Memory, Word};
+
+/// Magic constant used by the unstable ANE/LXA opcodes. Real chips vary between
+/// 0x00, 0xEE and 0xFF depending on temperature and batch; 0xEE is the most common.
+pub const ANE_LXA_MAGIC: Byte = 0xEE;
+
+impl Cpu {
+    /// Address for the read-modify-write combinations (SLO, RLA, SRE, RRA, DCP, ISC),
+    /// decoded from the low bits of the opcode. Indexed modes always take the extra cycle.
+    fn addr_rmw_combo(&mut self, opcode: Byte, cycles: &mut i32, memory: &Memory) -> Word {
+        match opcode & 0x1F {
+            0x03 => self.addr_indirect_x(cycles, memory),
+            0x07 => self.addr_zero_page(cycles, memory),
+            0x0F => self.addr_absolute(cycles, memory),
+            0x13 => self.addr_indirect_y_6(cycles, memory),
+            0x17 => self.addr_zero_page_x(cycles, memory),
+            0x1B => self.addr_absolute_y_5(cycles, memory),
+            _ => self.addr_absolute_x_5(cycles, memory),
+        }
+    }
+
+    /// SHA/SHX/SHY/TAS store `value & (H + 1)` where H is the high byte of the base
+    /// address. When indexing crosses a page the stored value also replaces the high
+    /// byte of the target address.
+    fn store_and_high(&mut self, base: Word, index: Byte, value: Byte, memory: &mut Memory, cycles: &mut i32) {
+        let addr = base.wrapping_add(index as Word);
+        let value = value & ((base >> 8) as Byte).wrapping_add(1);
+        let addr = if (base & 0xFF00) != (addr & 0xFF00) {
+            ((value as Word) << 8) | (addr & 0x00FF)
+        } else {
+            addr
+        };
+        *cycles -= 1;
+        self.write_byte(memory, addr, value, cycles);
+    }
+
+    /// Applies a read-modify-write combination (SLO, RLA, SRE, RRA, DCP, ISC) to
+    /// `value`, updating A and the flags, and returns the value written back.
+    pub(crate) fn rmw_combo(&mut self, opcode: Byte, value: Byte) -> Byte {
+        let mut cycles = 0;
+        match opcode >> 5 {
+            0 => {
+                let result = self.asl(value, &mut cycles);
+                self.reg_a |= result;
+                self.set_zero_and_negative_flags(self.reg_a);
+                result
+            }
+            1 => {
+                let result = self.rol(value, &mut cycles);
+                self.reg_a &= result;
+                self.set_zero_and_negative_flags(self.reg_a);
+                result
+            }
+            2 => {
+                let result = self.lsr(value, &mut cycles);
+                self.reg_a ^= result;
+                self.set_zero_and_negative_flags(self.reg_a);
+                result
+            }
+            3 => {
+                let result = self.ror(value, &mut cycles);
+                self.adc(result, &mut cycles);
+                result
+            }
+            6 => {
+                let result = value.wrapping_sub(1);
+                self.cmp(result, self.reg_a);
+                result
+            }
+            _ => {
+                let result = value.wrapping_add(1);
+                self.sbc(result, &mut cycles);
+                result
+            }
+        }
+    }
+
+    /// ANC, ALR, ARR, SBX, USBC, ANE and LXA with their immediate operand.
+    pub(crate) fn immediate_combo(&mut self, opcode: Byte, operand: Byte) {
+        match opcode {
+            INS_ANC | INS_ANC_2 => {
+                self.reg_a &= operand;
+                self.set_zero_and_negative_flags(self.reg_a);
+                self.status.carry = self.status.negative;
+            }
+            INS_ALR => {
+                let value = self.reg_a & operand;
+                self.status.carry = (value & 0x01) != 0;
+                self.reg_a = value >> 1;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            INS_ARR => {
+                let value = self.reg_a & operand;
+                let carry_in = if self.status.carry { 0x80 } else { 0 };
+                self.reg_a = (value >> 1) | carry_in;
+                self.set_zero_and_negative_flags(self.reg_a);
+                if self.status.decimal_mode && self.variant.has_decimal_mode() {
+                    // N and Z come from the rotated value and V from bit 6 changing;
+                    // each nibble of it is then fixed up from the nibble of the AND
+                    self.status.overflow = ((value ^ self.reg_a) & 0x40) != 0;
+                    if (value & 0x0F) + (value & 0x01) > 0x05 {
+                        self.reg_a = (self.reg_a & 0xF0) | (self.reg_a.wrapping_add(0x06) & 0x0F);
+                    }
+                    self.status.carry = (value & 0xF0) as Word + (value & 0x10) as Word > 0x50;
+                    if self.status.carry {
+                        self.reg_a = self.reg_a.wrapping_add(0x60);
+                    }
+                } else {
+                    self.status.carry = (self.reg_a & 0x40) != 0;
+                    self.status.overflow = ((self.reg_a >> 6) ^ (self.reg_a >> 5)) & 0x01 != 0;
+                }
+            }
+            INS_SBX => {
+                let value = self.reg_a & self.reg_x;
+                self.cmp(operand, value);
+                self.reg_x = value.wrapping_sub(operand);
+            }
+            INS_ANE => {
+                self.reg_a = (self.reg_a | ANE_LXA_MAGIC) & self.reg_x & operand;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            INS_LXA => {
+                self.reg_a = (self.reg_a | ANE_LXA_MAGIC) & operand;
+                self.reg_x = self.reg_a;
+                self.set_zero_and_negative_flags(self.reg_a);
+            }
+            _ => {
+                // USBC
+                self.sbc(operand, &mut 0);
+            }
+        }
+    }
+
+    /// Executes an undocumented NMOS opcode. Only called when `illegal_opcodes` is set.
+    pub(crate) fn execute_illegal(&mut self, opcode: Byte, memory: &mut Memory, cycles: &mut i32) {
+        match opcode {
+            // --- Read-modify-write combinations ---
+            INS_SLO_ZP | INS_SLO_ZPX | INS_SLO_ABS | INS_SLO_ABSX | INS_SLO_ABSY | INS_SLO_INDX
+            | INS_SLO_INDY | INS_RLA_ZP | INS_RLA_ZPX | INS_RLA_ABS | INS_RLA_ABSX | INS_RLA_ABSY
+            | INS_RLA_INDX | INS_RLA_INDY | INS_SRE_ZP | INS_SRE_ZPX | INS_SRE_ABS | INS_SRE_ABSX
+            | INS_SRE_ABSY | INS_SRE_INDX | INS_SRE_INDY | INS_RRA_ZP | INS_RRA_ZPX | INS_RRA_ABS
+            | INS_RRA_ABSX | INS_RRA_ABSY | INS_RRA_INDX | INS_RRA_INDY | INS_DCP_ZP | INS_DCP_ZPX
+            | INS_DCP_ABS | INS_DCP_ABSX | INS_DCP_ABSY | INS_DCP_INDX | INS_DCP_INDY | INS_ISC_ZP
+            | INS_ISC_ZPX | INS_ISC_ABS | INS_ISC_ABSX | INS_ISC_ABSY | INS_ISC_INDX | INS_ISC_INDY => {
+                let addr = self.addr_rmw_combo(opcode, cycles, memory);
+                let operand = self.read_byte(memory, addr, cycles);
+                *cycles -= 1;
+                let result = self.rmw_combo(opcode, operand);
+                self.write_byte(memory, addr, result, cycles);
+            }
+
+            // --- Loads and stores of A and X together ---
+            INS_SAX_ZP => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.write_byte(memory, addr, self.reg_a & self.reg_x, cycles);
+            }
+            INS_SAX_ZPY => {
+                let addr = self.addr_zero_page_y(cycles, memory);
+                self.write_byte(memory, addr, self.reg_a & self.reg_x, cycles);
+            }
+            INS_SAX_ABS => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.write_byte(memory, addr, self.reg_a & self.reg_x, cycles);
+            }
+            INS_SAX_INDX => {
+                let addr = self.addr_indirect_x(cycles, memory);
+                self.write_byte(memory, addr, self.reg_a & self.reg_x, cycles);
+            }
+            INS_LAX_ZP | INS_LAX_ZPY | INS_LAX_ABS | INS_LAX_ABSY | INS_LAX_INDX | INS_LAX_INDY => {
+                let addr = match opcode {
+                    INS_LAX_ZP => self.addr_zero_page(cycles, memory),
+                    INS_LAX_ZPY => self.addr_zero_page_y(cycles, memory),
+                    INS_LAX_ABS => self.addr_absolute(cycles, memory),
+                    INS_LAX_ABSY => self.addr_absolute_y(cycles, memory),
+                    INS_LAX_INDX => self.addr_indirect_x(cycles, memory),
+                    _ => self.addr_indirect_y(cycles, memory),
+                };
+                self.load_register_a(addr, memory, cycles);
+                self.reg_x = self.reg_a;
+            }
+            INS_LAS_ABSY => {
+                let addr = self.addr_absolute_y(cycles, memory);
+                let value = self.read_byte(memory, addr, cycles) & self.sp;
+                self.reg_a = value;
+                self.reg_x = value;
+                self.sp = value;
+                self.set_zero_and_negative_flags(value);
+            }
+
+            // --- Immediate combinations, including the unstable ANE/LXA ---
+            INS_ANC | INS_ANC_2 | INS_ALR | INS_ARR | INS_SBX | INS_USBC | INS_ANE | INS_LXA => {
+                let operand = self.fetch_byte(memory, cycles);
+                self.immediate_combo(opcode, operand);
+            }
+
+            // --- Unstable stores ---
+            INS_SHA_ABSY => {
+                let base = self.addr_absolute(cycles, memory);
+                self.store_and_high(base, self.reg_y, self.reg_a & self.reg_x, memory, cycles);
+            }
+            INS_SHA_INDY => {
+                let zp_addr = self.fetch_byte(memory, cycles);
+                let lo = self.read_byte(memory, zp_addr as Word, cycles) as Word;
+                let hi = self.read_byte(memory, zp_addr.wrapping_add(1) as Word, cycles) as Word;
+                self.store_and_high((hi << 8) | lo, self.reg_y, self.reg_a & self.reg_x, memory, cycles);
+            }
+            INS_SHX_ABSY => {
+                let base = self.addr_absolute(cycles, memory);
+                self.store_and_high(base, self.reg_y, self.reg_x, memory, cycles);
+            }
+            INS_SHY_ABSX => {
+                let base = self.addr_absolute(cycles, memory);
+                self.store_and_high(base, self.reg_x, self.reg_y, memory, cycles);
+            }
+            INS_TAS_ABSY => {
+                let base = self.addr_absolute(cycles, memory);
+                self.sp = self.reg_a & self.reg_x;
+                self.store_and_high(base, self.reg_y, self.sp, memory, cycles);
+            }
+
+            // --- NOPs that still fetch and read their operands ---
+            op if INS_NOP_IMP.contains(&op) => {
+                *cycles -= 1;
+            }
+            op if INS_NOP_IM.contains(&op) => {
+                self.fetch_byte(memory, cycles);
+            }
+            op if INS_NOP_ZP.contains(&op) => {
+                let addr = self.addr_zero_page(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            op if INS_NOP_ZPX.contains(&op) => {
+                let addr = self.addr_zero_page_x(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            INS_NOP_ABS => {
+                let addr = self.addr_absolute(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+            op if INS_NOP_ABSX.contains(&op) => {
+                let addr = self.addr_absolute_x(cycles, memory);
+                self.read_byte(memory, addr, cycles);
+            }
+
+            // --- JAM: the CPU stops fetching until reset ---
+            op if INS_JAM.contains(&op) => {
+                self.pc = self.pc.wrapping_sub(1);
+                self.jammed = true;
+            }
+
+            _ => {
+                panic!("Instruction {:02X} not implemented", opcode);
+            }
+        }
+    }
+}
@@ -3,10 +3,12 @@ type Byte = u8;
 // Process Status Bits
 pub const NEGATIVE_FLAG_BIT: Byte = 0b10000000;
 pub const OVERFLOW_FLAG_BIT: Byte = 0b01000000;
-pub const BREAK_FLAG_BIT: Byte = 0b00001000;
-pub const UNUSED_FLAG_BIT: Byte = 0b00010000;
+pub const UNUSED_FLAG_BIT: Byte = 0b00100000;
+pub const BREAK_FLAG_BIT: Byte = 0b00010000;
+pub const DECIMAL_FLAG_BIT: Byte = 0b00001000;
 pub const INTERRUPT_DISABLE_FLAG_BIT: Byte = 0b00000100;
-pub const ZERO_FLAG_BIT: Byte = 0b00000001;
+pub const ZERO_FLAG_BIT: Byte = 0b00000010;
+pub const CARRY_FLAG_BIT: Byte = 0b00000001;
 
 // opcodes
 // LDA
@@ -204,3 +206,150 @@ pub const INS_ROR_ABSX: Byte = 0x7E;
 pub const INS_NOP: Byte = 0xEA;
 pub const INS_BRK: Byte = 0x00;
 pub const INS_RTI: Byte = 0x40;
+
+// Undocumented (NMOS), only decoded when Cpu::illegal_opcodes is set
+// SLO: ASL then ORA
+pub const INS_SLO_ZP: Byte = 0x07;
+pub const INS_SLO_ZPX: Byte = 0x17;
+pub const INS_SLO_ABS: Byte = 0x0F;
+pub const INS_SLO_ABSX: Byte = 0x1F;
+pub const INS_SLO_ABSY: Byte = 0x1B;
+pub const INS_SLO_INDX: Byte = 0x03;
+pub const INS_SLO_INDY: Byte = 0x13;
+
+// RLA: ROL then AND
+pub const INS_RLA_ZP: Byte = 0x27;
+pub const INS_RLA_ZPX: Byte = 0x37;
+pub const INS_RLA_ABS: Byte = 0x2F;
+pub const INS_RLA_ABSX: Byte = 0x3F;
+pub const INS_RLA_ABSY: Byte = 0x3B;
+pub const INS_RLA_INDX: Byte = 0x23;
+pub const INS_RLA_INDY: Byte = 0x33;
+
+// SRE: LSR then EOR
+pub const INS_SRE_ZP: Byte = 0x47;
+pub const INS_SRE_ZPX: Byte = 0x57;
+pub const INS_SRE_ABS: Byte = 0x4F;
+pub const INS_SRE_ABSX: Byte = 0x5F;
+pub const INS_SRE_ABSY: Byte = 0x5B;
+pub const INS_SRE_INDX: Byte = 0x43;
+pub const INS_SRE_INDY: Byte = 0x53;
+
+// RRA: ROR then ADC
+pub const INS_RRA_ZP: Byte = 0x67;
+pub const INS_RRA_ZPX: Byte = 0x77;
+pub const INS_RRA_ABS: Byte = 0x6F;
+pub const INS_RRA_ABSX: Byte = 0x7F;
+pub const INS_RRA_ABSY: Byte = 0x7B;
+pub const INS_RRA_INDX: Byte = 0x63;
+pub const INS_RRA_INDY: Byte = 0x73;
+
+// SAX: store A & X
+pub const INS_SAX_ZP: Byte = 0x87;
+pub const INS_SAX_ZPY: Byte = 0x97;
+pub const INS_SAX_ABS: Byte = 0x8F;
+pub const INS_SAX_INDX: Byte = 0x83;
+
+// LAX: LDA and LDX
+pub const INS_LAX_ZP: Byte = 0xA7;
+pub const INS_LAX_ZPY: Byte = 0xB7;
+pub const INS_LAX_ABS: Byte = 0xAF;
+pub const INS_LAX_ABSY: Byte = 0xBF;
+pub const INS_LAX_INDX: Byte = 0xA3;
+pub const INS_LAX_INDY: Byte = 0xB3;
+
+// DCP: DEC then CMP
+pub const INS_DCP_ZP: Byte = 0xC7;
+pub const INS_DCP_ZPX: Byte = 0xD7;
+pub const INS_DCP_ABS: Byte = 0xCF;
+pub const INS_DCP_ABSX: Byte = 0xDF;
+pub const INS_DCP_ABSY: Byte = 0xDB;
+pub const INS_DCP_INDX: Byte = 0xC3;
+pub const INS_DCP_INDY: Byte = 0xD3;
+
+// ISC: INC then SBC
+pub const INS_ISC_ZP: Byte = 0xE7;
+pub const INS_ISC_ZPX: Byte = 0xF7;
+pub const INS_ISC_ABS: Byte = 0xEF;
+pub const INS_ISC_ABSX: Byte = 0xFF;
+pub const INS_ISC_ABSY: Byte = 0xFB;
+pub const INS_ISC_INDX: Byte = 0xE3;
+pub const INS_ISC_INDY: Byte = 0xF3;
+
+// Immediate combinations
+pub const INS_ANC: Byte = 0x0B;
+pub const INS_ANC_2: Byte = 0x2B;
+pub const INS_ALR: Byte = 0x4B;
+pub const INS_ARR: Byte = 0x6B;
+pub const INS_SBX: Byte = 0xCB;
+pub const INS_USBC: Byte = 0xEB;
+pub const INS_LAS_ABSY: Byte = 0xBB;
+
+// Unstable: results depend on analog effects, see illegal.rs for the model used
+pub const INS_ANE: Byte = 0x8B;
+pub const INS_LXA: Byte = 0xAB;
+pub const INS_SHA_ABSY: Byte = 0x9F;
+pub const INS_SHA_INDY: Byte = 0x93;
+pub const INS_SHX_ABSY: Byte = 0x9E;
+pub const INS_SHY_ABSX: Byte = 0x9C;
+pub const INS_TAS_ABSY: Byte = 0x9B;
+
+// NOPs with operands
+pub const INS_NOP_IMP: [Byte; 6] = [0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA];
+pub const INS_NOP_IM: [Byte; 5] = [0x80, 0x82, 0x89, 0xC2, 0xE2];
+pub const INS_NOP_ZP: [Byte; 3] = [0x04, 0x44, 0x64];
+pub const INS_NOP_ZPX: [Byte; 6] = [0x14, 0x34, 0x54, 0x74, 0xD4, 0xF4];
+pub const INS_NOP_ABS: Byte = 0x0C;
+pub const INS_NOP_ABSX: [Byte; 6] = [0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC];
+
+// JAM (KIL): locks up the CPU until reset
+pub const INS_JAM: [Byte; 12] = [
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+];
+
+// 65C02 additions, only decoded when Cpu::variant is a CMOS variant
+pub const INS_BRA: Byte = 0x80;
+pub const INS_PHX: Byte = 0xDA;
+pub const INS_PHY: Byte = 0x5A;
+pub const INS_PLX: Byte = 0xFA;
+pub const INS_PLY: Byte = 0x7A;
+
+pub const INS_STZ_ZP: Byte = 0x64;
+pub const INS_STZ_ZPX: Byte = 0x74;
+pub const INS_STZ_ABS: Byte = 0x9C;
+pub const INS_STZ_ABSX: Byte = 0x9E;
+
+pub const INS_TSB_ZP: Byte = 0x04;
+pub const INS_TSB_ABS: Byte = 0x0C;
+pub const INS_TRB_ZP: Byte = 0x14;
+pub const INS_TRB_ABS: Byte = 0x1C;
+
+pub const INS_INC_A: Byte = 0x1A;
+pub const INS_DEC_A: Byte = 0x3A;
+
+// (zp) addressing
+pub const INS_ORA_INDZP: Byte = 0x12;
+pub const INS_AND_INDZP: Byte = 0x32;
+pub const INS_EOR_INDZP: Byte = 0x52;
+pub const INS_ADC_INDZP: Byte = 0x72;
+pub const INS_STA_INDZP: Byte = 0x92;
+pub const INS_LDA_INDZP: Byte = 0xB2;
+pub const INS_CMP_INDZP: Byte = 0xD2;
+pub const INS_SBC_INDZP: Byte = 0xF2;
+
+pub const INS_BIT_IM: Byte = 0x89;
+pub const INS_BIT_ZPX: Byte = 0x34;
+pub const INS_BIT_ABSX: Byte = 0x3C;
+
+pub const INS_JMP_ABSX_IND: Byte = 0x7C;
+
+// Rockwell/WDC bit instructions: the bit number is in the high nibble of the opcode,
+// e.g. RMB3 = INS_RMB0 + 0x30, BBS7 = INS_BBS0 + 0x70
+pub const INS_RMB0: Byte = 0x07;
+pub const INS_SMB0: Byte = 0x87;
+pub const INS_BBR0: Byte = 0x0F;
+pub const INS_BBS0: Byte = 0x8F;
+
+// WDC W65C02S only
+pub const INS_WAI: Byte = 0xCB;
+pub const INS_STP: Byte = 0xDB;
@@ -1,7 +1,49 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 pub mod instructions;
+#[cfg(feature = "std")]
+pub mod nes;
+#[cfg(feature = "std")]
+mod block;
+mod bus;
+mod cmos;
+mod cycle;
+mod dispatch;
+#[cfg(feature = "std")]
+mod elf;
+mod illegal;
+#[cfg(feature = "std")]
+mod loader;
+#[cfg(feature = "std")]
+mod o65;
+#[cfg(feature = "std")]
+mod symbols;
+mod trace;
+#[cfg(feature = "std")]
+mod xex;
 use crate::instructions::*;
 
-use std::ops::{Index, IndexMut};
+#[cfg(feature = "std")]
+pub use crate::block::BlockCache;
+pub use crate::bus::Bus;
+pub use crate::cycle::BusAccess;
+#[cfg(feature = "std")]
+pub use crate::elf::load_elf;
+pub use crate::illegal::ANE_LXA_MAGIC;
+#[cfg(feature = "std")]
+pub use crate::loader::{Format, LoadError, load_intel_hex, load_prg, load_raw, load_srecord};
+#[cfg(feature = "std")]
+pub use crate::o65::{O65Cpu, O65Header, O65Layout, load_o65};
+#[cfg(feature = "std")]
+pub use crate::symbols::Symbols;
+#[cfg(feature = "std")]
+pub use crate::trace::{disassemble, disassemble_with_symbols};
+pub use crate::trace::{write_disassembly, write_trace_columns};
+#[cfg(feature = "std")]
+pub use crate::xex::load_xex;
+
+use core::fmt;
+use core::ops::{Index, IndexMut};
 
 pub type Byte = u8;
 pub type SByte = i8;
@@ -46,7 +88,7 @@ impl IndexMut<usize> for Memory {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct StatusFlags {
     pub carry: bool,
     pub zero: bool,
@@ -58,6 +100,94 @@ pub struct StatusFlags {
     pub unused: bool,
 }
 
+impl StatusFlags {
+    /// Packs the flags into the P register layout `NV-BDIZC`.
+    /// B and bit 5 only exist in the copy pushed to the stack: bit 5 is always set
+    /// and `break_bit` selects B (set for PHP/BRK, clear for IRQ/NMI).
+    pub fn to_byte(&self, break_bit: bool) -> Byte {
+        let mut status = UNUSED_FLAG_BIT;
+        if self.carry { status |= CARRY_FLAG_BIT; }
+        if self.zero { status |= ZERO_FLAG_BIT; }
+        if self.interrupt_disable { status |= INTERRUPT_DISABLE_FLAG_BIT; }
+        if self.decimal_mode { status |= DECIMAL_FLAG_BIT; }
+        if break_bit { status |= BREAK_FLAG_BIT; }
+        if self.overflow { status |= OVERFLOW_FLAG_BIT; }
+        if self.negative { status |= NEGATIVE_FLAG_BIT; }
+        status
+    }
+
+    /// Unpacks a P register byte (as pulled by PLP/RTI). B and bit 5 are ignored.
+    pub fn from_byte(status: Byte) -> Self {
+        StatusFlags {
+            carry: (status & CARRY_FLAG_BIT) != 0,
+            zero: (status & ZERO_FLAG_BIT) != 0,
+            interrupt_disable: (status & INTERRUPT_DISABLE_FLAG_BIT) != 0,
+            decimal_mode: (status & DECIMAL_FLAG_BIT) != 0,
+            break_command: false,
+            overflow: (status & OVERFLOW_FLAG_BIT) != 0,
+            negative: (status & NEGATIVE_FLAG_BIT) != 0,
+            unused: false,
+        }
+    }
+}
+
+/// Formats as `NV-BDIZC`: upper case for set flags, lower case for clear ones.
+impl fmt::Display for StatusFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.negative, 'N'),
+            (self.overflow, 'V'),
+            (true, '-'),
+            (self.break_command, 'B'),
+            (self.decimal_mode, 'D'),
+            (self.interrupt_disable, 'I'),
+            (self.zero, 'Z'),
+            (self.carry, 'C'),
+        ];
+        for (set, name) in flags {
+            let c = if set { name } else { name.to_ascii_lowercase() };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Instruction set the Cpu decodes.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuVariant {
+    /// Original NMOS 6502, including the JMP ($xxFF) page wrap bug.
+    #[default]
+    Nmos6502,
+    /// WDC 65C02: new instructions and addressing modes, valid N/Z in decimal mode,
+    /// and every unused opcode is a NOP.
+    Cmos65C02,
+    /// Rockwell R65C02: the 65C02 plus RMB, SMB, BBR and BBS.
+    Rockwell65C02,
+    /// WDC W65C02S: the Rockwell set plus WAI and STP.
+    Wdc65C02,
+    /// Ricoh 2A03/2A07 of the NES: an NMOS 6502 with decimal mode disconnected.
+    /// SED/CLD still change the D flag, but ADC/SBC always work in binary.
+    Ricoh2A03,
+}
+
+impl CpuVariant {
+    pub fn is_cmos(&self) -> bool {
+        !matches!(self, CpuVariant::Nmos6502 | CpuVariant::Ricoh2A03)
+    }
+
+    pub fn has_decimal_mode(&self) -> bool {
+        *self != CpuVariant::Ricoh2A03
+    }
+
+    pub fn has_bit_instructions(&self) -> bool {
+        matches!(self, CpuVariant::Rockwell65C02 | CpuVariant::Wdc65C02)
+    }
+
+    pub fn has_wait_and_stop(&self) -> bool {
+        *self == CpuVariant::Wdc65C02
+    }
+}
+
 pub struct Cpu {
     pub reg_a: Byte,
     pub reg_x: Byte,
@@ -65,10 +195,32 @@ pub struct Cpu {
     pub status: StatusFlags,
     pub pc: Word,
     pub sp: Byte,
+    pub variant: CpuVariant,
+    /// Decode the undocumented NMOS opcodes instead of panicking on them.
+    /// Ignored on the 65C02, which has none.
+    pub illegal_opcodes: bool,
+    /// Set when a JAM opcode locked up the CPU; only a reset clears it.
+    pub jammed: bool,
+    /// Set by WAI: execute does nothing until irq() or nmi() is signalled.
+    pub waiting: bool,
+    /// Set by STP: execute does nothing until reset.
+    pub stopped: bool,
+    /// Instruction in flight for the cycle-stepped `tick()` engine.
+    cycle: cycle::CycleState,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cpu {
     pub fn new() -> Self {
+        Self::with_variant(CpuVariant::default())
+    }
+
+    pub fn with_variant(variant: CpuVariant) -> Self {
         Cpu {
             reg_a: 0,
             reg_x: 0,
@@ -76,6 +228,12 @@ impl Cpu {
             status: StatusFlags::default(),
             pc: 0xFFFC,
             sp: 0xFD,
+            variant,
+            illegal_opcodes: false,
+            jammed: false,
+            waiting: false,
+            stopped: false,
+            cycle: cycle::CycleState::default(),
         }
     }
 
@@ -84,14 +242,63 @@ impl Cpu {
     }
 
     pub fn reset_vec(&mut self, memory: &mut Memory) {
-        self.pc = ((memory.data[0xFFFD] as Word) << 8) | (memory.data[0xFFFC] as Word);
+        self.reset_bus(memory);
+    }
+
+    /// `reset()` over a bus, for machines whose reset vector is not in flat
+    /// memory, e.g. in a cartridge ROM.
+    pub fn reset_bus(&mut self, bus: &mut impl Bus) {
+        self.pc = ((bus.read(0xFFFD) as Word) << 8) | (bus.read(0xFFFC) as Word);
         self.reg_a = 0;
         self.reg_x = 0;
         self.reg_y = 0;
         self.status = StatusFlags::default();
+        self.jammed = false;
+        self.waiting = false;
+        self.stopped = false;
+        self.cycle = cycle::CycleState::default();
         // memory.set_values(0);
     }
 
+    /// Signals a maskable interrupt. Wakes the CPU from WAI even when interrupts are
+    /// disabled, in which case execution resumes after the WAI without taking it.
+    /// Returns the number of cycles consumed.
+    pub fn irq(&mut self, memory: &mut Memory) -> i32 {
+        if self.jammed || self.stopped {
+            return 0;
+        }
+        self.waiting = false;
+        if self.status.interrupt_disable {
+            return 0;
+        }
+        let mut cycles = -2;
+        self.interrupt(memory, 0xFFFE, false, &mut cycles);
+        -cycles
+    }
+
+    /// Signals a non-maskable interrupt. Returns the number of cycles consumed.
+    pub fn nmi(&mut self, memory: &mut Memory) -> i32 {
+        if self.jammed || self.stopped {
+            return 0;
+        }
+        self.waiting = false;
+        let mut cycles = -2;
+        self.interrupt(memory, 0xFFFA, false, &mut cycles);
+        -cycles
+    }
+
+    /// Pushes PC and P, sets I (and clears D on the 65C02), then jumps through `vector`.
+    /// Hardware interrupts spend two more cycles before this, BRK one on its signature byte.
+    fn interrupt(&mut self, memory: &mut Memory, vector: Word, break_bit: bool, cycles: &mut i32) {
+        self.push_pc_to_stack(memory, cycles);
+        self.push_status_to_stack(memory, break_bit, cycles);
+        self.status.interrupt_disable = true;
+        if self.variant.is_cmos() {
+            self.status.decimal_mode = false;
+        }
+        self.pc = self.read_word(memory, vector, cycles);
+    }
+
     pub fn fetch_byte(&mut self, memory: &Memory, cycles: &mut i32) -> Byte {
         let data = memory[self.pc as usize];
         self.pc = self.pc.wrapping_add(1);
@@ -147,6 +354,32 @@ impl Cpu {
         self.push_word_to_stack(memory, self.pc, cycles);
     }
 
+    pub fn push_byte_to_stack(&mut self, memory: &mut Memory, value: Byte, cycles: &mut i32) {
+        self.write_byte(memory, self.sp_to_address(), value, cycles);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    pub fn pull_byte_from_stack(&mut self, memory: &Memory, cycles: &mut i32) -> Byte {
+        self.sp = self.sp.wrapping_add(1);
+        self.read_byte(memory, self.sp_to_address(), cycles)
+    }
+
+    pub fn pull_word_from_stack(&mut self, memory: &Memory, cycles: &mut i32) -> Word {
+        let lo = self.pull_byte_from_stack(memory, cycles) as Word;
+        let hi = self.pull_byte_from_stack(memory, cycles) as Word;
+        (hi << 8) | lo
+    }
+
+    pub fn push_status_to_stack(&mut self, memory: &mut Memory, break_bit: bool, cycles: &mut i32) {
+        let status = self.status.to_byte(break_bit);
+        self.push_byte_to_stack(memory, status, cycles);
+    }
+
+    pub fn pull_status_from_stack(&mut self, memory: &Memory, cycles: &mut i32) {
+        let status = self.pull_byte_from_stack(memory, cycles);
+        self.status = StatusFlags::from_byte(status);
+    }
+
     // Addressing Modes
     fn addr_zero_page(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let zp_addr = self.fetch_byte(memory, cycles);
@@ -193,6 +426,16 @@ impl Cpu {
         addr
     }
 
+    /// Absolute,X for ASL/LSR/ROL/ROR. The NMOS 6502 always subtracts the extra
+    /// cycle; the 65C02 only does when indexing crosses a page.
+    pub fn addr_absolute_x_shift(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
+        if self.variant.is_cmos() {
+            self.addr_absolute_x(cycles, memory)
+        } else {
+            self.addr_absolute_x_5(cycles, memory)
+        }
+    }
+
     /// Absolute,Y (with page boundary penalty)
     pub fn addr_absolute_y(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let base = self.fetch_word(memory, cycles);
@@ -215,7 +458,7 @@ impl Cpu {
     pub fn addr_indirect_x(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let mut zp_addr = self.fetch_byte(memory, cycles);
         zp_addr = zp_addr.wrapping_add(self.reg_x);
-        *cycles -= 1;
+        *cycles -= 3; // index add, then the two pointer reads
         let lo = memory[zp_addr as usize] as Word;
         let hi = memory[zp_addr.wrapping_add(1) as usize] as Word;
         (hi << 8) | lo
@@ -224,6 +467,7 @@ impl Cpu {
     /// (Indirect),Y (with page boundary penalty)
     pub fn addr_indirect_y(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let zp_addr = self.fetch_byte(memory, cycles);
+        *cycles -= 2; // pointer reads
         let lo = memory[zp_addr as usize] as Word;
         let hi = memory[zp_addr.wrapping_add(1) as usize] as Word;
         let base = (hi << 8) | lo;
@@ -237,6 +481,7 @@ impl Cpu {
     /// (Indirect),Y (always subtracts a cycle, for store instructions)
     pub fn addr_indirect_y_6(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let zp_addr = self.fetch_byte(memory, cycles);
+        *cycles -= 2; // pointer reads
         let lo = memory[zp_addr as usize] as Word;
         let hi = memory[zp_addr.wrapping_add(1) as usize] as Word;
         let base = (hi << 8) | lo;
@@ -245,11 +490,25 @@ impl Cpu {
         addr
     }
 
+    /// (Indirect) zero page, 65C02 only
+    pub fn addr_indirect_zp(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
+        let zp_addr = self.fetch_byte(memory, cycles);
+        *cycles -= 2; // pointer reads
+        let lo = memory[zp_addr as usize] as Word;
+        let hi = memory[zp_addr.wrapping_add(1) as usize] as Word;
+        (hi << 8) | lo
+    }
+
     /// JMP (indirect) – emulate 6502 bug: if the indirect address ends in 0xFF, the high byte wraps within the same page.
+    /// The 65C02 fixes the bug at the cost of an extra cycle.
     pub fn addr_indirect_mp(&mut self, cycles: &mut i32, memory: &Memory) -> Word {
         let ptr = self.fetch_word(memory, cycles);
+        *cycles -= 2; // pointer reads
         let lo = memory[ptr as usize] as Word;
-        let hi_addr = if (ptr & 0x00FF) == 0x00FF {
+        let hi_addr = if self.variant.is_cmos() {
+            *cycles -= 1;
+            ptr.wrapping_add(1)
+        } else if (ptr & 0x00FF) == 0x00FF {
             ptr & 0xFF00
         } else {
             ptr.wrapping_add(1)
@@ -270,14 +529,6 @@ impl Cpu {
         self.reg_a = self.read_byte(memory, addr, cycles);
         self.set_zero_and_negative_flags(self.reg_a);
     }
-    fn load_register_x(&mut self, addr: Word, memory: &Memory, cycles: &mut i32) {
-        self.reg_x = self.read_byte(memory, addr, cycles);
-        self.set_zero_and_negative_flags(self.reg_x);
-    }
-    fn load_register_y(&mut self, addr: Word, memory: &Memory, cycles: &mut i32) {
-        self.reg_y = self.read_byte(memory, addr, cycles);
-        self.set_zero_and_negative_flags(self.reg_y);
-    }
 
     fn and(&mut self, addr: Word, memory: &Memory, cycles: &mut i32) {
         self.reg_a &= self.read_byte(memory, addr, cycles);
@@ -294,19 +545,23 @@ impl Cpu {
         self.set_zero_and_negative_flags(self.reg_a);
     }
 
-    // fn branch_if(&mut self, flag: bool, expected: bool, memory: &Memory, cycles: &mut i32) {
-    //     let offset = self.fetch_sbyte(memory, cycles);
-    //     if flag == expected {
-    //         let old_pc = self.pc;
-    //         self.pc = self.pc.wrapping_add(offset as Word);
-    //         *cycles -= 1;
-    //         if (old_pc & 0xFF00) != (self.pc & 0xFF00) {
-    //             *cycles -= 1;
-    //         }
-    //     }
-    // }
+    fn branch_if(&mut self, flag: bool, expected: bool, memory: &Memory, cycles: &mut i32) {
+        let offset = self.fetch_sbyte(memory, cycles);
+        if flag == expected {
+            let old_pc = self.pc;
+            self.pc = self.pc.wrapping_add(offset as Word);
+            *cycles -= 1;
+            if (old_pc & 0xFF00) != (self.pc & 0xFF00) {
+                *cycles -= 1;
+            }
+        }
+    }
 
-    fn adc(&mut self, operand: Byte) {
+    fn adc(&mut self, operand: Byte, cycles: &mut i32) {
+        if self.status.decimal_mode && self.variant.has_decimal_mode() {
+            self.adc_decimal(operand, cycles);
+            return;
+        }
         let carry = if self.status.carry { 1 } else { 0 };
         let sum = self.reg_a as u16 + operand as u16 + carry as u16;
         let result = sum as Byte;
@@ -317,9 +572,78 @@ impl Cpu {
         self.set_zero_and_negative_flags(self.reg_a);
     }
 
-    fn sbc(&mut self, operand: Byte) {
+    fn sbc(&mut self, operand: Byte, cycles: &mut i32) {
+        if self.status.decimal_mode && self.variant.has_decimal_mode() {
+            self.sbc_decimal(operand, cycles);
+            return;
+        }
         // 6502 SBC is ADC with the ones complement of the operand.
-        self.adc(!operand);
+        self.adc(!operand, cycles);
+    }
+
+    /// BCD addition. The NMOS 6502 sets Z from the binary sum and N/V from the
+    /// intermediate result before the high nibble is adjusted; the 65C02 sets N/Z
+    /// from the final result and takes an extra cycle.
+    fn adc_decimal(&mut self, operand: Byte, cycles: &mut i32) {
+        let a = self.reg_a as u16;
+        let b = operand as u16;
+        let carry = if self.status.carry { 1 } else { 0 };
+        let binary = (a + b + carry) as Byte;
+
+        let mut lo = (a & 0x0F) + (b & 0x0F) + carry;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (a & 0xF0) + (b & 0xF0) + lo;
+        self.status.negative = (sum & 0x80) != 0;
+        self.status.overflow = ((a ^ sum) & (b ^ sum) & 0x80) != 0;
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+        self.status.carry = sum >= 0x100;
+        self.reg_a = sum as Byte;
+
+        if self.variant.is_cmos() {
+            self.set_zero_and_negative_flags(self.reg_a);
+            *cycles -= 1;
+        } else {
+            self.status.zero = binary == 0;
+        }
+    }
+
+    /// BCD subtraction. Flags come from the binary subtraction, except that the
+    /// 65C02 sets N/Z from the final result and takes an extra cycle.
+    fn sbc_decimal(&mut self, operand: Byte, cycles: &mut i32) {
+        let a = self.reg_a as i16;
+        let b = operand as i16;
+        let borrow = if self.status.carry { 0 } else { 1 };
+
+        let binary = a - b - borrow;
+        let binary_byte = binary as Byte;
+        self.status.carry = binary >= 0;
+        self.status.overflow = ((self.reg_a ^ operand) & (self.reg_a ^ binary_byte) & 0x80) != 0;
+        self.set_zero_and_negative_flags(binary_byte);
+
+        let lo = (a & 0x0F) - (b & 0x0F) - borrow;
+        if self.variant.is_cmos() {
+            let mut result = binary;
+            if result < 0 {
+                result -= 0x60;
+            }
+            if lo < 0 {
+                result -= 0x06;
+            }
+            self.reg_a = result as Byte;
+            self.set_zero_and_negative_flags(self.reg_a);
+            *cycles -= 1;
+        } else {
+            let lo = if lo < 0 { ((lo - 0x06) & 0x0F) - 0x10 } else { lo };
+            let mut result = (a & 0xF0) - (b & 0xF0) + lo;
+            if result < 0 {
+                result -= 0x60;
+            }
+            self.reg_a = result as Byte;
+        }
     }
 
     fn cmp(&mut self, operand: Byte, reg: Byte) {
@@ -369,722 +693,16 @@ impl Cpu {
     /// Returns the number of cycles consumed.
     pub fn execute(&mut self, mut cycles: i32, memory: &mut Memory) -> i32 {
         let cycles_requested = cycles;
-        while cycles > 0 {
+        while cycles > 0 && !self.jammed && !self.waiting && !self.stopped {
             let opcode = self.fetch_byte(memory, &mut cycles);
-            match opcode {
-                // --- Load Accumulator ---
-                INS_LDA_IM => {
-                    self.reg_a = self.fetch_byte(memory, &mut cycles);
-                    self.set_zero_and_negative_flags(self.reg_a);
-                }
-                INS_LDA_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-                INS_LDA_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    self.load_register_a(addr, memory, &mut cycles);
-                }
-
-                // --- Load X/Y ---
-                INS_LDX_IM => {
-                    self.reg_x = self.fetch_byte(memory, &mut cycles);
-                    self.set_zero_and_negative_flags(self.reg_x);
-                }
-                INS_LDX_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.load_register_x(addr, memory, &mut cycles);
-                }
-                INS_LDX_ZPY => {
-                    let addr = self.addr_zero_page_y(&mut cycles, memory);
-                    self.load_register_x(addr, memory, &mut cycles);
-                }
-                INS_LDX_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.load_register_x(addr, memory, &mut cycles);
-                }
-                INS_LDX_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    self.load_register_x(addr, memory, &mut cycles);
-                }
-
-                INS_LDY_IM => {
-                    self.reg_y = self.fetch_byte(memory, &mut cycles);
-                    self.set_zero_and_negative_flags(self.reg_y);
-                }
-                INS_LDY_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.load_register_y(addr, memory, &mut cycles);
-                }
-                INS_LDY_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.load_register_y(addr, memory, &mut cycles);
-                }
-                INS_LDY_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.load_register_y(addr, memory, &mut cycles);
-                }
-                INS_LDY_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    self.load_register_y(addr, memory, &mut cycles);
-                }
-
-                // --- Store Accumulator, X, Y ---
-                INS_STA_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_ABSX => {
-                    let addr = self.addr_absolute_x_5(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_ABSY => {
-                    let addr = self.addr_absolute_y_5(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-                INS_STA_INDY => {
-                    let addr = self.addr_indirect_y_6(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_a, &mut cycles);
-                }
-
-                INS_STX_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_x, &mut cycles);
-                }
-                INS_STX_ZPY => {
-                    let addr = self.addr_zero_page_y(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_x, &mut cycles);
-                }
-                INS_STX_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_x, &mut cycles);
-                }
-
-                INS_STY_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_y, &mut cycles);
-                }
-                INS_STY_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_y, &mut cycles);
-                }
-                INS_STY_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.write_byte(memory, addr, self.reg_y, &mut cycles);
-                }
-
-                // --- Transfer and Stack Operations ---
-                INS_TSX => {
-                    // Transfer stack pointer to X
-                    self.reg_x = self.sp;
-                    self.set_zero_and_negative_flags(self.reg_x);
-                    cycles -= 1;
-                }
-                INS_TXS => {
-                    // Transfer X to stack pointer
-                    self.sp = self.reg_x;
-                    cycles -= 1;
-                }
-                INS_PHA => {
-                    self.push_word_to_stack(memory, self.reg_a as Word, &mut cycles);
-                }
-                INS_PLA => {
-                    self.reg_a = self.read_byte(memory, self.sp_to_address(), &mut cycles);
-                    self.set_zero_and_negative_flags(self.reg_a);
-                }
-                INS_PHP => {
-                    // Push processor status (with unused bit set)
-                    let mut status = 0;
-                    if self.status.carry { status |= 0x01; }
-                    if self.status.zero { status |= 0x02; }
-                    if self.status.interrupt_disable { status |= 0x04; }
-                    if self.status.decimal_mode { status |= 0x08; }
-                    if self.status.break_command { status |= 0x10; }
-                    if self.status.overflow { status |= 0x40; }
-                    if self.status.negative { status |= 0x80; }
-                    self.push_word_to_stack(memory, status as Word, &mut cycles);
-                }
-                INS_PLP => {
-                    // Pull processor status from stack – not a full implementation
-                    let status = self.read_byte(memory, self.sp_to_address(), &mut cycles);
-                    self.status.carry = (status & 0x01) != 0;
-                    self.status.zero = (status & 0x02) != 0;
-                    self.status.interrupt_disable = (status & 0x04) != 0;
-                    self.status.decimal_mode = (status & 0x08) != 0;
-                    self.status.break_command = (status & 0x10) != 0;
-                    self.status.overflow = (status & 0x40) != 0;
-                    self.status.negative = (status & 0x80) != 0;
-                }
-
-                // --- Jumps and Calls ---
-                INS_JMP_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.pc = addr;
-                }
-                INS_JMP_IND => {
-                    let addr = self.addr_indirect_mp(&mut cycles, memory);
-                    self.pc = addr;
-                }
-                INS_JSR => {
-                    // Push (PC-1), then set PC to target
-                    self.push_pc_to_stack(memory, &mut cycles);
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.pc = addr;
-                }
-                INS_RTS => {
-                    // Pop return address and add one
-                    let ret_addr = self.read_word(memory, self.sp_to_address(), &mut cycles);
-                    self.pc = ret_addr.wrapping_add(1);
-                }
-                INS_BRK => {
-                    self.status.break_command = true;
-                    cycles = 0;
-                }
-                INS_RTI => {
-                    // Pull processor status, then PC – simplified
-                    let status = self.read_byte(memory, self.sp_to_address(), &mut cycles);
-                    self.status.carry = (status & 0x01) != 0;
-                    self.status.zero = (status & 0x02) != 0;
-                    self.status.interrupt_disable = (status & 0x04) != 0;
-                    self.status.decimal_mode = (status & 0x08) != 0;
-                    self.status.break_command = (status & 0x10) != 0;
-                    self.status.overflow = (status & 0x40) != 0;
-                    self.status.negative = (status & 0x80) != 0;
-                    self.pc = self.read_word(memory, self.sp_to_address(), &mut cycles);
-                }
-
-                // --- Logical Ops: AND, ORA, EOR, BIT ---
-                INS_AND_IM => {
-                    let value = self.fetch_byte(memory, &mut cycles);
-                    self.reg_a &= value;
-                    self.set_zero_and_negative_flags(self.reg_a);
-                }
-                INS_AND_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-                INS_AND_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    self.and(addr, memory, &mut cycles);
-                }
-
-                INS_ORA_IM => {
-                    let value = self.fetch_byte(memory, &mut cycles);
-                    self.reg_a |= value;
-                    self.set_zero_and_negative_flags(self.reg_a);
-                }
-                INS_ORA_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-                INS_ORA_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    self.ora(addr, memory, &mut cycles);
-                }
-
-                INS_EOR_IM => {
-                    let value = self.fetch_byte(memory, &mut cycles);
-                    self.reg_a ^= value;
-                    self.set_zero_and_negative_flags(self.reg_a);
-                }
-                INS_EOR_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-                INS_EOR_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    self.eor(addr, memory, &mut cycles);
-                }
-
-                INS_BIT_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let value = self.read_byte(memory, addr, &mut cycles);
-                    self.status.zero = (self.reg_a & value) == 0;
-                    self.status.negative = (value & 0x80) != 0;
-                    self.status.overflow = (value & 0x40) != 0;
-                }
-                INS_BIT_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let value = self.read_byte(memory, addr, &mut cycles);
-                    self.status.zero = (self.reg_a & value) == 0;
-                    self.status.negative = (value & 0x80) != 0;
-                    self.status.overflow = (value & 0x40) != 0;
-                }
-
-                // --- Arithmetic ---
-                INS_ADC => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-                INS_ADC_INDY => {
-                    self.fetch_byte(memory, &mut cycles); // Dummy read for timing
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.adc(operand);
-                }
-
-                INS_SBC => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-                INS_SBC_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.sbc(operand);
-                }
-
-                // --- Comparison ---
-                INS_CMP => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_ABSY => {
-                    let addr = self.addr_absolute_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_INDX => {
-                    let addr = self.addr_indirect_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-                INS_CMP_INDY => {
-                    let addr = self.addr_indirect_y(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_a);
-                }
-
-                INS_CPX => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    self.cmp(operand, self.reg_x);
-                }
-                INS_CPX_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_x);
-                }
-                INS_CPX_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_x);
-                }
-                INS_CPY => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    self.cmp(operand, self.reg_y);
-                }
-                INS_CPY_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_y);
-                }
-                INS_CPY_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    self.cmp(operand, self.reg_y);
-                }
-
-                // --- Increments/Decrements ---
-                INS_INX => {
-                    self.reg_x = self.reg_x.wrapping_add(1);
-                    self.set_zero_and_negative_flags(self.reg_x);
-                    cycles -= 1;
-                }
-                INS_INY => {
-                    self.reg_y = self.reg_y.wrapping_add(1);
-                    self.set_zero_and_negative_flags(self.reg_y);
-                    cycles -= 1;
-                }
-                INS_DEX => {
-                    self.reg_x = self.reg_x.wrapping_sub(1);
-                    self.set_zero_and_negative_flags(self.reg_x);
-                    cycles -= 1;
-                }
-                INS_DEY => {
-                    self.reg_y = self.reg_y.wrapping_sub(1);
-                    self.set_zero_and_negative_flags(self.reg_y);
-                    cycles -= 1;
-                }
-                INS_DEC_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_sub(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_DEC_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_sub(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_DEC_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_sub(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_DEC_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_sub(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_INC_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_add(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_INC_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_add(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_INC_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_add(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-                INS_INC_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let tmp = self.read_byte(memory, addr, &mut cycles).wrapping_add(1);
-                    self.write_byte(memory, addr, tmp, &mut cycles);
-                    self.set_zero_and_negative_flags(tmp);
-                }
-
-                // --- Shifts ---
-                INS_ASL => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    let result = self.asl(operand, &mut cycles);
-                    // For accumulator mode, update A.
-                    self.reg_a = result;
-                }
-                INS_ASL_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.asl(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ASL_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.asl(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ASL_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.asl(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ASL_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.asl(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-
-                INS_LSR => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    let result = self.lsr(operand, &mut cycles);
-                    self.reg_a = result;
-                }
-                INS_LSR_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.lsr(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_LSR_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.lsr(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_LSR_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.lsr(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_LSR_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.lsr(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-
-                INS_ROL => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    let result = self.rol(operand, &mut cycles);
-                    self.reg_a = result;
-                }
-                INS_ROL_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.rol(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROL_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.rol(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROL_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.rol(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROL_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.rol(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-
-                INS_ROR => {
-                    let operand = self.fetch_byte(memory, &mut cycles);
-                    let result = self.ror(operand, &mut cycles);
-                    self.reg_a = result;
-                }
-                INS_ROR_ZP => {
-                    let addr = self.addr_zero_page(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.ror(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROR_ZPX => {
-                    let addr = self.addr_zero_page_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.ror(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROR_ABS => {
-                    let addr = self.addr_absolute(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.ror(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-                INS_ROR_ABSX => {
-                    let addr = self.addr_absolute_x(&mut cycles, memory);
-                    let operand = self.read_byte(memory, addr, &mut cycles);
-                    let result = self.ror(operand, &mut cycles);
-                    self.write_byte(memory, addr, result, &mut cycles);
-                }
-
-                // --- Flag and Status Changes ---
-                INS_CLC => {
-                    self.status.carry = false;
-                    cycles -= 1;
-                }
-                INS_SEC => {
-                    self.status.carry = true;
-                    cycles -= 1;
-                }
-                INS_CLD => {
-                    self.status.decimal_mode = false;
-                    cycles -= 1;
-                }
-                INS_SED => {
-                    self.status.decimal_mode = true;
-                    cycles -= 1;
-                }
-                INS_CLI => {
-                    self.status.interrupt_disable = false;
-                    cycles -= 1;
-                }
-                INS_SEI => {
-                    self.status.interrupt_disable = true;
-                    cycles -= 1;
-                }
-                INS_CLV => {
-                    self.status.overflow = false;
-                    cycles -= 1;
-                }
-
-                // --- No Operation ---
-                INS_NOP => {
-                    cycles -= 1;
-                }
-
-                _ => {
-                    panic!("Instruction {:02X} not implemented", opcode);
-                }
+            if let Some(handler) = dispatch::HANDLERS[opcode as usize] {
+                handler(self, memory, &mut cycles);
+            } else if self.variant.is_cmos() {
+                self.execute_cmos(opcode, memory, &mut cycles);
+            } else if self.illegal_opcodes {
+                self.execute_illegal(opcode, memory, &mut cycles);
+            } else {
+                panic!("Instruction {:02X} not implemented", opcode);
             }
         }
         cycles_requested - cycles
@@ -0,0 +1,274 @@
+use crate::{Byte, MAX_MEM, Memory, Word, xex};
+use std::fmt;
+
+/// Program file formats the loaders understand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A plain memory image with no addresses of its own.
+    Raw,
+    /// A two-byte little-endian load address followed by the payload, as in
+    /// Commodore PRG files and cc65 output. `detect` cannot tell these from raw
+    /// binaries.
+    Prg,
+    IntelHex,
+    SRecord,
+    /// A 6502 ELF executable, such as llvm-mos produces.
+    Elf,
+    /// An iNES or NES 2.0 cartridge image, which runs on an NES memory map
+    /// rather than in flat memory.
+    INes,
+    /// An Atari DOS executable: segments behind a $FFFF header, with init and
+    /// run addresses.
+    Xex,
+}
+
+impl Format {
+    /// Guesses the format of a program file from its contents: files starting
+    /// with the ELF or iNES magic are ELF or iNES, files that split exactly into
+    /// Atari executable segments behind $FFFF are XEX, text whose first record
+    /// starts with `:` is Intel
+    /// HEX, text starting with `S0`-`S9` is Motorola S-records, and anything
+    /// else is a raw binary.
+    pub fn detect(bytes: &[u8]) -> Format {
+        if bytes.starts_with(b"\x7FELF") {
+            return Format::Elf;
+        }
+        if bytes.starts_with(b"NES\x1A") {
+            return Format::INes;
+        }
+        // Raw images padded with $FF start with $FFFF too
+        if bytes.starts_with(&[0xFF, 0xFF]) && xex::segments(bytes).is_ok_and(|segments| !segments.is_empty()) {
+            return Format::Xex;
+        }
+        if !bytes.iter().all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace()) {
+            return Format::Raw;
+        }
+        match bytes.trim_ascii_start() {
+            [b':', digit, ..] if digit.is_ascii_hexdigit() => Format::IntelHex,
+            [b'S', digit, ..] if digit.is_ascii_digit() => Format::SRecord,
+            _ => Format::Raw,
+        }
+    }
+}
+
+/// Why a program file could not be loaded. Lines are numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// A record that is not well-formed, e.g. odd hex digits or a length that
+    /// does not match its byte count.
+    Malformed { line: usize, reason: &'static str },
+    /// The record's checksum byte does not match its contents.
+    Checksum { line: usize, expected: Byte, found: Byte },
+    UnsupportedRecord { line: usize, record: String },
+    /// Data or a start address beyond the 64K address space.
+    OutOfRange { line: usize, addr: u32 },
+    /// An S5/S6 record whose count differs from the data records before it.
+    RecordCount { line: usize, expected: u32, found: u32 },
+    /// The file ended without an Intel HEX end-of-file record, so it may be truncated.
+    MissingEnd,
+    /// A binary file that ends in the middle of a header, at this offset.
+    Truncated { offset: usize },
+    /// A payload that runs past the end of memory.
+    TooLarge { addr: Word, len: usize },
+    /// A binary file whose headers are not valid for its format.
+    Invalid(String),
+    /// A relocatable module refers to a symbol that nothing exports.
+    UndefinedSymbol(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Malformed { line, reason } => write!(f, "line {}: {}", line, reason),
+            LoadError::Checksum { line, expected, found } => {
+                write!(f, "line {}: checksum is ${:02X}, expected ${:02X}", line, found, expected)
+            }
+            LoadError::UnsupportedRecord { line, record } => {
+                write!(f, "line {}: unsupported record type {}", line, record)
+            }
+            LoadError::OutOfRange { line, addr } => {
+                write!(f, "line {}: address ${:X} is outside the 64K address space", line, addr)
+            }
+            LoadError::RecordCount { line, expected, found } => {
+                write!(f, "line {}: record count is {}, but {} data records precede it", line, expected, found)
+            }
+            LoadError::MissingEnd => write!(f, "missing end-of-file record"),
+            LoadError::Truncated { offset } => write!(f, "file is truncated at offset {}", offset),
+            LoadError::TooLarge { addr, len } => {
+                write!(f, "{} bytes at ${:04X} run past the end of memory", len, addr)
+            }
+            LoadError::Invalid(reason) => write!(f, "{}", reason),
+            LoadError::UndefinedSymbol(name) => write!(f, "undefined symbol '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Data records found while parsing, written to memory only once the whole
+/// file has been checked.
+#[derive(Default)]
+struct Image {
+    chunks: Vec<(Word, Vec<Byte>)>,
+    entry: Option<Word>,
+}
+
+impl Image {
+    fn add(&mut self, line: usize, addr: u32, data: &[Byte]) -> Result<(), LoadError> {
+        let end = addr as u64 + data.len() as u64;
+        if end > MAX_MEM as u64 {
+            return Err(LoadError::OutOfRange { line, addr: addr.max(MAX_MEM as u32) });
+        }
+        self.chunks.push((addr as Word, data.to_vec()));
+        Ok(())
+    }
+
+    fn set_entry(&mut self, line: usize, addr: u32) -> Result<(), LoadError> {
+        let entry = Word::try_from(addr).map_err(|_| LoadError::OutOfRange { line, addr })?;
+        self.entry = Some(entry);
+        Ok(())
+    }
+
+    fn write(self, memory: &mut Memory) -> Option<Word> {
+        for (addr, data) in self.chunks {
+            let addr = addr as usize;
+            memory.data[addr..addr + data.len()].copy_from_slice(&data);
+        }
+        self.entry
+    }
+}
+
+/// Copies a raw image to `addr`.
+pub fn load_raw(bytes: &[u8], addr: Word, memory: &mut Memory) -> Result<(), LoadError> {
+    let start = addr as usize;
+    if start + bytes.len() > MAX_MEM {
+        return Err(LoadError::TooLarge { addr, len: bytes.len() });
+    }
+    memory.data[start..start + bytes.len()].copy_from_slice(bytes);
+    Ok(())
+}
+
+/// Loads a file with a two-byte load address header, such as a Commodore PRG.
+/// The payload goes to `load_addr` if given, or to the address in the header,
+/// and the address it went to is returned.
+pub fn load_prg(bytes: &[u8], load_addr: Option<Word>, memory: &mut Memory) -> Result<Word, LoadError> {
+    let [low, high, payload @ ..] = bytes else {
+        return Err(LoadError::Truncated { offset: bytes.len() });
+    };
+    let addr = load_addr.unwrap_or(Word::from_le_bytes([*low, *high]));
+    load_raw(payload, addr, memory)?;
+    Ok(addr)
+}
+
+/// The non-blank lines of a text file, numbered from 1, without surrounding whitespace.
+fn records(text: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    text.split(|&b| b == b'\n')
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_ascii()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Decodes a run of hex digit pairs.
+fn decode_hex(digits: &[u8], line: usize) -> Result<Vec<Byte>, LoadError> {
+    if !digits.len().is_multiple_of(2) {
+        return Err(LoadError::Malformed { line, reason: "odd number of hex digits" });
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            core::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| Byte::from_str_radix(pair, 16).ok())
+                .ok_or(LoadError::Malformed { line, reason: "invalid hex digit" })
+        })
+        .collect()
+}
+
+/// Big-endian value of up to four bytes.
+fn big_endian(bytes: &[Byte]) -> u32 {
+    bytes.iter().fold(0, |value, &b| value << 8 | b as u32)
+}
+
+/// Loads an Intel HEX file into memory. Supports data, end-of-file, extended
+/// segment and linear address records, and returns the address from a start
+/// segment or start linear address record, if there is one.
+///
+/// Every record is checked before memory is written, so on error memory is
+/// left as it was.
+pub fn load_intel_hex(text: &[u8], memory: &mut Memory) -> Result<Option<Word>, LoadError> {
+    let mut image = Image::default();
+    let mut base: u32 = 0;
+    for (line, record) in records(text) {
+        let Some(digits) = record.strip_prefix(b":") else {
+            return Err(LoadError::Malformed { line, reason: "record does not start with ':'" });
+        };
+        let bytes = decode_hex(digits, line)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(LoadError::Malformed { line, reason: "record length does not match its byte count" });
+        }
+        let (body, found) = bytes.split_at(bytes.len() - 1);
+        let expected = body.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)).wrapping_neg();
+        if found[0] != expected {
+            return Err(LoadError::Checksum { line, expected, found: found[0] });
+        }
+        let addr = big_endian(&body[1..3]);
+        let data = &body[4..];
+        match (body[3], data.len()) {
+            (0x00, _) => image.add(line, base + addr, data)?,
+            (0x01, _) => return Ok(image.write(memory)),
+            (0x02, 2) => base = big_endian(data) << 4,
+            (0x03, 4) => image.set_entry(line, (big_endian(&data[..2]) << 4) + big_endian(&data[2..]))?,
+            (0x04, 2) => base = big_endian(data) << 16,
+            (0x05, 4) => image.set_entry(line, big_endian(data))?,
+            (0x02..=0x05, _) => return Err(LoadError::Malformed { line, reason: "wrong data length for record type" }),
+            (record, _) => return Err(LoadError::UnsupportedRecord { line, record: format!("{:02X}", record) }),
+        }
+    }
+    Err(LoadError::MissingEnd)
+}
+
+/// Loads a Motorola S-record file into memory. S1-S3 data records are placed
+/// at their addresses, S5/S6 counts are checked, and the address from an
+/// S7-S9 termination record is returned. The S0 header is ignored.
+///
+/// Every record is checked before memory is written, so on error memory is
+/// left as it was.
+pub fn load_srecord(text: &[u8], memory: &mut Memory) -> Result<Option<Word>, LoadError> {
+    let mut image = Image::default();
+    let mut data_records = 0;
+    for (line, record) in records(text) {
+        let (kind, digits) = match record {
+            [b'S', kind, digits @ ..] if kind.is_ascii_digit() => (kind - b'0', digits),
+            _ => return Err(LoadError::Malformed { line, reason: "record does not start with 'S' and a digit" }),
+        };
+        let bytes = decode_hex(digits, line)?;
+        let addr_len = match kind {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => return Err(LoadError::UnsupportedRecord { line, record: format!("S{}", kind) }),
+        };
+        if bytes.len() < addr_len + 2 || bytes.len() != bytes[0] as usize + 1 {
+            return Err(LoadError::Malformed { line, reason: "record length does not match its byte count" });
+        }
+        let (body, found) = bytes.split_at(bytes.len() - 1);
+        let expected = !body.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        if found[0] != expected {
+            return Err(LoadError::Checksum { line, expected, found: found[0] });
+        }
+        let addr = big_endian(&body[1..1 + addr_len]);
+        let data = &body[1 + addr_len..];
+        match kind {
+            1..=3 => {
+                image.add(line, addr, data)?;
+                data_records += 1;
+            }
+            5 | 6 if addr != data_records => {
+                return Err(LoadError::RecordCount { line, expected: addr, found: data_records });
+            }
+            7..=9 => image.set_entry(line, addr)?,
+            _ => {}
+        }
+    }
+    Ok(image.write(memory))
+}
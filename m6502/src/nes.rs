@@ -0,0 +1,369 @@
+//! The CPU side of the NES: iNES cartridges, the CPU memory map with stub
+//! PPU and APU registers, and a harness for test ROMs that report via $6000.
+
+use crate::loader::LoadError;
+use crate::{Bus, Byte, Cpu, Word};
+
+const HEADER_LEN: usize = 16;
+const TRAINER_LEN: usize = 512;
+const PRG_BANK_LEN: usize = 0x4000;
+const CHR_BANK_LEN: usize = 0x2000;
+
+/// Nametable layout wired on the cartridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+/// A cartridge image from an iNES or NES 2.0 file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct INesRom {
+    pub mapper: u16,
+    /// Always 0 in iNES files.
+    pub submapper: Byte,
+    /// Whether the header is NES 2.0 rather than iNES.
+    pub nes2: bool,
+    pub mirroring: Mirroring,
+    /// Whether PRG RAM is battery-backed.
+    pub battery: bool,
+    /// 512 bytes meant for $7000-$71FF, if the file has them.
+    pub trainer: Option<Vec<Byte>>,
+    pub prg_rom: Vec<Byte>,
+    pub chr_rom: Vec<Byte>,
+    /// Bytes of PRG RAM at $6000, volatile and battery-backed together.
+    pub prg_ram_len: usize,
+}
+
+impl INesRom {
+    pub fn parse(bytes: &[Byte]) -> Result<INesRom, LoadError> {
+        let header = bytes.get(..HEADER_LEN).ok_or(LoadError::Truncated { offset: bytes.len() })?;
+        if !header.starts_with(b"NES\x1A") {
+            return Err(LoadError::Invalid("not an iNES file".to_string()));
+        }
+        let (flags6, flags7) = (header[6], header[7]);
+        let nes2 = flags7 & 0x0C == 0x08;
+        let mirroring = match (flags6 & 0x08 != 0, flags6 & 0x01 != 0) {
+            (true, _) => Mirroring::FourScreen,
+            (false, true) => Mirroring::Vertical,
+            (false, false) => Mirroring::Horizontal,
+        };
+        let (mapper, submapper, prg_len, chr_len, prg_ram_len);
+        if nes2 {
+            mapper = (flags6 >> 4) as u16 | (flags7 & 0xF0) as u16 | ((header[8] & 0x0F) as u16) << 8;
+            submapper = header[8] >> 4;
+            prg_len = nes2_rom_len(header[4], header[9] & 0x0F, PRG_BANK_LEN)?;
+            chr_len = nes2_rom_len(header[5], header[9] >> 4, CHR_BANK_LEN)?;
+            prg_ram_len = [header[10] & 0x0F, header[10] >> 4]
+                .into_iter()
+                .map(|shift| if shift == 0 { 0 } else { 64 << shift })
+                .sum();
+        } else {
+            // Old dumps may have a signature such as "DiskDude!" from byte 7 on
+            let high = if header[12..].iter().all(|&b| b == 0) { flags7 & 0xF0 } else { 0 };
+            mapper = (flags6 >> 4 | high) as u16;
+            submapper = 0;
+            prg_len = header[4] as usize * PRG_BANK_LEN;
+            chr_len = header[5] as usize * CHR_BANK_LEN;
+            prg_ram_len = header[8].max(1) as usize * 0x2000;
+        }
+
+        let mut offset = HEADER_LEN;
+        let mut take = |len: usize| {
+            let chunk = bytes.get(offset..offset + len).ok_or(LoadError::Truncated { offset: bytes.len() })?;
+            offset += len;
+            Ok(chunk.to_vec())
+        };
+        let trainer = if flags6 & 0x04 != 0 { Some(take(TRAINER_LEN)?) } else { None };
+        let prg_rom = take(prg_len)?;
+        let chr_rom = take(chr_len)?;
+        Ok(INesRom {
+            mapper,
+            submapper,
+            nes2,
+            mirroring,
+            battery: flags6 & 0x02 != 0,
+            trainer,
+            prg_rom,
+            chr_rom,
+            prg_ram_len,
+        })
+    }
+
+    /// The cartridge hardware for this ROM. Only NROM (mapper 0) is supported.
+    pub fn mapper(&self) -> Result<Box<dyn Mapper>, LoadError> {
+        match self.mapper {
+            0 => Ok(Box::new(Nrom::new(self)?)),
+            mapper => Err(LoadError::Invalid(format!("mapper {} is not supported", mapper))),
+        }
+    }
+}
+
+/// A NES 2.0 ROM size: `lsb` banks plus `msb` << 8, or when `msb` is $F, the
+/// exponent-multiplier form 2^E * (MM * 2 + 1) with `lsb` as EEEEEEMM.
+fn nes2_rom_len(lsb: Byte, msb: Byte, bank_len: usize) -> Result<usize, LoadError> {
+    if msb != 0x0F {
+        return Ok(((msb as usize) << 8 | lsb as usize) * bank_len);
+    }
+    let (exponent, multiplier) = (lsb >> 2, (lsb & 3) as usize * 2 + 1);
+    1usize
+        .checked_shl(exponent as u32)
+        .and_then(|size| size.checked_mul(multiplier))
+        .filter(|&size| size <= 1 << 24)
+        .ok_or_else(|| LoadError::Invalid(format!("ROM size 2^{} * {} is too large", exponent, multiplier)))
+}
+
+/// Cartridge hardware at $4020-$FFFF.
+pub trait Mapper {
+    /// The byte at `addr`, or `None` where nothing drives the bus. Reads must
+    /// not have side effects.
+    fn read(&self, addr: Word) -> Option<Byte>;
+    fn write(&mut self, addr: Word, value: Byte);
+}
+
+/// Mapper 0: 16K or 32K of PRG ROM at $8000, a 16K ROM mirrored at $C000, and
+/// 8K of PRG RAM at $6000 as on Family BASIC carts and test ROMs.
+pub struct Nrom {
+    prg_rom: Vec<Byte>,
+    prg_ram: Vec<Byte>,
+}
+
+impl Nrom {
+    pub fn new(rom: &INesRom) -> Result<Nrom, LoadError> {
+        if rom.prg_rom.len() != PRG_BANK_LEN && rom.prg_rom.len() != 2 * PRG_BANK_LEN {
+            return Err(LoadError::Invalid(format!("NROM needs 16K or 32K of PRG ROM, not {} bytes", rom.prg_rom.len())));
+        }
+        let mut prg_ram = vec![0; 0x2000];
+        if let Some(trainer) = &rom.trainer {
+            prg_ram[0x1000..0x1000 + TRAINER_LEN].copy_from_slice(trainer);
+        }
+        Ok(Nrom { prg_rom: rom.prg_rom.clone(), prg_ram })
+    }
+}
+
+impl Mapper for Nrom {
+    fn read(&self, addr: Word) -> Option<Byte> {
+        match addr {
+            0x6000..=0x7FFF => Some(self.prg_ram[addr as usize - 0x6000]),
+            0x8000..=0xFFFF => Some(self.prg_rom[(addr as usize - 0x8000) % self.prg_rom.len()]),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        if let 0x6000..=0x7FFF = addr {
+            self.prg_ram[addr as usize - 0x6000] = value;
+        }
+    }
+}
+
+/// NTSC CPU cycles per frame, rounding the odd half cycle up.
+const CYCLES_PER_FRAME: u32 = 29781;
+/// Cycles into the frame at which vblank starts (scanline 241) and ends
+/// (the pre-render scanline).
+const VBLANK_START: u32 = 241 * 341 / 3;
+const VBLANK_END: u32 = 261 * 341 / 3;
+
+/// The PPU registers at $2000-$2007, without any rendering. The vblank flag
+/// and NMI follow NTSC frame timing so that code waiting for vblank runs on.
+#[derive(Clone, Debug, Default)]
+pub struct Ppu {
+    pub ctrl: Byte,
+    pub mask: Byte,
+    status: Byte,
+    /// Last value written to any register, which reads of the write-only
+    /// registers return.
+    latch: Byte,
+    cycle: u32,
+    nmi: bool,
+}
+
+impl Ppu {
+    fn tick(&mut self) {
+        self.cycle = (self.cycle + 1) % CYCLES_PER_FRAME;
+        if self.cycle == VBLANK_START {
+            self.status |= 0x80;
+            self.nmi |= self.ctrl & 0x80 != 0;
+        } else if self.cycle == VBLANK_END {
+            self.status &= !0x80;
+        }
+    }
+
+    fn peek(&self, register: Word) -> Byte {
+        match register {
+            2 => self.status & 0xE0 | self.latch & 0x1F,
+            _ => self.latch,
+        }
+    }
+
+    fn read(&mut self, register: Word) -> Byte {
+        let value = self.peek(register);
+        if register == 2 {
+            self.status &= !0x80;
+        }
+        value
+    }
+
+    fn write(&mut self, register: Word, value: Byte) {
+        self.latch = value;
+        match register {
+            0 => {
+                // Enabling NMI during vblank raises it straight away
+                self.nmi |= value & 0x80 != 0 && self.ctrl & 0x80 == 0 && self.status & 0x80 != 0;
+                self.ctrl = value;
+            }
+            1 => self.mask = value,
+            _ => {}
+        }
+    }
+
+    /// Whether vblank started with NMI enabled, or NMI was enabled during
+    /// vblank, since the last call.
+    pub fn take_nmi(&mut self) -> bool {
+        core::mem::take(&mut self.nmi)
+    }
+}
+
+/// The APU and I/O registers at $4000-$4017, without sound. Writes are kept,
+/// the status register reads as silent and the controllers as not pressed.
+#[derive(Clone, Debug, Default)]
+pub struct Apu {
+    pub registers: [Byte; 0x18],
+}
+
+/// The NES CPU memory map: 2K of RAM mirrored through $1FFF, the PPU registers
+/// mirrored through $3FFF, the APU and I/O registers, and the cartridge from
+/// $4020. Every access is one CPU cycle for the `tick()` engine.
+pub struct NesBus {
+    pub ram: [Byte; 0x800],
+    pub ppu: Ppu,
+    pub apu: Apu,
+    pub mapper: Box<dyn Mapper>,
+    /// Last value on the data bus, which unmapped reads return.
+    open_bus: Byte,
+    cycles: u64,
+}
+
+impl NesBus {
+    pub fn new(mapper: Box<dyn Mapper>) -> Self {
+        NesBus { ram: [0; 0x800], ppu: Ppu::default(), apu: Apu::default(), mapper, open_bus: 0, cycles: 0 }
+    }
+
+    /// Reads `addr` as the CPU would, without side effects and without a
+    /// cycle passing.
+    pub fn peek(&self, addr: Word) -> Byte {
+        match addr {
+            0x0000..=0x1FFF => self.ram[addr as usize & 0x7FF],
+            0x2000..=0x3FFF => self.ppu.peek(addr & 7),
+            0x4015 => 0,
+            0x4016 | 0x4017 => self.open_bus & 0xE0,
+            0x4000..=0x401F => self.open_bus,
+            _ => self.mapper.read(addr).unwrap_or(self.open_bus),
+        }
+    }
+
+    /// CPU cycles since power-on.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        self.ppu.tick();
+    }
+}
+
+impl Bus for NesBus {
+    fn read(&mut self, addr: Word) -> Byte {
+        self.tick();
+        let value = match addr {
+            0x2000..=0x3FFF => self.ppu.read(addr & 7),
+            _ => self.peek(addr),
+        };
+        self.open_bus = value;
+        value
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        self.tick();
+        self.open_bus = value;
+        match addr {
+            0x0000..=0x1FFF => self.ram[addr as usize & 0x7FF] = value,
+            0x2000..=0x3FFF => self.ppu.write(addr & 7, value),
+            // OAM DMA at $4014 is recorded but takes no time
+            0x4000..=0x4017 => self.apu.registers[addr as usize - 0x4000] = value,
+            0x4018..=0x401F => {}
+            _ => self.mapper.write(addr, value),
+        }
+    }
+}
+
+/// Status byte of the test ROM protocol while the test runs.
+const STATUS_RUNNING: Byte = 0x80;
+/// Status byte asking for the reset button to be pressed.
+const STATUS_RESET: Byte = 0x81;
+/// Written to $6001-$6003 once $6000 and the text are valid.
+const SIGNATURE: [Byte; 3] = [0xDE, 0xB0, 0x61];
+/// How long to hold off a requested reset; the protocol asks for at least 100 ms.
+const RESET_DELAY: u64 = 6 * CYCLES_PER_FRAME as u64;
+
+/// How a test ROM run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestRomOutcome {
+    /// The ROM finished with this result code; 0 means it passed.
+    Finished(Byte),
+    /// `max_cycles` passed before the ROM finished.
+    TimedOut,
+    Jammed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestRomReport {
+    pub outcome: TestRomOutcome,
+    /// The zero-terminated text at $6004.
+    pub text: String,
+    pub cycles: u64,
+}
+
+/// Runs a test ROM that reports through $6000, as blargg's CPU tests do: once
+/// $6001-$6003 hold $DE $B0 $61, $6000 is $80 while the test runs, $81 when it
+/// wants a reset, and otherwise the result code, with the text at $6004.
+///
+/// Resets the CPU, then runs it on the `tick()` engine until the result is in
+/// or `max_cycles` bus cycles have passed. Requested resets are pressed after
+/// six frames. Most test ROMs need `cpu.illegal_opcodes` set.
+pub fn run_test_rom(cpu: &mut Cpu, bus: &mut NesBus, max_cycles: u64) -> TestRomReport {
+    cpu.reset_bus(bus);
+    let mut reset_at = None;
+    let outcome = loop {
+        if cpu.jammed {
+            break TestRomOutcome::Jammed;
+        }
+        if bus.cycles() >= max_cycles {
+            break TestRomOutcome::TimedOut;
+        }
+        if bus.ppu.take_nmi() {
+            cpu.trigger_nmi();
+        }
+        cpu.tick_instruction(bus);
+
+        if (1..4).any(|i| bus.peek(0x6000 + i) != SIGNATURE[i as usize - 1]) {
+            continue;
+        }
+        match bus.peek(0x6000) {
+            STATUS_RUNNING => reset_at = None,
+            STATUS_RESET => match reset_at {
+                None => reset_at = Some(bus.cycles() + RESET_DELAY),
+                Some(at) if bus.cycles() >= at => {
+                    cpu.reset_bus(bus);
+                    reset_at = None;
+                }
+                Some(_) => {}
+            },
+            result => break TestRomOutcome::Finished(result),
+        }
+    };
+    let text: Vec<Byte> = (0x6004..0x8000).map(|addr| bus.peek(addr)).take_while(|&b| b != 0).collect();
+    TestRomReport { outcome, text: String::from_utf8_lossy(&text).into_owned(), cycles: bus.cycles() }
+}
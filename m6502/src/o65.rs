@@ -0,0 +1,351 @@
+use crate::loader::LoadError;
+use crate::{Byte, CpuVariant, MAX_MEM, Memory, Symbols, Word};
+
+/// Non-C64 marker, "o65" and version 0.
+const MAGIC: [Byte; 6] = [0x01, 0x00, b'o', b'6', b'5', 0x00];
+
+const MODE_65816: u16 = 0x8000;
+/// Segments may only move by whole pages, so HIGH relocations omit their low byte.
+const MODE_PAGEWISE: u16 = 0x4000;
+const MODE_32BIT: u16 = 0x2000;
+const MODE_CHAIN: u16 = 0x0400;
+const MODE_BSSZERO: u16 = 0x0200;
+
+const RELOC_WORD: Byte = 0x80;
+const RELOC_HIGH: Byte = 0x40;
+const RELOC_LOW: Byte = 0x20;
+
+const SEGMENT_UNDEFINED: Byte = 0;
+const SEGMENT_ABSOLUTE: Byte = 1;
+const SEGMENT_TEXT: Byte = 2;
+const SEGMENT_DATA: Byte = 3;
+const SEGMENT_BSS: Byte = 4;
+const SEGMENT_ZERO: Byte = 5;
+const SEGMENT_NAMES: [&str; 6] = ["undefined", "absolute", "text", "data", "bss", "zero page"];
+
+/// The processor an o65 file was assembled for, from the CPU bits of its mode word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum O65Cpu {
+    /// The documented NMOS 6502 instructions, which every variant runs.
+    Mos6502,
+    /// A 65C02 without new instructions, relying on its bug fixes such as
+    /// `JMP ($xxFF)`.
+    Cmos65C02,
+    /// The 65SC02 instruction set: the 65C02 additions without the bit
+    /// instructions.
+    Cmos65SC02,
+    Cmos65CE02,
+    /// The NMOS 6502 including undocumented opcodes.
+    Nmos6502Undocumented,
+    /// The 65816 in 6502 emulation mode.
+    W65816Emulation,
+    /// The 65816 in native mode (mode bit 15).
+    W65816,
+    /// A CPU type this crate does not know, e.g. 6809.
+    Other(Byte),
+}
+
+impl O65Cpu {
+    fn from_mode(mode: u16) -> O65Cpu {
+        if mode & MODE_65816 != 0 {
+            return O65Cpu::W65816;
+        }
+        match (mode >> 4) as Byte & 0x0F {
+            0 => O65Cpu::Mos6502,
+            1 => O65Cpu::Cmos65C02,
+            2 => O65Cpu::Cmos65SC02,
+            3 => O65Cpu::Cmos65CE02,
+            4 => O65Cpu::Nmos6502Undocumented,
+            5 => O65Cpu::W65816Emulation,
+            other => O65Cpu::Other(other),
+        }
+    }
+
+    /// Whether a Cpu of `variant` runs every instruction the file may use.
+    /// Undocumented opcodes also need `Cpu::illegal_opcodes`.
+    pub fn runs_on(self, variant: CpuVariant) -> bool {
+        match self {
+            O65Cpu::Mos6502 => true,
+            O65Cpu::Cmos65C02 | O65Cpu::Cmos65SC02 => variant.is_cmos(),
+            O65Cpu::Nmos6502Undocumented => !variant.is_cmos(),
+            _ => false,
+        }
+    }
+}
+
+/// Start addresses of the four o65 segments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct O65Layout {
+    pub text: Word,
+    pub data: Word,
+    pub bss: Word,
+    pub zero: Word,
+}
+
+impl O65Layout {
+    fn segment(&self, id: Byte) -> Word {
+        match id {
+            SEGMENT_TEXT => self.text,
+            SEGMENT_DATA => self.data,
+            SEGMENT_BSS => self.bss,
+            _ => self.zero,
+        }
+    }
+}
+
+/// The header of an o65 file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct O65Header {
+    pub mode: u16,
+    pub cpu: O65Cpu,
+    /// Where the segments were assembled to run.
+    pub layout: O65Layout,
+    pub text_len: Word,
+    pub data_len: Word,
+    pub bss_len: Word,
+    pub zero_len: Word,
+    /// Stack bytes the module needs, 0 if unknown.
+    pub stack: Word,
+    /// Header options as type and data, e.g. type 0 is the file name.
+    pub options: Vec<(Byte, Vec<Byte>)>,
+}
+
+impl O65Header {
+    /// Parses the header, so that a loader can size the segments before
+    /// choosing where they go.
+    pub fn parse(bytes: &[Byte]) -> Result<O65Header, LoadError> {
+        O65Header::read(&mut Cursor { bytes, offset: 0 })
+    }
+
+    fn read(file: &mut Cursor) -> Result<O65Header, LoadError> {
+        if file.take(MAGIC.len())? != MAGIC {
+            return Err(LoadError::Invalid("not an o65 file".to_string()));
+        }
+        let mode = file.word()?;
+        if mode & MODE_32BIT != 0 {
+            return Err(LoadError::Invalid("only o65 files with 16-bit addresses are supported".to_string()));
+        }
+        let (text, text_len) = (file.word()?, file.word()?);
+        let (data, data_len) = (file.word()?, file.word()?);
+        let (bss, bss_len) = (file.word()?, file.word()?);
+        let (zero, zero_len) = (file.word()?, file.word()?);
+        let stack = file.word()?;
+        let mut options = Vec::new();
+        loop {
+            // The length includes itself and the type byte
+            let len = file.byte()? as usize;
+            if len == 0 {
+                break;
+            }
+            if len < 2 {
+                return Err(LoadError::Invalid(format!("header option of {} bytes", len)));
+            }
+            let kind = file.byte()?;
+            options.push((kind, file.take(len - 2)?.to_vec()));
+        }
+        Ok(O65Header {
+            mode,
+            cpu: O65Cpu::from_mode(mode),
+            layout: O65Layout { text, data, bss, zero },
+            text_len,
+            data_len,
+            bss_len,
+            zero_len,
+            stack,
+            options,
+        })
+    }
+
+    /// Bytes per alignment unit that segment addresses must keep.
+    fn alignment(&self) -> Word {
+        [1, 2, 4, 256][(self.mode & 3) as usize]
+    }
+}
+
+/// Reads an o65 file from the front.
+struct Cursor<'a> {
+    bytes: &'a [Byte],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [Byte], LoadError> {
+        let bytes = self.bytes.get(self.offset..self.offset + len).ok_or(LoadError::Truncated { offset: self.bytes.len() })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<Byte, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<Word, LoadError> {
+        let bytes = self.take(2)?;
+        Ok(Word::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// A zero-terminated name.
+    fn name(&mut self) -> Result<&'a str, LoadError> {
+        let tail = &self.bytes[self.offset..];
+        let len = tail.iter().position(|&b| b == 0).ok_or(LoadError::Truncated { offset: self.bytes.len() })?;
+        self.offset += len + 1;
+        core::str::from_utf8(&tail[..len]).map_err(|_| LoadError::Invalid("symbol name is not UTF-8".to_string()))
+    }
+}
+
+/// How far each segment moves, and the names it imports.
+struct Relocation<'a> {
+    header: &'a O65Header,
+    layout: &'a O65Layout,
+    undefined: Vec<&'a str>,
+    symbols: &'a Symbols,
+}
+
+impl Relocation<'_> {
+    /// What to add to a value that refers to segment `id`.
+    fn delta(&self, id: Byte, file: &mut Cursor) -> Result<Word, LoadError> {
+        match id {
+            SEGMENT_UNDEFINED => {
+                let index = file.word()?;
+                let name = self.undefined.get(index as usize).ok_or_else(|| {
+                    LoadError::Invalid(format!("relocation refers to undefined symbol {} of {}", index, self.undefined.len()))
+                })?;
+                self.symbols.addr(name).ok_or_else(|| LoadError::UndefinedSymbol(name.to_string()))
+            }
+            SEGMENT_ABSOLUTE => Ok(0),
+            SEGMENT_TEXT..=SEGMENT_ZERO => Ok(self.layout.segment(id).wrapping_sub(self.header.layout.segment(id))),
+            _ => Err(LoadError::Invalid(format!("unknown segment {}", id))),
+        }
+    }
+
+    /// Applies the relocation table at `file` to `segment`. Entries step
+    /// forward from one byte before the segment; 255 steps 254 bytes without
+    /// an entry and 0 ends the table.
+    fn apply(&self, segment: &mut [Byte], name: &str, file: &mut Cursor) -> Result<(), LoadError> {
+        let mut position = -1isize;
+        loop {
+            let mut step = file.byte()?;
+            while step == 255 {
+                position += 254;
+                step = file.byte()?;
+            }
+            if step == 0 {
+                return Ok(());
+            }
+            position += step as isize;
+            let kind = file.byte()?;
+            let width = if kind & 0xE0 == RELOC_WORD { 2 } else { 1 };
+            let at = position as usize;
+            if at + width > segment.len() {
+                return Err(LoadError::Invalid(format!("relocation at offset {} is outside the {} segment", at, name)));
+            }
+            match kind & 0xE0 {
+                RELOC_WORD => {
+                    let value = Word::from_le_bytes([segment[at], segment[at + 1]]);
+                    let delta = self.delta(kind & 0x1F, file)?;
+                    segment[at..at + 2].copy_from_slice(&value.wrapping_add(delta).to_le_bytes());
+                }
+                RELOC_HIGH => {
+                    // The low byte of the full value follows, unless segments move by whole pages
+                    let low = if self.header.mode & MODE_PAGEWISE != 0 { 0 } else { file.byte()? };
+                    let delta = self.delta(kind & 0x1F, file)?;
+                    segment[at] = (Word::from_le_bytes([low, segment[at]]).wrapping_add(delta) >> 8) as Byte;
+                }
+                RELOC_LOW => {
+                    let delta = self.delta(kind & 0x1F, file)?;
+                    segment[at] = segment[at].wrapping_add(delta as Byte);
+                }
+                kind => return Err(LoadError::Invalid(format!("unsupported relocation type ${:02X}", kind))),
+            }
+        }
+    }
+}
+
+/// Loads an o65 relocatable module with its segments moved to `layout`.
+/// Text and data are relocated and copied to memory, and BSS is cleared when
+/// the file asks for it. References to undefined symbols are resolved against
+/// `symbols`, and the module's exported globals are added to it at their
+/// relocated addresses, so a module can import what an earlier one exported.
+/// Returns the header; `O65Header::parse` reads it beforehand to size the
+/// segments, and its `layout` loads the module where it was assembled.
+///
+/// The whole file is checked before memory is written, so on error memory and
+/// `symbols` are left as they were.
+pub fn load_o65(
+    bytes: &[Byte],
+    layout: &O65Layout,
+    memory: &mut Memory,
+    symbols: &mut Symbols,
+) -> Result<O65Header, LoadError> {
+    let mut file = Cursor { bytes, offset: 0 };
+    let header = O65Header::read(&mut file)?;
+    if header.mode & MODE_CHAIN != 0 {
+        return Err(LoadError::Invalid("chained o65 files are not supported".to_string()));
+    }
+    let segments = [
+        (SEGMENT_TEXT, header.text_len, MAX_MEM),
+        (SEGMENT_DATA, header.data_len, MAX_MEM),
+        (SEGMENT_BSS, header.bss_len, MAX_MEM),
+        (SEGMENT_ZERO, header.zero_len, 0x100),
+    ];
+    for (id, len, limit) in segments {
+        let (name, addr) = (SEGMENT_NAMES[id as usize], layout.segment(id));
+        if addr as usize + len as usize > limit {
+            return Err(LoadError::Invalid(format!(
+                "{} segment of {} bytes at ${:04X} runs past ${:X}",
+                name,
+                len,
+                addr,
+                limit - 1
+            )));
+        }
+        if len > 0 && !addr.is_multiple_of(header.alignment()) {
+            return Err(LoadError::Invalid(format!(
+                "{} segment at ${:04X} is not aligned to {} bytes",
+                name,
+                addr,
+                header.alignment()
+            )));
+        }
+        // The zero page segment can only move within page zero
+        let moved = addr.wrapping_sub(header.layout.segment(id));
+        if header.mode & MODE_PAGEWISE != 0 && id != SEGMENT_ZERO && moved & 0xFF != 0 {
+            return Err(LoadError::Invalid(format!("{} segment can only move by whole pages", name)));
+        }
+    }
+
+    let mut text = file.take(header.text_len as usize)?.to_vec();
+    let mut data = file.take(header.data_len as usize)?.to_vec();
+    let mut undefined = Vec::new();
+    for _ in 0..file.word()? {
+        undefined.push(file.name()?);
+    }
+    let relocation = Relocation { header: &header, layout, undefined, symbols };
+    relocation.apply(&mut text, "text", &mut file)?;
+    relocation.apply(&mut data, "data", &mut file)?;
+
+    let mut exports = Vec::new();
+    for _ in 0..file.word()? {
+        let name = file.name()?;
+        let id = file.byte()?;
+        let value = file.word()?;
+        if id == SEGMENT_UNDEFINED || id as usize >= SEGMENT_NAMES.len() {
+            return Err(LoadError::Invalid(format!("exported symbol '{}' is in segment {}", name, id)));
+        }
+        let delta = match id {
+            SEGMENT_ABSOLUTE => 0,
+            _ => layout.segment(id).wrapping_sub(header.layout.segment(id)),
+        };
+        exports.push((name, value.wrapping_add(delta)));
+    }
+
+    memory.data[layout.text as usize..][..text.len()].copy_from_slice(&text);
+    memory.data[layout.data as usize..][..data.len()].copy_from_slice(&data);
+    if header.mode & MODE_BSSZERO != 0 {
+        memory.data[layout.bss as usize..][..header.bss_len as usize].fill(0);
+    }
+    for (name, addr) in exports {
+        symbols.insert(name, addr);
+    }
+    Ok(header)
+}
@@ -0,0 +1,164 @@
+use crate::Word;
+use crate::loader::LoadError;
+use std::collections::BTreeMap;
+
+/// Names for addresses, looked up in either direction.
+///
+/// A name has one address. An address can have several names; `name` returns
+/// the first one added.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    addrs: BTreeMap<String, Word>,
+    names: BTreeMap<Word, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `name`, moving it if it already names another address.
+    pub fn insert(&mut self, name: impl Into<String>, addr: Word) {
+        let name = name.into();
+        if let Some(old) = self.addrs.insert(name.clone(), addr)
+            && old != addr
+            && self.names.get(&old) == Some(&name)
+        {
+            self.names.remove(&old);
+            // Fall back to another name for the old address, if it has one
+            if let Some((other, _)) = self.addrs.iter().find(|&(_, &other)| other == old) {
+                self.names.insert(old, other.clone());
+            }
+        }
+        self.names.entry(addr).or_insert(name);
+    }
+
+    /// The address of `name`.
+    pub fn addr(&self, name: &str) -> Option<Word> {
+        self.addrs.get(name).copied()
+    }
+
+    /// The name of `addr`.
+    pub fn name(&self, addr: Word) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    /// Number of names.
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Every name and its address, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Word)> {
+        self.addrs.iter().map(|(name, &addr)| (name.as_str(), addr))
+    }
+
+    /// An address given as `$C000`, `0xC000` or decimal, or as a name.
+    pub fn resolve(&self, text: &str) -> Option<Word> {
+        parse_addr(text).or_else(|| self.addr(text))
+    }
+
+    // --- Symbol files ---
+
+    /// Adds the symbols from a VICE label file, a ca65/ld65 debug file or a
+    /// `name = $addr` list, telling them apart by their first line.
+    pub fn read(&mut self, text: &str) -> Result<(), LoadError> {
+        let first = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+        if first.starts_with("al ") {
+            self.read_vice_labels(text)
+        } else if first.starts_with("version") {
+            self.read_ca65_debug(text)
+        } else {
+            self.read_label_list(text)
+        }
+    }
+
+    /// Adds the labels of a VICE monitor label file, as written by `ld65 -Ln`:
+    /// `al C:0810 .start`.
+    pub fn read_vice_labels(&mut self, text: &str) -> Result<(), LoadError> {
+        self.insert_all(text, |line, record| {
+            let malformed = LoadError::Malformed { line, reason: "expected 'al <address> .<label>'" };
+            let fields: Vec<&str> = record.split_whitespace().collect();
+            let ["al", addr, name] = fields[..] else { return Err(malformed) };
+            let addr = addr.strip_prefix("C:").unwrap_or(addr);
+            let addr = Word::from_str_radix(addr, 16).map_err(|_| LoadError::Malformed { line, reason: "invalid label address" })?;
+            Ok(Some((name.strip_prefix('.').unwrap_or(name), addr)))
+        })
+    }
+
+    /// Adds the labels of an ld65 debug file (`ld65 --dbgfile`), taken from its
+    /// `sym` lines of type `lab`. Constants defined with `=` are skipped, since
+    /// their values are usually not addresses.
+    pub fn read_ca65_debug(&mut self, text: &str) -> Result<(), LoadError> {
+        self.insert_all(text, |line, record| {
+            let Some(("sym", attributes)) = record.split_once(char::is_whitespace) else { return Ok(None) };
+            let attributes: Vec<(&str, &str)> =
+                attributes.trim().split(',').filter_map(|attribute| attribute.split_once('=')).collect();
+            let attribute = |key: &str| attributes.iter().find(|&&(k, _)| k == key).map(|&(_, value)| value);
+            if attribute("type") != Some("lab") {
+                return Ok(None);
+            }
+            let name = attribute("name")
+                .map(|name| name.trim_matches('"'))
+                .ok_or(LoadError::Malformed { line, reason: "symbol without a name" })?;
+            let addr = attribute("val")
+                .and_then(parse_addr)
+                .ok_or(LoadError::Malformed { line, reason: "symbol without a 16-bit value" })?;
+            Ok(Some((name, addr)))
+        })
+    }
+
+    /// Adds the labels of a plain list with one `name = $addr` per line. `;`
+    /// and `#` start comments.
+    pub fn read_label_list(&mut self, text: &str) -> Result<(), LoadError> {
+        self.insert_all(text, |line, record| {
+            let record = record.split([';', '#']).next().unwrap_or_default().trim();
+            if record.is_empty() {
+                return Ok(None);
+            }
+            let Some((name, addr)) = record.split_once('=') else {
+                return Err(LoadError::Malformed { line, reason: "expected 'name = $addr'" });
+            };
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(LoadError::Malformed { line, reason: "invalid label name" });
+            }
+            let addr = parse_addr(addr.trim()).ok_or(LoadError::Malformed { line, reason: "invalid label address" })?;
+            Ok(Some((name, addr)))
+        })
+    }
+
+    /// Runs `parse` on each non-blank line, numbered from 1, and inserts the
+    /// names it returns once every line has parsed.
+    fn insert_all<'a>(
+        &mut self,
+        text: &'a str,
+        mut parse: impl FnMut(usize, &'a str) -> Result<Option<(&'a str, Word)>, LoadError>,
+    ) -> Result<(), LoadError> {
+        let mut found = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if !line.is_empty()
+                && let Some(symbol) = parse(index + 1, line)?
+            {
+                found.push(symbol);
+            }
+        }
+        for (name, addr) in found {
+            self.insert(name, addr);
+        }
+        Ok(())
+    }
+}
+
+/// Parses `$C000`, `0xC000` or `49152`.
+fn parse_addr(text: &str) -> Option<Word> {
+    match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => Word::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,361 @@
+#[cfg(feature = "std")]
+use crate::Symbols;
+use crate::{Byte, Cpu, Memory, Word};
+use core::fmt::{self, Write};
+
+/// Addressing modes of the NMOS opcode table, as far as the disassembler needs them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+use Mode::*;
+
+/// Mnemonic, addressing mode and whether the opcode is documented, for every NMOS opcode.
+const OPCODES: [(&str, Mode, bool); 256] = [
+    // 0x
+    ("BRK", Implied, true), ("ORA", IndirectX, true), ("JAM", Implied, false), ("SLO", IndirectX, false),
+    ("NOP", ZeroPage, false), ("ORA", ZeroPage, true), ("ASL", ZeroPage, true), ("SLO", ZeroPage, false),
+    ("PHP", Implied, true), ("ORA", Immediate, true), ("ASL", Accumulator, true), ("ANC", Immediate, false),
+    ("NOP", Absolute, false), ("ORA", Absolute, true), ("ASL", Absolute, true), ("SLO", Absolute, false),
+    // 1x
+    ("BPL", Relative, true), ("ORA", IndirectY, true), ("JAM", Implied, false), ("SLO", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("ORA", ZeroPageX, true), ("ASL", ZeroPageX, true), ("SLO", ZeroPageX, false),
+    ("CLC", Implied, true), ("ORA", AbsoluteY, true), ("NOP", Implied, false), ("SLO", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("ORA", AbsoluteX, true), ("ASL", AbsoluteX, true), ("SLO", AbsoluteX, false),
+    // 2x
+    ("JSR", Absolute, true), ("AND", IndirectX, true), ("JAM", Implied, false), ("RLA", IndirectX, false),
+    ("BIT", ZeroPage, true), ("AND", ZeroPage, true), ("ROL", ZeroPage, true), ("RLA", ZeroPage, false),
+    ("PLP", Implied, true), ("AND", Immediate, true), ("ROL", Accumulator, true), ("ANC", Immediate, false),
+    ("BIT", Absolute, true), ("AND", Absolute, true), ("ROL", Absolute, true), ("RLA", Absolute, false),
+    // 3x
+    ("BMI", Relative, true), ("AND", IndirectY, true), ("JAM", Implied, false), ("RLA", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("AND", ZeroPageX, true), ("ROL", ZeroPageX, true), ("RLA", ZeroPageX, false),
+    ("SEC", Implied, true), ("AND", AbsoluteY, true), ("NOP", Implied, false), ("RLA", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("AND", AbsoluteX, true), ("ROL", AbsoluteX, true), ("RLA", AbsoluteX, false),
+    // 4x
+    ("RTI", Implied, true), ("EOR", IndirectX, true), ("JAM", Implied, false), ("SRE", IndirectX, false),
+    ("NOP", ZeroPage, false), ("EOR", ZeroPage, true), ("LSR", ZeroPage, true), ("SRE", ZeroPage, false),
+    ("PHA", Implied, true), ("EOR", Immediate, true), ("LSR", Accumulator, true), ("ALR", Immediate, false),
+    ("JMP", Absolute, true), ("EOR", Absolute, true), ("LSR", Absolute, true), ("SRE", Absolute, false),
+    // 5x
+    ("BVC", Relative, true), ("EOR", IndirectY, true), ("JAM", Implied, false), ("SRE", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("EOR", ZeroPageX, true), ("LSR", ZeroPageX, true), ("SRE", ZeroPageX, false),
+    ("CLI", Implied, true), ("EOR", AbsoluteY, true), ("NOP", Implied, false), ("SRE", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("EOR", AbsoluteX, true), ("LSR", AbsoluteX, true), ("SRE", AbsoluteX, false),
+    // 6x
+    ("RTS", Implied, true), ("ADC", IndirectX, true), ("JAM", Implied, false), ("RRA", IndirectX, false),
+    ("NOP", ZeroPage, false), ("ADC", ZeroPage, true), ("ROR", ZeroPage, true), ("RRA", ZeroPage, false),
+    ("PLA", Implied, true), ("ADC", Immediate, true), ("ROR", Accumulator, true), ("ARR", Immediate, false),
+    ("JMP", Indirect, true), ("ADC", Absolute, true), ("ROR", Absolute, true), ("RRA", Absolute, false),
+    // 7x
+    ("BVS", Relative, true), ("ADC", IndirectY, true), ("JAM", Implied, false), ("RRA", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("ADC", ZeroPageX, true), ("ROR", ZeroPageX, true), ("RRA", ZeroPageX, false),
+    ("SEI", Implied, true), ("ADC", AbsoluteY, true), ("NOP", Implied, false), ("RRA", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("ADC", AbsoluteX, true), ("ROR", AbsoluteX, true), ("RRA", AbsoluteX, false),
+    // 8x
+    ("NOP", Immediate, false), ("STA", IndirectX, true), ("NOP", Immediate, false), ("SAX", IndirectX, false),
+    ("STY", ZeroPage, true), ("STA", ZeroPage, true), ("STX", ZeroPage, true), ("SAX", ZeroPage, false),
+    ("DEY", Implied, true), ("NOP", Immediate, false), ("TXA", Implied, true), ("ANE", Immediate, false),
+    ("STY", Absolute, true), ("STA", Absolute, true), ("STX", Absolute, true), ("SAX", Absolute, false),
+    // 9x
+    ("BCC", Relative, true), ("STA", IndirectY, true), ("JAM", Implied, false), ("SHA", IndirectY, false),
+    ("STY", ZeroPageX, true), ("STA", ZeroPageX, true), ("STX", ZeroPageY, true), ("SAX", ZeroPageY, false),
+    ("TYA", Implied, true), ("STA", AbsoluteY, true), ("TXS", Implied, true), ("TAS", AbsoluteY, false),
+    ("SHY", AbsoluteX, false), ("STA", AbsoluteX, true), ("SHX", AbsoluteY, false), ("SHA", AbsoluteY, false),
+    // Ax
+    ("LDY", Immediate, true), ("LDA", IndirectX, true), ("LDX", Immediate, true), ("LAX", IndirectX, false),
+    ("LDY", ZeroPage, true), ("LDA", ZeroPage, true), ("LDX", ZeroPage, true), ("LAX", ZeroPage, false),
+    ("TAY", Implied, true), ("LDA", Immediate, true), ("TAX", Implied, true), ("LXA", Immediate, false),
+    ("LDY", Absolute, true), ("LDA", Absolute, true), ("LDX", Absolute, true), ("LAX", Absolute, false),
+    // Bx
+    ("BCS", Relative, true), ("LDA", IndirectY, true), ("JAM", Implied, false), ("LAX", IndirectY, false),
+    ("LDY", ZeroPageX, true), ("LDA", ZeroPageX, true), ("LDX", ZeroPageY, true), ("LAX", ZeroPageY, false),
+    ("CLV", Implied, true), ("LDA", AbsoluteY, true), ("TSX", Implied, true), ("LAS", AbsoluteY, false),
+    ("LDY", AbsoluteX, true), ("LDA", AbsoluteX, true), ("LDX", AbsoluteY, true), ("LAX", AbsoluteY, false),
+    // Cx
+    ("CPY", Immediate, true), ("CMP", IndirectX, true), ("NOP", Immediate, false), ("DCP", IndirectX, false),
+    ("CPY", ZeroPage, true), ("CMP", ZeroPage, true), ("DEC", ZeroPage, true), ("DCP", ZeroPage, false),
+    ("INY", Implied, true), ("CMP", Immediate, true), ("DEX", Implied, true), ("SBX", Immediate, false),
+    ("CPY", Absolute, true), ("CMP", Absolute, true), ("DEC", Absolute, true), ("DCP", Absolute, false),
+    // Dx
+    ("BNE", Relative, true), ("CMP", IndirectY, true), ("JAM", Implied, false), ("DCP", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("CMP", ZeroPageX, true), ("DEC", ZeroPageX, true), ("DCP", ZeroPageX, false),
+    ("CLD", Implied, true), ("CMP", AbsoluteY, true), ("NOP", Implied, false), ("DCP", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("CMP", AbsoluteX, true), ("DEC", AbsoluteX, true), ("DCP", AbsoluteX, false),
+    // Ex
+    ("CPX", Immediate, true), ("SBC", IndirectX, true), ("NOP", Immediate, false), ("ISC", IndirectX, false),
+    ("CPX", ZeroPage, true), ("SBC", ZeroPage, true), ("INC", ZeroPage, true), ("ISC", ZeroPage, false),
+    ("INX", Implied, true), ("SBC", Immediate, true), ("NOP", Implied, true), ("SBC", Immediate, false),
+    ("CPX", Absolute, true), ("SBC", Absolute, true), ("INC", Absolute, true), ("ISC", Absolute, false),
+    // Fx
+    ("BEQ", Relative, true), ("SBC", IndirectY, true), ("JAM", Implied, false), ("ISC", IndirectY, false),
+    ("NOP", ZeroPageX, false), ("SBC", ZeroPageX, true), ("INC", ZeroPageX, true), ("ISC", ZeroPageX, false),
+    ("SED", Implied, true), ("SBC", AbsoluteY, true), ("NOP", Implied, false), ("ISC", AbsoluteY, false),
+    ("NOP", AbsoluteX, false), ("SBC", AbsoluteX, true), ("INC", AbsoluteX, true), ("ISC", AbsoluteX, false),
+];
+
+impl Mode {
+    pub(crate) fn len(self) -> Word {
+        match self {
+            Implied | Accumulator => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// Mnemonic and addressing mode of an NMOS opcode.
+#[cfg(feature = "std")]
+pub(crate) fn opcode_info(opcode: crate::Byte) -> (&'static str, Mode) {
+    let (mnemonic, mode, _) = OPCODES[opcode as usize];
+    (mnemonic, mode)
+}
+
+fn read_word(memory: &Memory, lo: Word, hi: Word) -> Word {
+    memory.data[lo as usize] as Word | (memory.data[hi as usize] as Word) << 8
+}
+
+/// Writes the NMOS instruction at `addr` to `out`, e.g. `LDA $0300,X`, without
+/// allocating. Returns the instruction length in bytes.
+pub fn write_disassembly(out: &mut impl Write, memory: &Memory, addr: Word) -> Result<Word, fmt::Error> {
+    write_named_disassembly(out, memory, addr, |_| None)
+}
+
+/// `write_disassembly`, showing operand addresses that `name` knows by name.
+fn write_named_disassembly<'a>(
+    out: &mut impl Write,
+    memory: &Memory,
+    addr: Word,
+    name: impl Fn(Word) -> Option<&'a str>,
+) -> Result<Word, fmt::Error> {
+    let (mnemonic, mode, _) = OPCODES[memory.data[addr as usize] as usize];
+    let byte = memory.data[addr.wrapping_add(1) as usize];
+    let word = read_word(memory, addr.wrapping_add(1), addr.wrapping_add(2));
+    let target = match mode {
+        Relative => addr.wrapping_add(2).wrapping_add(byte as i8 as Word),
+        Absolute | AbsoluteX | AbsoluteY | Indirect => word,
+        _ => byte as Word,
+    };
+    out.write_str(mnemonic)?;
+    if let Some(name) = name(target).filter(|_| !matches!(mode, Implied | Accumulator | Immediate)) {
+        return match mode {
+            ZeroPageX | AbsoluteX => write!(out, " {},X", name),
+            ZeroPageY | AbsoluteY => write!(out, " {},Y", name),
+            Indirect => write!(out, " ({})", name),
+            IndirectX => write!(out, " ({},X)", name),
+            IndirectY => write!(out, " ({}),Y", name),
+            _ => write!(out, " {}", name),
+        }
+        .map(|()| mode.len());
+    }
+    match mode {
+        Implied => {}
+        Accumulator => out.write_str(" A")?,
+        Immediate => write!(out, " #${:02X}", byte)?,
+        ZeroPage => write!(out, " ${:02X}", byte)?,
+        ZeroPageX => write!(out, " ${:02X},X", byte)?,
+        ZeroPageY => write!(out, " ${:02X},Y", byte)?,
+        Absolute => write!(out, " ${:04X}", word)?,
+        AbsoluteX => write!(out, " ${:04X},X", word)?,
+        AbsoluteY => write!(out, " ${:04X},Y", word)?,
+        Indirect => write!(out, " (${:04X})", word)?,
+        IndirectX => write!(out, " (${:02X},X)", byte)?,
+        IndirectY => write!(out, " (${:02X}),Y", byte)?,
+        Relative => write!(out, " ${:04X}", target)?,
+    }
+    Ok(mode.len())
+}
+
+/// Disassembles the NMOS instruction at `addr`, e.g. `LDA $0300,X`.
+/// Returns the text and the instruction length in bytes.
+#[cfg(feature = "std")]
+pub fn disassemble(memory: &Memory, addr: Word) -> (String, Word) {
+    let mut text = String::new();
+    let len = write_disassembly(&mut text, memory, addr).expect("formatting into a String cannot fail");
+    (text, len)
+}
+
+/// `disassemble`, with operand addresses that have a name in `symbols` shown
+/// by name, e.g. `LDA table,X` or `BNE main_loop`.
+#[cfg(feature = "std")]
+pub fn disassemble_with_symbols(memory: &Memory, addr: Word, symbols: &Symbols) -> (String, Word) {
+    let mut text = String::new();
+    let len = write_named_disassembly(&mut text, memory, addr, |addr| symbols.name(addr))
+        .expect("formatting into a String cannot fail");
+    (text, len)
+}
+
+/// A trace column formatted on the stack, so that it can be padded without allocating.
+struct Column {
+    bytes: [u8; 48],
+    len: usize,
+}
+
+impl Column {
+    fn new() -> Self {
+        Column { bytes: [0; 48], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole `&str`s are ever copied in
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for Column {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl Cpu {
+    /// Writes the instruction at PC and the registers before it runs to `out`, in
+    /// the layout of the nestest.log reference trace (without its PPU column):
+    ///
+    /// `C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7`
+    ///
+    /// Operands are annotated with the effective address and the value there,
+    /// read straight from `memory`. Undocumented opcodes are marked with `*`.
+    pub fn write_trace_line(&self, out: &mut impl Write, memory: &Memory, cycles: u64) -> fmt::Result {
+        let mut text = Column::new();
+        self.write_operation(&mut text, memory, |_| None)?;
+        self.write_trace_columns(out, memory, cycles, text.as_str())
+    }
+
+    /// `write_trace_line` into a new `String`.
+    #[cfg(feature = "std")]
+    pub fn trace_line(&self, memory: &Memory, cycles: u64) -> String {
+        let mut line = String::new();
+        self.write_trace_line(&mut line, memory, cycles).expect("formatting into a String cannot fail");
+        line
+    }
+
+    /// `trace_line`, with operand addresses that have a name in `symbols` shown
+    /// by name. The effective addresses in the annotation stay numeric, and a
+    /// name that is too long for the column widens the line.
+    #[cfg(feature = "std")]
+    pub fn trace_line_with_symbols(&self, memory: &Memory, cycles: u64, symbols: &Symbols) -> String {
+        let mut text = String::new();
+        let mut line = String::new();
+        self.write_operation(&mut text, memory, |addr| symbols.name(addr))
+            .and_then(|()| self.write_trace_columns(&mut line, memory, cycles, &text))
+            .expect("formatting into a String cannot fail");
+        line
+    }
+
+    /// The disassembly of the instruction at PC, followed by its effective
+    /// address and the value there.
+    fn write_operation<'a>(&self, text: &mut impl Write, memory: &Memory, name: impl Fn(Word) -> Option<&'a str>) -> fmt::Result {
+        let pc = self.pc;
+        let (mnemonic, mode, _) = OPCODES[memory.data[pc as usize] as usize];
+        write_named_disassembly(text, memory, pc, name)?;
+
+        let byte = memory.data[pc.wrapping_add(1) as usize];
+        let word = read_word(memory, pc.wrapping_add(1), pc.wrapping_add(2));
+        let value = |addr: Word| memory.data[addr as usize];
+        match mode {
+            ZeroPage => write!(text, " = {:02X}", value(byte as Word))?,
+            ZeroPageX | ZeroPageY => {
+                let index = if mode == ZeroPageX { self.reg_x } else { self.reg_y };
+                let addr = byte.wrapping_add(index) as Word;
+                write!(text, " @ {:02X} = {:02X}", addr, value(addr))?;
+            }
+            Absolute if mnemonic == "JMP" || mnemonic == "JSR" => {}
+            Absolute => write!(text, " = {:02X}", value(word))?,
+            AbsoluteX | AbsoluteY => {
+                let index = if mode == AbsoluteX { self.reg_x } else { self.reg_y };
+                let addr = word.wrapping_add(index as Word);
+                write!(text, " @ {:04X} = {:02X}", addr, value(addr))?;
+            }
+            // NMOS JMP ($xxFF) takes the high byte from the start of the same page
+            Indirect => write!(text, " = {:04X}", read_word(memory, word, (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF)))?,
+            IndirectX => {
+                let ptr = byte.wrapping_add(self.reg_x);
+                let addr = read_word(memory, ptr as Word, ptr.wrapping_add(1) as Word);
+                write!(text, " @ {:02X} = {:04X} = {:02X}", ptr, addr, value(addr))?;
+            }
+            IndirectY => {
+                let base = read_word(memory, byte as Word, byte.wrapping_add(1) as Word);
+                let addr = base.wrapping_add(self.reg_y as Word);
+                write!(text, " = {:04X} @ {:04X} = {:02X}", base, addr, value(addr))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The whole trace line around `text`: address, instruction bytes and registers.
+    fn write_trace_columns(&self, out: &mut impl Write, memory: &Memory, cycles: u64, text: &str) -> fmt::Result {
+        let pc = self.pc;
+        let (_, mode, documented) = OPCODES[memory.data[pc as usize] as usize];
+        let mut bytes = [0; 3];
+        for (i, byte) in bytes.iter_mut().enumerate().take(mode.len() as usize) {
+            *byte = memory.data[pc.wrapping_add(i as Word) as usize];
+        }
+        write_trace_columns(
+            out,
+            format_args!("{:04X}", pc),
+            &bytes[..mode.len() as usize],
+            8,
+            documented,
+            text,
+            format_args!(
+                "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+                self.reg_a,
+                self.reg_x,
+                self.reg_y,
+                self.status.to_byte(false),
+                self.sp,
+                cycles
+            ),
+        )
+    }
+}
+
+/// Writes one trace line in the columns of `Cpu::write_trace_line`: the address,
+/// the instruction bytes padded to `bytes_width`, `*` for an undocumented
+/// opcode, the operation padded to the register column, then the registers.
+/// Other cores trace through this so that their logs line up the same way.
+pub fn write_trace_columns(
+    out: &mut impl Write,
+    addr: fmt::Arguments<'_>,
+    bytes: &[Byte],
+    bytes_width: usize,
+    documented: bool,
+    operation: &str,
+    registers: fmt::Arguments<'_>,
+) -> fmt::Result {
+    let mut column = Column::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        let separator = if i == 0 { "" } else { " " };
+        write!(column, "{}{:02X}", separator, byte)?;
+    }
+    write!(
+        out,
+        "{}  {:<bytes_width$} {}{:<31} {}",
+        addr,
+        column.as_str(),
+        if documented { ' ' } else { '*' },
+        operation,
+        registers
+    )
+}
@@ -0,0 +1,113 @@
+use crate::loader::LoadError;
+use crate::{Byte, Cpu, Memory, Word};
+
+/// Where a segment stores the address of a routine to call once it is loaded.
+const INITAD: Word = 0x02E2;
+/// Where the file stores the address to run once every segment is loaded.
+const RUNAD: Word = 0x02E0;
+/// Init routines return here, which is never executed: an RTS to a pushed
+/// $FFFE lands on $FFFF.
+const INIT_RETURN: Word = 0xFFFF;
+
+/// A segment of the file: `data` goes to `start`.
+pub(crate) struct Segment<'a> {
+    start: Word,
+    data: &'a [Byte],
+}
+
+impl Segment<'_> {
+    /// Whether the segment writes either byte of the vector at `addr`.
+    fn covers(&self, addr: Word) -> bool {
+        let end = self.start as usize + self.data.len();
+        (self.start as usize) < addr as usize + 2 && end > addr as usize
+    }
+}
+
+fn word(bytes: &[Byte], offset: usize) -> Result<Word, LoadError> {
+    match bytes.get(offset..offset + 2) {
+        Some(&[lo, hi]) => Ok(Word::from_le_bytes([lo, hi])),
+        _ => Err(LoadError::Truncated { offset: bytes.len() }),
+    }
+}
+
+/// Loads an Atari DOS executable (XEX) the way DOS does. Each segment is a
+/// start and end address, optionally preceded by $FFFF, followed by its bytes.
+/// After a segment that writes INITAD ($02E2) its init routine is called on
+/// `cpu` and must return with RTS within `max_init_cycles`. Returns the address
+/// in RUNAD ($02E0) if a segment wrote it, or else the first segment's start.
+///
+/// The segment structure is checked before memory is written, so a truncated
+/// file loads nothing. An init routine that hangs or halts the CPU stops the
+/// load with the segments before it in memory.
+pub fn load_xex(bytes: &[Byte], cpu: &mut Cpu, memory: &mut Memory, max_init_cycles: u64) -> Result<Word, LoadError> {
+    let segments = segments(bytes)?;
+    let Some(first) = segments.first() else { return Err(LoadError::Truncated { offset: bytes.len() }) };
+    let mut run = first.start;
+
+    for segment in &segments {
+        memory.data[segment.start as usize..][..segment.data.len()].copy_from_slice(segment.data);
+        if segment.covers(INITAD) {
+            run_init(cpu, memory, read_word(memory, INITAD), max_init_cycles)?;
+        }
+        if segment.covers(RUNAD) {
+            run = read_word(memory, RUNAD);
+        }
+    }
+    Ok(run)
+}
+
+/// Splits the file into its segments, checking that their headers are sound
+/// and that they use up the whole file.
+pub(crate) fn segments(bytes: &[Byte]) -> Result<Vec<Segment<'_>>, LoadError> {
+    if word(bytes, 0)? != 0xFFFF {
+        return Err(LoadError::Invalid("not an Atari executable".to_string()));
+    }
+    let mut segments = Vec::new();
+    let mut offset = 2;
+    while offset < bytes.len() {
+        let mut start = word(bytes, offset)?;
+        if start == 0xFFFF {
+            offset += 2;
+            start = word(bytes, offset)?;
+        }
+        let end = word(bytes, offset + 2)?;
+        if end < start {
+            return Err(LoadError::Invalid(format!("segment ends at ${:04X}, before its start ${:04X}", end, start)));
+        }
+        offset += 4;
+        let len = (end - start) as usize + 1;
+        let data = bytes.get(offset..offset + len).ok_or(LoadError::Truncated { offset: bytes.len() })?;
+        segments.push(Segment { start, data });
+        offset += len;
+    }
+    Ok(segments)
+}
+
+fn read_word(memory: &Memory, addr: Word) -> Word {
+    Word::from_le_bytes([memory.data[addr as usize], memory.data[addr as usize + 1]])
+}
+
+/// Calls the routine at `addr` as DOS does with `JSR (INITAD)`.
+fn run_init(cpu: &mut Cpu, memory: &mut Memory, addr: Word, max_cycles: u64) -> Result<(), LoadError> {
+    for byte in (INIT_RETURN - 1).to_be_bytes() {
+        memory.data[0x0100 + cpu.sp as usize] = byte;
+        cpu.sp = cpu.sp.wrapping_sub(1);
+    }
+    cpu.pc = addr;
+    let mut cycles = 0;
+    while cpu.pc != INIT_RETURN {
+        if cycles >= max_cycles {
+            return Err(LoadError::Invalid(format!(
+                "init routine at ${:04X} did not return within {} cycles",
+                addr, max_cycles
+            )));
+        }
+        let used = cpu.execute(1, memory);
+        // Jammed, stopped or waiting for an interrupt
+        if used == 0 {
+            return Err(LoadError::Invalid(format!("init routine at ${:04X} halted the CPU at ${:04X}", addr, cpu.pc)));
+        }
+        cycles += used as u64;
+    }
+    Ok(())
+}
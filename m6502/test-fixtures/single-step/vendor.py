@@ -0,0 +1,54 @@
+#!/usr/bin/env python3
+"""Replaces the vendored single-step cases with the first cases of each upstream
+SingleStepTests/ProcessorTests `6502/v1` file, for every opcode except the JAMs.
+
+    python3 vendor.py [--cases N] [--source DIR_OR_URL]
+
+`--source` defaults to the upstream repository; point it at a local checkout of
+`6502/v1` to vendor without network access.
+"""
+
+import argparse
+import json
+import pathlib
+import urllib.request
+
+UPSTREAM = "https://raw.githubusercontent.com/SingleStepTests/ProcessorTests/main/6502/v1"
+HERE = pathlib.Path(__file__).resolve().parent
+
+
+def is_jam(opcode):
+    """The JAM opcodes lock up the bus, so upstream traces for them are not meaningful."""
+    return opcode & 0x0F == 0x02 and opcode not in (0x82, 0xA2, 0xC2, 0xE2)
+
+
+def fetch(source, name):
+    if source.startswith(("http://", "https://")):
+        with urllib.request.urlopen(f"{source}/{name}") as response:
+            return json.load(response)
+    return json.loads(pathlib.Path(source, name).read_text())
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--cases", type=int, default=8, help="cases kept per opcode")
+    parser.add_argument("--source", default=UPSTREAM, help="directory or URL of 6502/v1")
+    args = parser.parse_args()
+
+    # Fetch everything before touching the old files, so a failed download keeps them
+    files = {}
+    for opcode in range(0x100):
+        if not is_jam(opcode):
+            name = f"{opcode:02x}.json"
+            files[name] = fetch(args.source, name)[: args.cases]
+            print(name, len(files[name]))
+    for old in HERE.glob("*.json"):
+        old.unlink()
+    for name, cases in files.items():
+        # One case to a line, like the rest of the fixtures
+        lines = ",\n".join(json.dumps(case) for case in cases)
+        (HERE / name).write_text(f"[\n{lines}\n]\n")
+
+
+if __name__ == "__main__":
+    main()
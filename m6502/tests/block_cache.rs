@@ -0,0 +1,166 @@
+//! `Cpu::execute_cached` against `Cpu::execute`.
+
+use m6502::{BlockCache, Cpu, Memory, Word};
+
+const FUNCTIONAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_functional_test.bin");
+const DECIMAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_decimal_test.bin");
+
+const FUNCTIONAL_SUCCESS: Word = 0x3469;
+
+fn load(memory: &mut Memory, addr: usize, image: &[u8]) {
+    memory.data[addr..addr + image.len()].copy_from_slice(image);
+}
+
+fn registers(cpu: &Cpu) -> String {
+    format!(
+        "pc={:04X} a={:02X} x={:02X} y={:02X} sp={:02X} p={:02X}",
+        cpu.pc,
+        cpu.reg_a,
+        cpu.reg_x,
+        cpu.reg_y,
+        cpu.sp,
+        cpu.status.to_byte(false)
+    )
+}
+
+#[test]
+fn functional_test_passes() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    load(&mut memory, 0, FUNCTIONAL_TEST);
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0400;
+    let mut cache = BlockCache::new();
+    // The test traps by jumping or branching to itself
+    loop {
+        let pc = cpu.pc;
+        cpu.execute_cached(1, &mut memory, &mut cache);
+        if cpu.pc == pc {
+            break;
+        }
+        cpu.execute_cached(1000, &mut memory, &mut cache);
+    }
+    assert_eq!(cpu.pc, FUNCTIONAL_SUCCESS, "functional test failed in test case {:02X}", memory.data[0x0200]);
+    assert!(!cache.is_empty());
+}
+
+/// Runs both engines side by side in uneven slices, so slices end in the middle
+/// of blocks, and compares registers and cycles after each one.
+fn run_in_lockstep(image: &[u8], load_addr: usize, start: Word, total_cycles: i32) {
+    let mut expected_memory = Memory { data: [0; 1024 * 64] };
+    load(&mut expected_memory, load_addr, image);
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    load(&mut memory, load_addr, image);
+    let mut expected_cpu = Cpu::new();
+    expected_cpu.pc = start;
+    let mut cpu = Cpu::new();
+    cpu.pc = start;
+    let mut cache = BlockCache::new();
+
+    let mut elapsed = 0;
+    let mut slice = 1;
+    while elapsed < total_cycles {
+        let expected_cycles = expected_cpu.execute(slice, &mut expected_memory);
+        let cycles = cpu.execute_cached(slice, &mut memory, &mut cache);
+        assert_eq!(
+            (registers(&cpu), cycles),
+            (registers(&expected_cpu), expected_cycles),
+            "after {} cycles",
+            elapsed
+        );
+        elapsed += cycles;
+        slice = slice % 37 + 1;
+    }
+    assert!(memory.data == expected_memory.data, "memory differs after {} cycles", elapsed);
+}
+
+#[test]
+fn functional_test_in_lockstep() {
+    run_in_lockstep(FUNCTIONAL_TEST, 0x0000, 0x0400, 2_000_000);
+}
+
+#[test]
+fn decimal_test_in_lockstep() {
+    run_in_lockstep(DECIMAL_TEST, 0x0200, 0x0200, 2_000_000);
+}
+
+#[test]
+fn code_modified_within_its_own_block_is_decoded_again() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    load(&mut memory, 0x0200, &[
+        0xA9, 0xE8,       // LDA #$E8
+        0x8D, 0x06, 0x02, // STA $0206
+        0xEA,             // NOP
+        0xCA,             // DEX, patched to INX
+        0x4C, 0x07, 0x02, // JMP $0207
+    ]);
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0200;
+    let mut cache = BlockCache::new();
+    let cycles = cpu.execute_cached(2 + 4 + 2 + 2, &mut memory, &mut cache);
+    assert_eq!(cycles, 10);
+    assert_eq!(cpu.reg_x, 0x01);
+    assert_eq!(cpu.pc, 0x0207);
+}
+
+#[test]
+fn code_reported_as_replaced_is_decoded_again() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    load(&mut memory, 0x0200, &[
+        0xE8,             // INX
+        0x4C, 0x00, 0x02, // JMP $0200
+    ]);
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0200;
+    let mut cache = BlockCache::new();
+    cpu.execute_cached(50, &mut memory, &mut cache);
+    assert_eq!(cpu.reg_x, 10);
+
+    memory.data[0x0200] = 0xCA; // DEX
+    cache.invalidate(0x0200, 0x0200);
+    cpu.execute_cached(50, &mut memory, &mut cache);
+    assert_eq!(cpu.reg_x, 0);
+}
+
+#[test]
+fn undocumented_opcodes_fall_back_to_execute() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    load(&mut memory, 0x0200, &[
+        0xA7, 0x10,       // LAX $10
+        0xE8,             // INX
+        0x4C, 0x00, 0x02, // JMP $0200
+    ]);
+    memory.data[0x10] = 0x42;
+    let mut cpu = Cpu::new();
+    cpu.illegal_opcodes = true;
+    cpu.pc = 0x0200;
+    let mut cache = BlockCache::new();
+    let cycles = cpu.execute_cached(3 + 2 + 3, &mut memory, &mut cache);
+    assert_eq!(cycles, 8);
+    assert_eq!((cpu.reg_a, cpu.reg_x), (0x42, 0x43));
+    assert_eq!(cpu.pc, 0x0200);
+}
+
+#[test]
+fn traces_are_left_where_code_goes_elsewhere() {
+    let mut image = [0; 0x19];
+    image[..0x0E].copy_from_slice(&[
+        0xA2, 0x03,       // LDX #$03
+        0xCA,             // DEX
+        0xD0, 0xFD,       // BNE $0202, not taken on the last pass
+        0x20, 0x10, 0x02, // JSR $0210
+        0xC8,             // INY, skipped by the changed return address
+        0xC8,             // INY
+        0xC8,             // INY
+        0x4C, 0x00, 0x02, // JMP $0200
+    ]);
+    image[0x10..].copy_from_slice(&[
+        0x68,             // PLA
+        0x68,             // PLA
+        0xA9, 0x02,       // LDA #$02
+        0x48,             // PHA
+        0xA9, 0x0A,       // LDA #$0A
+        0x48,             // PHA
+        0x60,             // RTS to $020B
+    ]);
+    run_in_lockstep(&image, 0x0200, 0x0200, 20_000);
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use common::setup;
+use m6502::CpuVariant;
+use m6502::instructions::*;
+
+#[test]
+fn bra_always_branches() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &[INS_BRA, 0x10]);
+    assert_eq!(cpu.execute(3, &mut memory), 3);
+    assert_eq!(cpu.pc, 0x8012);
+}
+
+#[test]
+fn phx_ply_move_through_the_stack() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &[INS_PHX, INS_PLY]);
+    cpu.reg_x = 0x80;
+    assert_eq!(cpu.execute(3 + 4, &mut memory), 7);
+    assert_eq!(cpu.reg_y, 0x80);
+    assert!(cpu.status.negative);
+    assert_eq!(cpu.sp, 0xFD);
+}
+
+#[test]
+fn stz_tsb_trb() {
+    let program = [INS_STZ_ABS, 0x00, 0x20, INS_TSB_ZP, 0x10, INS_TRB_ZP, 0x11];
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &program);
+    memory.data[0x2000] = 0xFF;
+    memory.data[0x10] = 0x0F;
+    memory.data[0x11] = 0xFF;
+    cpu.reg_a = 0xF0;
+    assert_eq!(cpu.execute(4 + 5 + 5, &mut memory), 14);
+    assert_eq!(memory.data[0x2000], 0x00);
+    assert_eq!(memory.data[0x10], 0xFF);
+    assert_eq!(memory.data[0x11], 0x0F);
+    assert!(!cpu.status.zero);
+}
+
+#[test]
+fn zero_page_indirect_and_accumulator_increment() {
+    let program = [INS_LDA_INDZP, 0x10, INS_INC_A, INS_STA_INDZP, 0x12];
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &program);
+    memory.data[0x10] = 0x00;
+    memory.data[0x11] = 0x30;
+    memory.data[0x12] = 0x01;
+    memory.data[0x13] = 0x30;
+    memory.data[0x3000] = 0x41;
+    assert_eq!(cpu.execute(5 + 2 + 5, &mut memory), 12);
+    assert_eq!(memory.data[0x3001], 0x42);
+}
+
+#[test]
+fn bit_immediate_only_sets_zero() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &[INS_BIT_IM, 0xC0]);
+    cpu.reg_a = 0x01;
+    cpu.execute(2, &mut memory);
+    assert!(cpu.status.zero);
+    assert!(!cpu.status.negative);
+    assert!(!cpu.status.overflow);
+}
+
+#[test]
+fn jmp_indirect_page_wrap_depends_on_variant() {
+    let program = [INS_JMP_IND, 0xFF, 0x20];
+    for (variant, target, cycles) in [
+        (CpuVariant::Nmos6502, 0x1234, 5),
+        (CpuVariant::Cmos65C02, 0x5634, 6),
+    ] {
+        let (mut cpu, mut memory) = setup(variant, &program);
+        memory.data[0x20FF] = 0x34;
+        memory.data[0x2000] = 0x12;
+        memory.data[0x2100] = 0x56;
+        assert_eq!(cpu.execute(cycles, &mut memory), cycles);
+        assert_eq!(cpu.pc, target);
+    }
+}
+
+#[test]
+fn jmp_absolute_indexed_indirect() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &[INS_JMP_ABSX_IND, 0x00, 0x20]);
+    cpu.reg_x = 0x04;
+    memory.data[0x2004] = 0x00;
+    memory.data[0x2005] = 0x90;
+    assert_eq!(cpu.execute(6, &mut memory), 6);
+    assert_eq!(cpu.pc, 0x9000);
+}
+
+#[test]
+fn decimal_adc_flags_depend_on_variant() {
+    // 0x99 + 0x01 = 0x00 with carry; NMOS computes Z and N from intermediate results
+    let program = [INS_SED, INS_ADC, 0x01];
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &program);
+    cpu.reg_a = 0x99;
+    assert_eq!(cpu.execute(4, &mut memory), 4);
+    assert_eq!(cpu.reg_a, 0x00);
+    assert!(cpu.status.carry);
+    assert!(!cpu.status.zero);
+    assert!(cpu.status.negative);
+
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &program);
+    cpu.reg_a = 0x99;
+    assert_eq!(cpu.execute(5, &mut memory), 5);
+    assert_eq!(cpu.reg_a, 0x00);
+    assert!(cpu.status.carry);
+    assert!(cpu.status.zero);
+    assert!(!cpu.status.negative);
+}
+
+#[test]
+fn decimal_sbc() {
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Cmos65C02] {
+        let (mut cpu, mut memory) = setup(variant, &[INS_SED, INS_SEC, INS_SBC, 0x01]);
+        cpu.reg_a = 0x10;
+        cpu.execute(6, &mut memory);
+        assert_eq!(cpu.reg_a, 0x09);
+        assert!(cpu.status.carry);
+    }
+}
+
+#[test]
+fn shifts_abs_x_skip_the_fix_up_cycle_on_the_65c02() {
+    // ASL $2000,X / ROR $20F0,X (crosses a page)
+    let program = [INS_ASL_ABSX, 0x00, 0x20, INS_ROR_ABSX, 0xF0, 0x20];
+    for (variant, cycles) in [(CpuVariant::Nmos6502, [7, 7]), (CpuVariant::Cmos65C02, [6, 7])] {
+        let (mut cpu, mut memory) = setup(variant, &program);
+        cpu.reg_x = 0x10;
+        memory.data[0x2010] = 0x41;
+        for expected in cycles {
+            assert_eq!(cpu.execute(1, &mut memory), expected, "{:?} at {:04X}", variant, cpu.pc);
+        }
+        assert_eq!(memory.data[0x2010], 0x82);
+    }
+}
+
+#[test]
+fn cmos_interrupts_clear_decimal_mode() {
+    for (variant, decimal) in [(CpuVariant::Nmos6502, true), (CpuVariant::Cmos65C02, false)] {
+        let (mut cpu, mut memory) = setup(variant, &[INS_BRK]);
+        cpu.status.decimal_mode = true;
+        cpu.execute(1, &mut memory);
+        assert_eq!(cpu.status.decimal_mode, decimal, "{:?}", variant);
+
+        let (mut cpu, mut memory) = setup(variant, &[]);
+        cpu.status.decimal_mode = true;
+        cpu.nmi(&mut memory);
+        assert_eq!(cpu.status.decimal_mode, decimal, "{:?}", variant);
+    }
+}
+
+#[test]
+fn unused_opcodes_are_nops_of_defined_length() {
+    let program = [0x03, 0x02, 0xFF, 0x44, 0x00, 0x5C, 0x00, 0x00, 0xDC, 0x00, 0x00];
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &program);
+    assert_eq!(cpu.execute(1 + 2 + 3 + 8 + 4, &mut memory), 18);
+    assert_eq!(cpu.pc, 0x800B);
+}
+
+#[test]
+fn bit_instructions_are_nops_on_the_plain_65c02() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Cmos65C02, &[INS_SMB0 + 0x30, 0x10]);
+    assert_eq!(cpu.execute(1, &mut memory), 1);
+    assert_eq!(cpu.pc, 0x8001);
+    assert_eq!(memory.data[0x10], 0x00);
+}
+
+#[test]
+fn rmb_smb_modify_a_single_bit() {
+    let program = [INS_SMB0 + 0x30, 0x10, INS_RMB0 + 0x70, 0x11];
+    let (mut cpu, mut memory) = setup(CpuVariant::Rockwell65C02, &program);
+    memory.data[0x11] = 0xFF;
+    assert_eq!(cpu.execute(5 + 5, &mut memory), 10);
+    assert_eq!(memory.data[0x10], 0x08);
+    assert_eq!(memory.data[0x11], 0x7F);
+}
+
+#[test]
+fn bbr_bbs_branch_on_a_bit() {
+    let program = [INS_BBS0 + 0x20, 0x10, 0x03, INS_BBR0 + 0x20, 0x10, 0x10];
+    let (mut cpu, mut memory) = setup(CpuVariant::Wdc65C02, &program);
+    memory.data[0x10] = 0x04;
+    // BBS2 taken over the BBR2, which would not have branched
+    assert_eq!(cpu.execute(6, &mut memory), 6);
+    assert_eq!(cpu.pc, 0x8006);
+
+    let (mut cpu, mut memory) = setup(CpuVariant::Wdc65C02, &program[3..]);
+    memory.data[0x10] = 0x04;
+    assert_eq!(cpu.execute(5, &mut memory), 5);
+    assert_eq!(cpu.pc, 0x8003);
+}
+
+#[test]
+fn wai_idles_until_an_interrupt() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Wdc65C02, &[INS_WAI, INS_INX]);
+    assert_eq!(cpu.execute(10, &mut memory), 3);
+    assert!(cpu.waiting);
+    assert_eq!(cpu.execute(10, &mut memory), 0);
+
+    // With interrupts disabled an IRQ only wakes the CPU
+    cpu.status.interrupt_disable = true;
+    assert_eq!(cpu.irq(&mut memory), 0);
+    assert!(!cpu.waiting);
+    cpu.execute(2, &mut memory);
+    assert_eq!(cpu.reg_x, 1);
+}
+
+#[test]
+fn stp_stops_until_reset() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Wdc65C02, &[INS_STP, INS_INX]);
+    assert_eq!(cpu.execute(10, &mut memory), 3);
+    assert!(cpu.stopped);
+    assert_eq!(cpu.nmi(&mut memory), 0);
+    assert_eq!(cpu.execute(10, &mut memory), 0);
+    cpu.reset(&mut memory);
+    assert!(!cpu.stopped);
+}
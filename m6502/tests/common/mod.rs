@@ -0,0 +1,14 @@
+//! Fixtures shared by the instruction tests.
+
+use m6502::{Cpu, CpuVariant, Memory};
+
+/// A `variant` CPU reset into `program` at $8000, in otherwise zeroed memory.
+pub fn setup(variant: CpuVariant, program: &[u8]) -> (Cpu, Memory) {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    memory.data[0xFFFC] = 0x00;
+    memory.data[0xFFFD] = 0x80;
+    memory.data[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    let mut cpu = Cpu::with_variant(variant);
+    cpu.reset(&mut memory);
+    (cpu, memory)
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::setup;
+use m6502::instructions::*;
+use m6502::BusAccess::{Read, Write};
+use m6502::{Bus, BusAccess, Byte, Cpu, CpuVariant, Memory, Word};
+
+fn trace(cpu: &mut Cpu, bus: &mut impl Bus) -> Vec<BusAccess> {
+    let mut accesses = vec![cpu.tick(bus)];
+    while !cpu.at_instruction_boundary() {
+        accesses.push(cpu.tick(bus));
+    }
+    accesses
+}
+
+#[test]
+fn indexed_read_across_a_page_reads_the_unfixed_address_first() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_LDA_ABSX, 0xFF, 0x20]);
+    cpu.reg_x = 0x01;
+    memory.data[0x2100] = 0x42;
+    assert_eq!(trace(&mut cpu, &mut memory), [
+        Read { addr: 0x8000, value: INS_LDA_ABSX },
+        Read { addr: 0x8001, value: 0xFF },
+        Read { addr: 0x8002, value: 0x20 },
+        Read { addr: 0x2000, value: 0x00 },
+        Read { addr: 0x2100, value: 0x42 },
+    ]);
+    assert_eq!(cpu.reg_a, 0x42);
+}
+
+#[test]
+fn indexed_store_always_takes_the_dummy_read() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_STA_ABSX, 0x00, 0x20]);
+    cpu.reg_a = 0x99;
+    cpu.reg_x = 0x01;
+    assert_eq!(trace(&mut cpu, &mut memory)[3..], [
+        Read { addr: 0x2001, value: 0x00 },
+        Write { addr: 0x2001, value: 0x99 },
+    ]);
+}
+
+#[test]
+fn read_modify_write_writes_twice() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_INC_ZP, 0x10]);
+    memory.data[0x10] = 0x7F;
+    assert_eq!(trace(&mut cpu, &mut memory)[2..], [
+        Read { addr: 0x0010, value: 0x7F },
+        Write { addr: 0x0010, value: 0x7F },
+        Write { addr: 0x0010, value: 0x80 },
+    ]);
+    assert!(cpu.status.negative);
+
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_ASL_ABSX, 0x00, 0x20]);
+    assert_eq!(cpu.tick_instruction(&mut memory), 7);
+}
+
+#[test]
+fn zero_page_indexing_wraps_after_a_dummy_read() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_LDA_ZPX, 0xF0]);
+    cpu.reg_x = 0x20;
+    memory.data[0x10] = 0x05;
+    assert_eq!(trace(&mut cpu, &mut memory)[2..], [
+        Read { addr: 0x00F0, value: 0x00 },
+        Read { addr: 0x0010, value: 0x05 },
+    ]);
+}
+
+#[test]
+fn jsr_and_rts_bus_sequence() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_JSR, 0x00, 0x90]);
+    memory.data[0x9000] = INS_RTS;
+    assert_eq!(trace(&mut cpu, &mut memory), [
+        Read { addr: 0x8000, value: INS_JSR },
+        Read { addr: 0x8001, value: 0x00 },
+        Read { addr: 0x01FD, value: 0x00 },
+        Write { addr: 0x01FD, value: 0x80 },
+        Write { addr: 0x01FC, value: 0x02 },
+        Read { addr: 0x8002, value: 0x90 },
+    ]);
+    assert_eq!(cpu.pc, 0x9000);
+    assert_eq!(cpu.tick_instruction(&mut memory), 6);
+    assert_eq!(cpu.pc, 0x8003);
+    assert_eq!(cpu.sp, 0xFD);
+}
+
+#[test]
+fn branch_timing() {
+    // Not taken, taken, taken across a page
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_BEQ, 0x10, INS_BNE, 0x80]);
+    assert_eq!(cpu.tick_instruction(&mut memory), 2);
+    assert_eq!(cpu.tick_instruction(&mut memory), 4);
+    assert_eq!(cpu.pc, 0x7F84);
+
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_BNE, 0x02]);
+    assert_eq!(cpu.tick_instruction(&mut memory), 3);
+    assert_eq!(cpu.pc, 0x8004);
+}
+
+#[test]
+fn irq_line_is_taken_at_an_instruction_boundary() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502, &[INS_CLI, INS_NOP]);
+    memory.data[0xFFFE] = 0x00;
+    memory.data[0xFFFF] = 0x90;
+    cpu.status.interrupt_disable = true;
+    cpu.set_irq_line(true);
+    assert_eq!(cpu.tick_instruction(&mut memory), 2);
+    assert_eq!(cpu.tick_instruction(&mut memory), 7);
+    assert_eq!(cpu.pc, 0x9000);
+    assert!(cpu.status.interrupt_disable);
+    // Pushed P has B clear
+    assert_eq!(memory.data[0x01FB] & 0x10, 0);
+    assert_eq!(memory.data[0x01FC], 0x01);
+}
+
+/// Bus with a register that reads back the current cycle number, like a raster
+/// line counter.
+struct ClockedBus {
+    memory: Memory,
+    clock: Byte,
+}
+
+impl Bus for ClockedBus {
+    fn read(&mut self, addr: Word) -> Byte {
+        self.clock += 1;
+        if addr == 0xD012 { self.clock } else { self.memory.data[addr as usize] }
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        self.clock += 1;
+        self.memory.data[addr as usize] = value;
+    }
+}
+
+#[test]
+fn io_registers_are_read_on_the_exact_cycle() {
+    let (mut cpu, memory) = setup(CpuVariant::Nmos6502, &[INS_NOP, INS_LDA_ABS, 0x12, 0xD0]);
+    let mut bus = ClockedBus { memory, clock: 0 };
+    cpu.tick_instruction(&mut bus);
+    cpu.tick_instruction(&mut bus);
+    assert_eq!(cpu.reg_a, 6);
+}
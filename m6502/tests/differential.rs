@@ -0,0 +1,108 @@
+//! Differential fuzzing: random instruction sequences and initial states run on
+//! `Cpu::execute` and on the independent reference model in `reference/`, one
+//! instruction at a time. Registers, flags, memory and cycle counts must agree
+//! after every instruction.
+//!
+//! Runs offline under `cargo test`; set `PROPTEST_CASES` for a longer run.
+
+mod reference;
+
+use m6502::{Cpu, Memory, StatusFlags, disassemble};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use reference::{Model, Registers};
+
+/// Differing addresses listed in a failure.
+const MAX_REPORTED: usize = 8;
+
+/// A documented opcode with two operand bytes; only as many as it needs are used.
+fn instruction() -> impl Strategy<Value = [u8; 3]> {
+    (prop::sample::select(reference::documented()), any::<u8>(), any::<u8>())
+        .prop_map(|(opcode, lo, hi)| [opcode, lo, hi])
+}
+
+fn registers() -> impl Strategy<Value = Registers> {
+    (any::<u16>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>())
+        .prop_map(|(pc, a, x, y, s, p)| Registers { pc, a, x, y, s, p })
+}
+
+/// Random background memory so that pointers, vectors and stack contents vary.
+fn fill(memory: &mut Memory, seed: u64) {
+    let mut state = seed | 1;
+    for byte in memory.data.iter_mut() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        *byte = (state >> 32) as u8;
+    }
+}
+
+fn cpu_registers(cpu: &Cpu) -> Registers {
+    Registers {
+        pc: cpu.pc,
+        a: cpu.reg_a,
+        x: cpu.reg_x,
+        y: cpu.reg_y,
+        s: cpu.sp,
+        p: cpu.status.to_byte(false) & !0x30,
+    }
+}
+
+fn memory_diff(memory: &Memory, model: &Model) -> Vec<String> {
+    if memory.data[..] == model.mem[..] {
+        return Vec::new();
+    }
+    (0..memory.data.len())
+        .filter(|&addr| memory.data[addr] != model.mem[addr])
+        .take(MAX_REPORTED)
+        .map(|addr| format!("{:04X}: cpu {:02X}, reference {:02X}", addr, memory.data[addr], model.mem[addr]))
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn execute_matches_reference(
+        seed in any::<u64>(),
+        program in vec(instruction(), 1..32),
+        initial in registers(),
+    ) {
+        let mut memory = Memory { data: [0; 1024 * 64] };
+        fill(&mut memory, seed);
+        let mut addr = initial.pc;
+        for bytes in &program {
+            for &byte in &bytes[..reference::length(bytes[0]) as usize] {
+                memory.data[addr as usize] = byte;
+                addr = addr.wrapping_add(1);
+            }
+        }
+
+        let mut cpu = Cpu::new();
+        cpu.pc = initial.pc;
+        cpu.reg_a = initial.a;
+        cpu.reg_x = initial.x;
+        cpu.reg_y = initial.y;
+        cpu.sp = initial.s;
+        cpu.status = StatusFlags::from_byte(initial.p);
+        let mut model = Model::new(initial, Box::new(memory.data));
+
+        // Control flow may leave the generated program; stop at the first
+        // undocumented opcode it runs into
+        for step in 0..program.len() {
+            if !reference::is_documented(memory.data[cpu.pc as usize]) {
+                break;
+            }
+            let (text, _) = disassemble(&memory, cpu.pc);
+            let context = format!("step {} at {:04X}: {}", step, cpu.pc, text);
+            let before = model.regs;
+            let expected_cycles = model.step().unwrap();
+            let cycles = cpu.execute(1, &mut memory) as u64;
+
+            prop_assert_eq!(cpu_registers(&cpu), model.regs, "{}, registers before {:?}", context, before);
+            prop_assert_eq!(cycles, expected_cycles, "cycle count of {}", context);
+            let diff = memory_diff(&memory, &model);
+            prop_assert!(diff.is_empty(), "memory after {} (reference wrote {:04X?}):\n{}", context, model.writes, diff.join("\n"));
+        }
+    }
+}
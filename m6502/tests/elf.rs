@@ -0,0 +1,206 @@
+//! ELF loading, on files laid out the way llvm-mos links them.
+
+use m6502::{LoadError, Memory, Symbols, load_elf};
+
+const EM_MOS: u16 = 6502;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_FILE: u8 = 4;
+const SHN_ABS: u16 = 0xFFF1;
+
+struct Segment {
+    vaddr: u32,
+    paddr: u32,
+    data: Vec<u8>,
+    memsz: u32,
+}
+
+struct Symbol {
+    name: &'static str,
+    value: u32,
+    kind: u8,
+    shndx: u16,
+}
+
+/// A minimal ELF32 executable: header, program headers, segment data, then a
+/// symbol table and its string table with their section headers.
+struct ElfFile {
+    machine: u16,
+    entry: u32,
+    segments: Vec<Segment>,
+    symbols: Vec<Symbol>,
+}
+
+fn push16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+impl ElfFile {
+    fn build(&self) -> Vec<u8> {
+        let phoff = 52;
+        let mut data_offset = phoff + 32 * self.segments.len();
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\x7FELF\x01\x01\x01");
+        out.resize(16, 0);
+        push16(&mut out, 2); // ET_EXEC
+        push16(&mut out, self.machine);
+        push32(&mut out, 1);
+        push32(&mut out, self.entry);
+        push32(&mut out, phoff as u32);
+        let shoff_at = out.len();
+        push32(&mut out, 0); // e_shoff, patched below
+        push32(&mut out, 0);
+        push16(&mut out, 52);
+        push16(&mut out, 32);
+        push16(&mut out, self.segments.len() as u16);
+        push16(&mut out, 40);
+        push16(&mut out, 3);
+        push16(&mut out, 0);
+
+        for segment in &self.segments {
+            push32(&mut out, 1); // PT_LOAD
+            push32(&mut out, data_offset as u32);
+            push32(&mut out, segment.vaddr);
+            push32(&mut out, segment.paddr);
+            push32(&mut out, segment.data.len() as u32);
+            push32(&mut out, segment.memsz);
+            push32(&mut out, 7);
+            push32(&mut out, 1);
+            data_offset += segment.data.len();
+        }
+        for segment in &self.segments {
+            out.extend_from_slice(&segment.data);
+        }
+
+        let mut strtab = vec![0];
+        let symtab_offset = out.len();
+        out.resize(out.len() + 16, 0); // the null symbol
+        for symbol in &self.symbols {
+            push32(&mut out, strtab.len() as u32);
+            strtab.extend_from_slice(symbol.name.as_bytes());
+            strtab.push(0);
+            push32(&mut out, symbol.value);
+            push32(&mut out, 0);
+            out.push(0x10 | symbol.kind); // STB_GLOBAL
+            out.push(0);
+            push16(&mut out, symbol.shndx);
+        }
+        let symtab_size = out.len() - symtab_offset;
+        let strtab_offset = out.len();
+        out.extend_from_slice(&strtab);
+
+        let shoff = out.len() as u32;
+        out[shoff_at..shoff_at + 4].copy_from_slice(&shoff.to_le_bytes());
+        out.resize(out.len() + 40, 0); // the null section
+        for (kind, offset, size, link, entsize) in
+            [(2, symtab_offset, symtab_size, 2, 16), (3, strtab_offset, strtab.len(), 0, 0)]
+        {
+            push32(&mut out, 0);
+            push32(&mut out, kind);
+            push32(&mut out, 0);
+            push32(&mut out, 0);
+            push32(&mut out, offset as u32);
+            push32(&mut out, size as u32);
+            push32(&mut out, link);
+            push32(&mut out, 0);
+            push32(&mut out, 1);
+            push32(&mut out, entsize);
+        }
+        out
+    }
+}
+
+fn program() -> ElfFile {
+    ElfFile {
+        machine: EM_MOS,
+        entry: 0x0200,
+        segments: vec![
+            // .text
+            Segment { vaddr: 0x0200, paddr: 0x0200, data: vec![0xA9, 0x42, 0x85, 0x10, 0x60], memsz: 5 },
+            // .data followed by .bss
+            Segment { vaddr: 0x0300, paddr: 0x0300, data: vec![1, 2, 3], memsz: 8 },
+        ],
+        symbols: vec![
+            Symbol { name: "_start", value: 0x0200, kind: STT_FUNC, shndx: 1 },
+            Symbol { name: "table", value: 0x0300, kind: STT_OBJECT, shndx: 2 },
+            Symbol { name: "__rc0", value: 0x0010, kind: 0, shndx: SHN_ABS },
+            Symbol { name: "main.c", value: 0, kind: STT_FILE, shndx: SHN_ABS },
+            Symbol { name: "putchar", value: 0, kind: STT_FUNC, shndx: 0 },
+            Symbol { name: "__heap_size", value: 0x0001_0000, kind: 0, shndx: SHN_ABS },
+        ],
+    }
+}
+
+fn memory() -> Memory {
+    Memory { data: [0xFF; 1024 * 64] }
+}
+
+#[test]
+fn loads_segments_and_entry_point() {
+    let mut memory = memory();
+    let mut symbols = Symbols::new();
+    assert_eq!(load_elf(&program().build(), &mut memory, &mut symbols), Ok(0x0200));
+    assert_eq!(memory.data[0x0200..0x0205], [0xA9, 0x42, 0x85, 0x10, 0x60]);
+    assert_eq!(memory.data[0x0205], 0xFF);
+    // BSS is cleared
+    assert_eq!(memory.data[0x02FF..0x0309], [0xFF, 1, 2, 3, 0, 0, 0, 0, 0, 0xFF]);
+}
+
+#[test]
+fn loads_segments_at_their_physical_address() {
+    let mut file = program();
+    // .data kept in ROM at $0300 and copied to RAM at $0400 by the startup code
+    file.segments[1] = Segment { vaddr: 0x0400, paddr: 0x0300, data: vec![1, 2, 3], memsz: 3 };
+    let mut memory = memory();
+    load_elf(&file.build(), &mut memory, &mut Symbols::new()).unwrap();
+    assert_eq!(memory.data[0x02FF..0x0304], [0xFF, 1, 2, 3, 0xFF]);
+    assert!(memory.data[0x0400..0x0403].iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn imports_defined_symbols() {
+    let mut symbols = Symbols::new();
+    load_elf(&program().build(), &mut memory(), &mut symbols).unwrap();
+    let names: Vec<_> = symbols.iter().collect();
+    assert_eq!(names, [("__rc0", 0x0010), ("_start", 0x0200), ("table", 0x0300)]);
+    assert_eq!(symbols.name(0x0200), Some("_start"));
+    assert_eq!(symbols.addr("table"), Some(0x0300));
+}
+
+#[test]
+fn rejects_other_machines() {
+    let file = ElfFile { machine: 62, ..program() };
+    let error = load_elf(&file.build(), &mut memory(), &mut Symbols::new()).unwrap_err();
+    assert_eq!(error.to_string(), "ELF machine 62 is not a 6502");
+
+    let mut bytes = program().build();
+    bytes[4] = 2; // ELFCLASS64
+    let error = load_elf(&bytes, &mut memory(), &mut Symbols::new()).unwrap_err();
+    assert_eq!(error.to_string(), "only 32-bit little-endian ELF files are supported");
+}
+
+#[test]
+fn rejects_segments_outside_memory() {
+    let mut file = program();
+    file.segments[1] = Segment { vaddr: 0xFFFE, paddr: 0xFFFE, data: vec![1, 2], memsz: 4 };
+    let mut memory = memory();
+    let mut symbols = Symbols::new();
+    let error = load_elf(&file.build(), &mut memory, &mut symbols).unwrap_err();
+    assert_eq!(error.to_string(), "segment of 4 bytes at $FFFE is outside the 64K address space");
+    // Nothing is loaded
+    assert!(memory.data.iter().all(|&b| b == 0xFF));
+    assert!(symbols.is_empty());
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let bytes = program().build();
+    for len in [3, 20, 60, 90] {
+        let result = load_elf(&bytes[..len], &mut memory(), &mut Symbols::new());
+        assert_eq!(result, Err(LoadError::Truncated { offset: len }), "{} bytes", len);
+    }
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use m6502::instructions::*;
+use m6502::{Cpu, CpuVariant, Memory};
+
+fn setup(program: &[u8]) -> (Cpu, Memory) {
+    let (mut cpu, memory) = common::setup(CpuVariant::Nmos6502, program);
+    cpu.illegal_opcodes = true;
+    (cpu, memory)
+}
+
+#[test]
+fn lax_loads_a_and_x() {
+    let (mut cpu, mut memory) = setup(&[INS_LAX_ZP, 0x10]);
+    memory.data[0x10] = 0x80;
+    assert_eq!(cpu.execute(3, &mut memory), 3);
+    assert_eq!(cpu.reg_a, 0x80);
+    assert_eq!(cpu.reg_x, 0x80);
+    assert!(cpu.status.negative);
+    assert!(!cpu.status.zero);
+}
+
+#[test]
+fn sax_stores_a_and_x() {
+    let (mut cpu, mut memory) = setup(&[INS_SAX_ABS, 0x00, 0x20]);
+    cpu.reg_a = 0xF0;
+    cpu.reg_x = 0x3C;
+    assert_eq!(cpu.execute(4, &mut memory), 4);
+    assert_eq!(memory.data[0x2000], 0x30);
+}
+
+#[test]
+fn dcp_decrements_then_compares() {
+    let (mut cpu, mut memory) = setup(&[INS_DCP_ZP, 0x10]);
+    memory.data[0x10] = 0x43;
+    cpu.reg_a = 0x42;
+    assert_eq!(cpu.execute(5, &mut memory), 5);
+    assert_eq!(memory.data[0x10], 0x42);
+    assert!(cpu.status.zero);
+    assert!(cpu.status.carry);
+}
+
+#[test]
+fn isc_increments_then_subtracts() {
+    let (mut cpu, mut memory) = setup(&[INS_ISC_ABSX, 0x00, 0x20]);
+    memory.data[0x2001] = 0x0F;
+    cpu.reg_a = 0x20;
+    cpu.reg_x = 0x01;
+    cpu.status.carry = true;
+    assert_eq!(cpu.execute(7, &mut memory), 7);
+    assert_eq!(memory.data[0x2001], 0x10);
+    assert_eq!(cpu.reg_a, 0x10);
+    assert!(cpu.status.carry);
+}
+
+#[test]
+fn slo_indirect_x_takes_eight_cycles() {
+    let (mut cpu, mut memory) = setup(&[INS_SLO_INDX, 0x10]);
+    cpu.reg_x = 0x02;
+    memory.data[0x12] = 0x00;
+    memory.data[0x13] = 0x30;
+    memory.data[0x3000] = 0x81;
+    cpu.reg_a = 0x01;
+    assert_eq!(cpu.execute(8, &mut memory), 8);
+    assert_eq!(memory.data[0x3000], 0x02);
+    assert_eq!(cpu.reg_a, 0x03);
+    assert!(cpu.status.carry);
+}
+
+#[test]
+fn rra_rotates_then_adds_carry_out() {
+    let (mut cpu, mut memory) = setup(&[INS_RRA_ZP, 0x10]);
+    memory.data[0x10] = 0x03;
+    cpu.reg_a = 0x10;
+    assert_eq!(cpu.execute(5, &mut memory), 5);
+    assert_eq!(memory.data[0x10], 0x01);
+    assert_eq!(cpu.reg_a, 0x12);
+}
+
+#[test]
+fn sbx_and_anc() {
+    let (mut cpu, mut memory) = setup(&[INS_SBX, 0x01, INS_ANC, 0x80]);
+    cpu.reg_a = 0xFF;
+    cpu.reg_x = 0x0F;
+    assert_eq!(cpu.execute(2, &mut memory), 2);
+    assert_eq!(cpu.reg_x, 0x0E);
+    assert!(cpu.status.carry);
+    assert_eq!(cpu.execute(2, &mut memory), 2);
+    assert_eq!(cpu.reg_a, 0x80);
+    assert!(cpu.status.carry);
+    assert!(cpu.status.negative);
+}
+
+#[test]
+fn arr_fixes_up_nibbles_in_decimal_mode() {
+    let (mut cpu, mut memory) = setup(&[INS_ARR, 0xFF, INS_ARR, 0xFF]);
+    cpu.status.decimal_mode = true;
+    cpu.reg_a = 0xFF;
+    assert_eq!(cpu.execute(2, &mut memory), 2);
+    assert_eq!(cpu.reg_a, 0xD5);
+    assert!(cpu.status.carry && !cpu.status.overflow && !cpu.status.negative);
+
+    cpu.reg_a = 0x46;
+    assert_eq!(cpu.execute(2, &mut memory), 2);
+    assert_eq!(cpu.reg_a, 0xA9);
+    assert!(!cpu.status.carry && cpu.status.overflow && cpu.status.negative);
+
+    // The 2A03 has no decimal mode, so D changes nothing
+    let (mut cpu, mut memory) = setup(&[INS_ARR, 0xFF]);
+    cpu.variant = CpuVariant::Ricoh2A03;
+    cpu.status.decimal_mode = true;
+    cpu.reg_a = 0xFF;
+    assert_eq!(cpu.execute(2, &mut memory), 2);
+    assert_eq!(cpu.reg_a, 0x7F);
+    assert!(cpu.status.carry && !cpu.status.overflow);
+}
+
+#[test]
+fn undocumented_nops_consume_operands() {
+    let (mut cpu, mut memory) = setup(&[0x1A, 0x80, 0xFF, 0x14, 0x00, 0x0C, 0x00, 0x00, 0x1C, 0xFF, 0x00]);
+    cpu.reg_x = 0x01;
+    assert_eq!(cpu.execute(2 + 2 + 4 + 4 + 5, &mut memory), 17);
+    assert_eq!(cpu.pc, 0x800B);
+}
+
+#[test]
+fn jam_halts_until_reset() {
+    let (mut cpu, mut memory) = setup(&[0x02, INS_NOP]);
+    cpu.execute(10, &mut memory);
+    assert!(cpu.jammed);
+    assert_eq!(cpu.pc, 0x8000);
+    assert_eq!(cpu.execute(10, &mut memory), 0);
+    cpu.reset(&mut memory);
+    assert!(!cpu.jammed);
+}
+
+#[test]
+#[should_panic(expected = "not implemented")]
+fn undocumented_opcodes_panic_unless_enabled() {
+    let (mut cpu, mut memory) = setup(&[INS_LAX_ZP, 0x10]);
+    cpu.illegal_opcodes = false;
+    cpu.execute(3, &mut memory);
+}
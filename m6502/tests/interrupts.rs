@@ -0,0 +1,44 @@
+mod common;
+
+use m6502::instructions::*;
+use m6502::{Cpu, CpuVariant, Memory};
+
+/// Vectors NMI to $A000 and IRQ to an RTI at $9000.
+fn setup(variant: CpuVariant) -> (Cpu, Memory) {
+    let (cpu, mut memory) = common::setup(variant, &[]);
+    memory.data[0xFFFA] = 0x00;
+    memory.data[0xFFFB] = 0xA0;
+    memory.data[0xFFFE] = 0x00;
+    memory.data[0xFFFF] = 0x90;
+    memory.data[0x9000] = INS_RTI;
+    (cpu, memory)
+}
+
+#[test]
+fn irq_pushes_state_and_rti_restores_it() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502);
+    cpu.status.carry = true;
+    assert_eq!(cpu.irq(&mut memory), 7);
+    assert_eq!(cpu.pc, 0x9000);
+    assert!(cpu.status.interrupt_disable);
+    assert_eq!(memory.data[0x1FD], 0x80);
+    assert_eq!(memory.data[0x1FC], 0x00);
+    assert_eq!(memory.data[0x1FB], 0x21);
+    assert_eq!(cpu.sp, 0xFA);
+
+    assert_eq!(cpu.execute(6, &mut memory), 6);
+    assert_eq!(cpu.pc, 0x8000);
+    assert_eq!(cpu.sp, 0xFD);
+    assert!(cpu.status.carry);
+    assert!(!cpu.status.interrupt_disable);
+}
+
+#[test]
+fn irq_is_masked_but_nmi_is_not() {
+    let (mut cpu, mut memory) = setup(CpuVariant::Nmos6502);
+    cpu.status.interrupt_disable = true;
+    assert_eq!(cpu.irq(&mut memory), 0);
+    assert_eq!(cpu.pc, 0x8000);
+    assert_eq!(cpu.nmi(&mut memory), 7);
+    assert_eq!(cpu.pc, 0xA000);
+}
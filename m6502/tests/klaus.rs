@@ -0,0 +1,161 @@
+//! Klaus Dormann's functional, decimal and interrupt tests. See test-fixtures/README.md
+//! for where each binary is loaded and how it reports its result.
+
+use m6502::{Bus, Byte, Cpu, Memory, Word};
+
+const FUNCTIONAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_functional_test.bin");
+const DECIMAL_TEST: &[u8] = include_bytes!("../test-fixtures/6502_decimal_test.bin");
+const INTERRUPT_TEST: &[u8] = include_bytes!("../test-fixtures/6502_interrupt_test.bin");
+
+const FUNCTIONAL_SUCCESS: Word = 0x3469;
+const FUNCTIONAL_TEST_CASE: usize = 0x0200;
+const DECIMAL_DONE: Word = 0x024B;
+const DECIMAL_ERROR: usize = 0x000B;
+const INTERRUPT_SUCCESS: Word = 0x06E8;
+const FEEDBACK_PORT: usize = 0xBFFC;
+const IRQ_BIT: Byte = 0x01;
+const NMI_BIT: Byte = 0x02;
+
+const MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+fn load(image: &[u8], addr: usize) -> Memory {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    memory.data[addr..addr + image.len()].copy_from_slice(image);
+    memory
+}
+
+fn start(pc: Word) -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.pc = pc;
+    cpu
+}
+
+/// Runs one instruction at a time until PC stops moving, which is how the test
+/// suite signals both success and failure. Returns the trap address.
+fn run_until_trap(cpu: &mut Cpu, memory: &mut Memory, mut step: impl FnMut(&mut Cpu, &mut Memory)) -> Word {
+    for _ in 0..MAX_INSTRUCTIONS {
+        let pc = cpu.pc;
+        step(cpu, memory);
+        if cpu.pc == pc {
+            return pc;
+        }
+    }
+    panic!("no trap after {} instructions, PC = {:04X}", MAX_INSTRUCTIONS, cpu.pc);
+}
+
+fn execute_step(cpu: &mut Cpu, memory: &mut Memory) {
+    cpu.execute(1, memory);
+}
+
+fn tick_step(cpu: &mut Cpu, memory: &mut Memory) {
+    cpu.tick_instruction(memory);
+}
+
+fn functional_test(step: impl FnMut(&mut Cpu, &mut Memory)) {
+    let mut memory = load(FUNCTIONAL_TEST, 0);
+    let mut cpu = start(0x0400);
+    let trap = run_until_trap(&mut cpu, &mut memory, step);
+    assert_eq!(
+        trap, FUNCTIONAL_SUCCESS,
+        "functional test failed at {:04X} in test case {:02X}",
+        trap, memory.data[FUNCTIONAL_TEST_CASE]
+    );
+}
+
+#[test]
+fn functional_test_execute() {
+    functional_test(execute_step);
+}
+
+#[test]
+fn functional_test_tick() {
+    functional_test(tick_step);
+}
+
+fn decimal_test(mut step: impl FnMut(&mut Cpu, &mut Memory)) {
+    let mut memory = load(DECIMAL_TEST, 0x0200);
+    let mut cpu = start(0x0200);
+    for _ in 0..MAX_INSTRUCTIONS {
+        if cpu.pc == DECIMAL_DONE {
+            assert_eq!(
+                memory.data[DECIMAL_ERROR], 0,
+                "decimal test failed: N1 = {:02X}, N2 = {:02X}, carry in = {}",
+                memory.data[0x00], memory.data[0x01], cpu.reg_y
+            );
+            return;
+        }
+        step(&mut cpu, &mut memory);
+    }
+    panic!("decimal test did not finish, PC = {:04X}", cpu.pc);
+}
+
+#[test]
+fn decimal_test_execute() {
+    decimal_test(execute_step);
+}
+
+#[test]
+fn decimal_test_tick() {
+    decimal_test(tick_step);
+}
+
+/// Runs the interrupt test with `execute`, signalling interrupts from the feedback
+/// port between instructions. NMI is edge triggered, IRQ level triggered.
+#[test]
+fn interrupt_test_execute() {
+    let mut memory = load(INTERRUPT_TEST, 0);
+    // The image has $FF at the port; start with both interrupt lines released
+    memory.data[FEEDBACK_PORT] = 0;
+    let mut cpu = start(0x0400);
+    let mut nmi_line = false;
+    let trap = run_until_trap(&mut cpu, &mut memory, |cpu, memory| {
+        cpu.execute(1, memory);
+        let port = memory.data[FEEDBACK_PORT];
+        if port & NMI_BIT != 0 && !nmi_line {
+            cpu.nmi(memory);
+        }
+        nmi_line = port & NMI_BIT != 0;
+        if port & IRQ_BIT != 0 {
+            cpu.irq(memory);
+        }
+    });
+    assert_eq!(trap, INTERRUPT_SUCCESS, "interrupt test failed at {:04X}", trap);
+}
+
+/// Memory with the feedback port wired to the IRQ and NMI inputs of `tick()`.
+struct FeedbackBus<'a> {
+    memory: &'a mut Memory,
+    port: Byte,
+}
+
+impl Bus for FeedbackBus<'_> {
+    fn read(&mut self, addr: Word) -> Byte {
+        self.memory.data[addr as usize]
+    }
+
+    fn write(&mut self, addr: Word, value: Byte) {
+        self.memory.data[addr as usize] = value;
+        if addr as usize == FEEDBACK_PORT {
+            self.port = value;
+        }
+    }
+}
+
+#[test]
+fn interrupt_test_tick() {
+    let mut memory = load(INTERRUPT_TEST, 0);
+    // The image has $FF at the port; start with both interrupt lines released
+    memory.data[FEEDBACK_PORT] = 0;
+    let mut cpu = start(0x0400);
+    let mut port = 0;
+    let trap = run_until_trap(&mut cpu, &mut memory, |cpu, memory| {
+        let mut bus = FeedbackBus { memory, port };
+        cpu.tick_instruction(&mut bus);
+        if bus.port & NMI_BIT != 0 && port & NMI_BIT == 0 {
+            cpu.trigger_nmi();
+        }
+        cpu.set_irq_line(bus.port & IRQ_BIT != 0);
+        port = bus.port;
+    });
+    assert_eq!(trap, INTERRUPT_SUCCESS, "interrupt test failed at {:04X}", trap);
+}
@@ -0,0 +1,165 @@
+//! Program file loaders.
+
+use m6502::{Format, LoadError, Memory, load_intel_hex, load_prg, load_raw, load_srecord};
+
+const PROGRAM: [u8; 5] = [0xA9, 0x42, 0x8D, 0x00, 0x02];
+
+fn memory() -> Memory {
+    Memory { data: [0; 1024 * 64] }
+}
+
+#[test]
+fn detects_formats() {
+    assert_eq!(Format::detect(b":00000001FF\n"), Format::IntelHex);
+    assert_eq!(Format::detect(b"\r\n  :00000001FF"), Format::IntelHex);
+    assert_eq!(Format::detect(b"S00600004844521B\n"), Format::SRecord);
+    assert_eq!(Format::detect(b"NES\x1A\x01\x01"), Format::INes);
+    assert_eq!(Format::detect(&[0xFF, 0xFF, 0x00, 0x20, 0x00, 0x20, 0x60]), Format::Xex);
+    // A segment header that runs past the end, or a ROM padded with $FF
+    assert_eq!(Format::detect(&[0xFF, 0xFF, 0x00, 0x20, 0x01, 0x20, 0x60]), Format::Raw);
+    let mut rom = vec![0xFF; 0x2000];
+    rom[0x1000..0x1003].copy_from_slice(&[0xA9, 0x42, 0x60]);
+    rom[0x1FFC..].copy_from_slice(&[0x00, 0xF0, 0x00, 0xF0]);
+    assert_eq!(Format::detect(&rom), Format::Raw);
+    assert_eq!(Format::detect(&PROGRAM), Format::Raw);
+    // Binaries that happen to start with the same characters
+    assert_eq!(Format::detect(&[b':', b'0', 0x00, 0xFF]), Format::Raw);
+    assert_eq!(Format::detect(&[b'S', b'1', 0xA9, 0x42]), Format::Raw);
+    assert_eq!(Format::detect(b"SED"), Format::Raw);
+    assert_eq!(Format::detect(b""), Format::Raw);
+}
+
+#[test]
+fn loads_intel_hex_with_start_address() {
+    let mut memory = memory();
+    let text = b":05060000A9428D00027B\r\n:02FFFC000006FD\r\n\r\n:0400000500000600F1\r\n:00000001FF\r\n";
+    assert_eq!(load_intel_hex(text, &mut memory), Ok(Some(0x0600)));
+    assert_eq!(memory.data[0x0600..0x0605], PROGRAM);
+    assert_eq!(memory.data[0xFFFC..], [0x00, 0x06, 0x00, 0x00]);
+}
+
+#[test]
+fn intel_hex_start_address_is_optional() {
+    let mut memory = memory();
+    assert_eq!(load_intel_hex(b":05060000A9428D00027B\n:00000001FF\n", &mut memory), Ok(None));
+    assert_eq!(memory.data[0x0600..0x0605], PROGRAM);
+}
+
+#[test]
+fn intel_hex_segment_start_address() {
+    let mut memory = memory();
+    assert_eq!(load_intel_hex(b":040000030080001069\n:00000001FF\n", &mut memory), Ok(Some(0x0810)));
+}
+
+#[test]
+fn intel_hex_ignores_records_after_end() {
+    let mut memory = memory();
+    assert_eq!(load_intel_hex(b":00000001FF\n:0100000001FE\ngarbage\n", &mut memory), Ok(None));
+    assert_eq!(memory.data[0x0000], 0x00);
+}
+
+#[test]
+fn intel_hex_errors() {
+    let cases: &[(&[u8], LoadError)] = &[
+        (b":05060000A9428D00027C\n", LoadError::Checksum { line: 1, expected: 0x7B, found: 0x7C }),
+        (b":05060000A9428D00027B\n", LoadError::MissingEnd),
+        (b"\n05060000A9428D00027B\n", LoadError::Malformed { line: 2, reason: "record does not start with ':'" }),
+        (b":05060000A9428D0002\n", LoadError::Malformed { line: 1, reason: "record length does not match its byte count" }),
+        (b":0506000GA9428D00027B\n", LoadError::Malformed { line: 1, reason: "invalid hex digit" }),
+        (b":0506000A9428D00027B\n", LoadError::Malformed { line: 1, reason: "odd number of hex digits" }),
+        (b":02FFFF000102FD\n:00000001FF\n", LoadError::OutOfRange { line: 1, addr: 0x10000 }),
+        // Extended linear address $0001xxxx
+        (b":020000040001F9\n:0100000001FE\n", LoadError::OutOfRange { line: 2, addr: 0x10000 }),
+        // Extended segment address $FFF0 plus $0010
+        (b":020000020FFFEE\n:020010000102EB\n", LoadError::OutOfRange { line: 2, addr: 0x10000 }),
+        (b":0100000601F8\n", LoadError::UnsupportedRecord { line: 1, record: "06".to_string() }),
+    ];
+    for (text, error) in cases {
+        let mut memory = memory();
+        assert_eq!(load_intel_hex(text, &mut memory).as_ref(), Err(error), "{}", String::from_utf8_lossy(text));
+    }
+}
+
+#[test]
+fn intel_hex_error_leaves_memory_untouched() {
+    let mut memory = memory();
+    let text = b":05060000A9428D00027B\n:05060000A9428D00027C\n:00000001FF\n";
+    assert!(load_intel_hex(text, &mut memory).is_err());
+    assert!(memory.data.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn loads_srecords_with_start_address() {
+    let mut memory = memory();
+    let text = b"S00600004844521B\nS1080600A9428D000277\nS20600C000EAEA65\nS5030002FA\nS9030600F6\n";
+    assert_eq!(load_srecord(text, &mut memory), Ok(Some(0x0600)));
+    assert_eq!(memory.data[0x0600..0x0605], PROGRAM);
+    assert_eq!(memory.data[0xC000..0xC002], [0xEA, 0xEA]);
+}
+
+#[test]
+fn loads_32_bit_srecords() {
+    let mut memory = memory();
+    assert_eq!(load_srecord(b"S3060000030007EF\r\nS70500000300F7\r\n", &mut memory), Ok(Some(0x0300)));
+    assert_eq!(memory.data[0x0300], 0x07);
+    assert_eq!(load_srecord(b"S80400C0003B\n", &mut memory), Ok(Some(0xC000)));
+}
+
+#[test]
+fn srecord_errors() {
+    let cases: &[(&[u8], LoadError)] = &[
+        (b"S1080600A9428D000278\n", LoadError::Checksum { line: 1, expected: 0x77, found: 0x78 }),
+        (b"S1080600A9428D000277\nS5030003F9\n", LoadError::RecordCount { line: 2, expected: 3, found: 1 }),
+        (b"S105FFFF0102F9\n", LoadError::OutOfRange { line: 1, addr: 0x10000 }),
+        (b"S4030000FC\n", LoadError::UnsupportedRecord { line: 1, record: "S4".to_string() }),
+        (b"S1\n", LoadError::Malformed { line: 1, reason: "record length does not match its byte count" }),
+        (b"X1080600A9428D000277\n", LoadError::Malformed { line: 1, reason: "record does not start with 'S' and a digit" }),
+    ];
+    for (text, error) in cases {
+        let mut memory = memory();
+        assert_eq!(load_srecord(text, &mut memory).as_ref(), Err(error), "{}", String::from_utf8_lossy(text));
+    }
+}
+
+#[test]
+fn errors_name_the_line() {
+    let error = load_intel_hex(b"\n:05060000A9428D00027C\n", &mut memory()).unwrap_err();
+    assert_eq!(error.to_string(), "line 2: checksum is $7C, expected $7B");
+    assert_eq!(LoadError::MissingEnd.to_string(), "missing end-of-file record");
+}
+
+#[test]
+fn loads_raw_images() {
+    let mut memory = memory();
+    assert_eq!(load_raw(&PROGRAM, 0x8000, &mut memory), Ok(()));
+    assert_eq!(memory.data[0x8000..0x8005], PROGRAM);
+    assert_eq!(load_raw(&[0xEA; 4], 0xFFFC, &mut memory), Ok(()));
+    assert_eq!(load_raw(&PROGRAM, 0xFFFC, &mut memory), Err(LoadError::TooLarge { addr: 0xFFFC, len: 5 }));
+}
+
+#[test]
+fn loads_prg_at_its_header_address() {
+    let mut memory = memory();
+    let prg = [&[0x01, 0x08][..], &PROGRAM].concat();
+    assert_eq!(load_prg(&prg, None, &mut memory), Ok(0x0801));
+    assert_eq!(memory.data[0x0801..0x0806], PROGRAM);
+    assert_eq!(memory.data[0x0800], 0x00);
+}
+
+#[test]
+fn prg_load_address_can_be_overridden() {
+    let mut memory = memory();
+    let prg = [&[0x01, 0x08][..], &PROGRAM].concat();
+    assert_eq!(load_prg(&prg, Some(0xC000), &mut memory), Ok(0xC000));
+    assert_eq!(memory.data[0xC000..0xC005], PROGRAM);
+    assert_eq!(memory.data[0x0801], 0x00);
+}
+
+#[test]
+fn prg_errors() {
+    let mut memory = memory();
+    assert_eq!(load_prg(&[0x01], None, &mut memory), Err(LoadError::Truncated { offset: 1 }));
+    assert_eq!(load_prg(&[0x00, 0xC0], None, &mut memory), Ok(0xC000));
+    assert_eq!(load_prg(&[0xFF, 0xFF, 1, 2], None, &mut memory), Err(LoadError::TooLarge { addr: 0xFFFF, len: 2 }));
+    assert_eq!(LoadError::TooLarge { addr: 0xFFFF, len: 2 }.to_string(), "2 bytes at $FFFF run past the end of memory");
+}
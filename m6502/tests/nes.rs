@@ -0,0 +1,196 @@
+//! iNES parsing, the NES memory map and the $6000 test ROM harness.
+
+use m6502::nes::{INesRom, Mirroring, NesBus, TestRomOutcome, run_test_rom};
+use m6502::{Bus, Cpu, CpuVariant, LoadError};
+
+/// An iNES 1.0 file with `prg_banks` of 16K PRG ROM, whose first bank starts
+/// with `code` and resets to $8000.
+fn ines(flags6: u8, prg_banks: u8, code: &[u8]) -> Vec<u8> {
+    let mut file = b"NES\x1A".to_vec();
+    file.extend_from_slice(&[prg_banks, 1, flags6, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let mut prg = vec![0xEA; prg_banks as usize * 0x4000];
+    prg[..code.len()].copy_from_slice(code);
+    let vectors = prg.len() - 6;
+    prg[vectors..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    file.extend_from_slice(&prg);
+    file.extend_from_slice(&[0x55; 0x2000]);
+    file
+}
+
+/// Marks the test as running and writes the signature, runs `middle`, then
+/// copies `text` to $6004 and writes `result` to $6000.
+fn test_rom(middle: &[u8], text: &str, result: u8) -> Vec<u8> {
+    let mut code = vec![
+        0xA9, 0x80, 0x8D, 0x00, 0x60, // LDA #$80 / STA $6000
+        0xA9, 0xDE, 0x8D, 0x01, 0x60, // signature
+        0xA9, 0xB0, 0x8D, 0x02, 0x60,
+        0xA9, 0x61, 0x8D, 0x03, 0x60,
+    ];
+    code.extend_from_slice(middle);
+    let start = 0x8000 + code.len() as u16;
+    let text_addr = (start + 21).to_le_bytes();
+    let halt = (start + 18).to_le_bytes();
+    code.extend_from_slice(&[
+        0xA2, 0x00,                               // LDX #0
+        0xBD, text_addr[0], text_addr[1],         // loop: LDA text,X
+        0x9D, 0x04, 0x60,                         // STA $6004,X
+        0xF0, 0x03,                               // BEQ done
+        0xE8,                                     // INX
+        0xD0, 0xF5,                               // BNE loop
+        0xA9, result, 0x8D, 0x00, 0x60,           // done: LDA #result / STA $6000
+        0x4C, halt[0], halt[1],                   // JMP *
+    ]);
+    code.extend_from_slice(text.as_bytes());
+    code.push(0);
+    ines(0, 1, &code)
+}
+
+fn nes_bus(file: &[u8]) -> NesBus {
+    NesBus::new(INesRom::parse(file).unwrap().mapper().unwrap())
+}
+
+fn nes_cpu() -> Cpu {
+    let mut cpu = Cpu::with_variant(CpuVariant::Ricoh2A03);
+    cpu.illegal_opcodes = true;
+    cpu
+}
+
+#[test]
+fn parses_ines_headers() {
+    let mut file = ines(0x13, 2, &[]);
+    file[7] = 0x10; // mapper high nibble
+    let rom = INesRom::parse(&file).unwrap();
+    assert_eq!((rom.mapper, rom.nes2, rom.mirroring, rom.battery), (0x11, false, Mirroring::Vertical, true));
+    assert_eq!((rom.prg_rom.len(), rom.chr_rom.len(), rom.prg_ram_len), (0x8000, 0x2000, 0x2000));
+    assert_eq!(rom.mapper().err().unwrap().to_string(), "mapper 17 is not supported");
+
+    // A header with junk from byte 7 on keeps only the low mapper nibble
+    file[7..16].copy_from_slice(b"DiskDude!");
+    assert_eq!(INesRom::parse(&file).unwrap().mapper, 1);
+}
+
+#[test]
+fn parses_nes2_headers() {
+    // The trainer takes the first 512 bytes, so leave out CHR ROM
+    let mut file = ines(0x0C, 1, &[]);
+    file[5] = 0;
+    file[7] = 0x08;
+    file[8] = 0x21; // submapper 2, mapper bits 8-11 = 1
+    file[10] = 0x70; // 8K of battery-backed PRG RAM
+    let rom = INesRom::parse(&file).unwrap();
+    assert_eq!((rom.mapper, rom.submapper, rom.nes2), (0x100, 2, true));
+    assert_eq!((rom.mirroring, rom.prg_ram_len), (Mirroring::FourScreen, 0x2000));
+    assert_eq!(rom.trainer, Some(vec![0xEA; 512]));
+    assert_eq!(rom.prg_rom.len(), 0x4000);
+
+    // Exponent-multiplier size: 2^13 * 3 bytes of PRG ROM
+    let mut file = b"NES\x1A".to_vec();
+    file.extend_from_slice(&[0x35, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    file.resize(16 + 0x6000, 0);
+    assert_eq!(INesRom::parse(&file).unwrap().prg_rom.len(), 0x6000);
+    assert_eq!(INesRom::parse(&file[..16 + 0x5FFF]), Err(LoadError::Truncated { offset: 16 + 0x5FFF }));
+    assert_eq!(INesRom::parse(b"NES\x1A\x01"), Err(LoadError::Truncated { offset: 5 }));
+}
+
+#[test]
+fn maps_ram_rom_and_registers() {
+    let mut bus = nes_bus(&ines(0, 1, &[0xA9, 0x42]));
+    // 2K of RAM mirrored four times
+    bus.write(0x0123, 0x77);
+    assert_eq!([bus.read(0x0923), bus.read(0x1123), bus.read(0x1923)], [0x77; 3]);
+    // A 16K PRG ROM appears at $8000 and $C000
+    assert_eq!([bus.read(0x8000), bus.read(0xC001), bus.read(0xFFFD)], [0xA9, 0x42, 0x80]);
+    // PRG RAM
+    bus.write(0x6000, 0x81);
+    assert_eq!(bus.peek(0x6000), 0x81);
+    // PPU registers mirrored every 8 bytes
+    bus.write(0x3FF8, 0x80);
+    assert_eq!(bus.ppu.ctrl, 0x80);
+    bus.write(0x4000, 0x3F);
+    assert_eq!((bus.apu.registers[0], bus.read(0x4015)), (0x3F, 0));
+    assert_eq!(bus.cycles(), 11);
+
+    assert_eq!(
+        INesRom::parse(&ines(0, 3, &[])).unwrap().mapper().err().unwrap().to_string(),
+        "NROM needs 16K or 32K of PRG ROM, not 49152 bytes"
+    );
+}
+
+#[test]
+fn signals_vblank_once_a_frame() {
+    let mut bus = nes_bus(&ines(0, 1, &[]));
+    bus.write(0x2000, 0x80); // NMI on vblank
+    let mut frames = 0;
+    for _ in 0..2 * 29781 {
+        if bus.read(0x2002) & 0x80 != 0 {
+            frames += 1;
+            // Reading the status clears the flag
+            assert_eq!(bus.read(0x2002) & 0x80, 0);
+        }
+    }
+    assert_eq!(frames, 2);
+    assert!(bus.ppu.take_nmi());
+    assert!(!bus.ppu.take_nmi());
+}
+
+#[test]
+fn enabling_nmi_during_vblank_raises_it() {
+    let mut bus = nes_bus(&ines(0, 1, &[]));
+    while bus.peek(0x2002) & 0x80 == 0 {
+        bus.read(0x0000);
+    }
+    assert!(!bus.ppu.take_nmi());
+    bus.write(0x2000, 0x80);
+    assert!(bus.ppu.take_nmi());
+    // Only the change from disabled to enabled counts
+    bus.write(0x2000, 0x80);
+    assert!(!bus.ppu.take_nmi());
+
+    // Not once vblank has been acknowledged
+    bus.write(0x2000, 0x00);
+    bus.read(0x2002);
+    bus.write(0x2000, 0x80);
+    assert!(!bus.ppu.take_nmi());
+}
+
+#[test]
+fn reports_the_result_and_text() {
+    let mut bus = nes_bus(&test_rom(&[], "\nPassed\n", 0));
+    let report = run_test_rom(&mut nes_cpu(), &mut bus, 1_000_000);
+    assert_eq!(report.outcome, TestRomOutcome::Finished(0));
+    assert_eq!(report.text, "\nPassed\n");
+
+    let mut bus = nes_bus(&test_rom(&[], "Failed #3", 3));
+    let report = run_test_rom(&mut nes_cpu(), &mut bus, 1_000_000);
+    assert_eq!((report.outcome, report.text.as_str()), (TestRomOutcome::Finished(3), "Failed #3"));
+}
+
+#[test]
+fn presses_reset_when_asked() {
+    // Counts boots in RAM, which a reset keeps, and asks for a reset on the first
+    let middle = [
+        0xEE, 0x00, 0x02, // INC $0200
+        0xAD, 0x00, 0x02, // LDA $0200
+        0xC9, 0x02,       // CMP #2
+        0xF0, 0x08,       // BEQ +8
+        0xA9, 0x81, 0x8D, 0x00, 0x60, // LDA #$81 / STA $6000
+        0x4C, 0x23, 0x80, // JMP *
+    ];
+    let mut bus = nes_bus(&test_rom(&middle, "Passed", 0));
+    let report = run_test_rom(&mut nes_cpu(), &mut bus, 1_000_000);
+    assert_eq!(report.outcome, TestRomOutcome::Finished(0));
+    assert_eq!(bus.ram[0x200], 2);
+    assert!(report.cycles > 6 * 29781, "{}", report.cycles);
+}
+
+#[test]
+fn times_out_and_jams() {
+    // Never writes the signature
+    let mut bus = nes_bus(&ines(0, 1, &[0x4C, 0x00, 0x80]));
+    let report = run_test_rom(&mut nes_cpu(), &mut bus, 10_000);
+    assert_eq!(report.outcome, TestRomOutcome::TimedOut);
+    assert!(report.cycles >= 10_000 && report.text.is_empty());
+
+    let mut bus = nes_bus(&ines(0, 1, &[0x02]));
+    assert_eq!(run_test_rom(&mut nes_cpu(), &mut bus, 10_000).outcome, TestRomOutcome::Jammed);
+}
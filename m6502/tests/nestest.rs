@@ -0,0 +1,149 @@
+//! Runs the CPU section of kevtris' nestest ROM from $C000 and compares every
+//! trace line with the Nintendulator reference log, stopping at the first
+//! divergence. See test-fixtures/README.md.
+
+use m6502::{Cpu, CpuVariant, Memory, StatusFlags};
+
+const ROM: &[u8] = include_bytes!("../test-fixtures/nestest.nes");
+const LOG: &str = include_str!("../test-fixtures/nestest.log");
+
+const INES_HEADER: usize = 16;
+const PRG_BANK: usize = 16 * 1024;
+
+/// Lines of our own trace shown before the divergence.
+const CONTEXT: usize = 5;
+
+/// The fields of a trace line that are compared; the disassembly and the PPU
+/// column are only shown.
+#[derive(PartialEq, Eq)]
+struct TraceState {
+    pc: String,
+    bytes: String,
+    a: String,
+    x: String,
+    y: String,
+    p: String,
+    sp: String,
+    cyc: String,
+}
+
+impl TraceState {
+    fn parse(line: &str) -> Self {
+        let field = |name: &str| {
+            line.split_whitespace()
+                .find_map(|token| token.strip_prefix(name))
+                .unwrap_or_else(|| panic!("no {} in trace line {:?}", name, line))
+                .to_string()
+        };
+        TraceState {
+            pc: line[0..4].to_string(),
+            bytes: line[6..14].trim().to_string(),
+            a: field("A:"),
+            x: field("X:"),
+            y: field("Y:"),
+            p: field("P:"),
+            sp: field("SP:"),
+            cyc: field("CYC:"),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &str); 8] {
+        [
+            ("PC", &self.pc),
+            ("bytes", &self.bytes),
+            ("A", &self.a),
+            ("X", &self.x),
+            ("Y", &self.y),
+            ("P", &self.p),
+            ("SP", &self.sp),
+            ("CYC", &self.cyc),
+        ]
+    }
+}
+
+/// Side by side comparison of the expected and actual registers and cycles.
+fn diff(expected: &TraceState, actual: &TraceState) -> String {
+    let mut out = format!("  {:<6} {:<10} {:<10}\n", "", "expected", "actual");
+    for ((name, want), (_, got)) in expected.fields().into_iter().zip(actual.fields()) {
+        let marker = if want == got { "" } else { "  <--" };
+        out += &format!("  {:<6} {:<10} {:<10}{}\n", name, want, got, marker);
+    }
+    out
+}
+
+fn load_nestest() -> Memory {
+    assert_eq!(&ROM[0..4], b"NES\x1A", "nestest.nes is not an iNES image");
+    let prg = &ROM[INES_HEADER..INES_HEADER + PRG_BANK];
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    // NROM-128: the single PRG bank is mirrored at $8000 and $C000
+    memory.data[0x8000..0xC000].copy_from_slice(prg);
+    memory.data[0xC000..0x10000].copy_from_slice(prg);
+    memory
+}
+
+#[test]
+fn nestest_matches_reference_log() {
+    let mut memory = load_nestest();
+    let mut cpu = Cpu::with_variant(CpuVariant::Ricoh2A03);
+    cpu.illegal_opcodes = true;
+    // Automated mode: start at $C000 with the state the log expects after reset
+    cpu.pc = 0xC000;
+    cpu.sp = 0xFD;
+    cpu.status = StatusFlags::from_byte(0x24);
+    let mut cycles: u64 = 7;
+
+    let mut history: Vec<String> = Vec::new();
+    for (number, expected_line) in LOG.lines().enumerate() {
+        let line = cpu.trace_line(&memory, cycles);
+        let expected = TraceState::parse(expected_line);
+        let actual = TraceState::parse(&line);
+        if expected != actual {
+            let start = history.len().saturating_sub(CONTEXT);
+            panic!(
+                "nestest diverged at log line {}\n{}\n  expected: {}\n  actual:   {}\n\n{}",
+                number + 1,
+                history[start..].iter().map(|l| format!("            {}\n", l)).collect::<String>(),
+                expected_line,
+                line,
+                diff(&expected, &actual)
+            );
+        }
+        history.push(line);
+        cycles += cpu.execute(1, &mut memory) as u64;
+    }
+
+    // The ROM leaves the number of the first failed test in $02 (documented) and $03 (undocumented)
+    assert_eq!(memory.data[0x02], 0, "nestest reports documented opcode failure {:02X}", memory.data[0x02]);
+    assert_eq!(memory.data[0x03], 0, "nestest reports undocumented opcode failure {:02X}", memory.data[0x03]);
+}
+
+/// Fixed-capacity `fmt::Write`, standing in for a `no_std` caller without an allocator.
+struct StackBuffer {
+    bytes: [u8; 128],
+    len: usize,
+}
+
+impl std::fmt::Write for StackBuffer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(std::fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn write_trace_line_matches_trace_line() {
+    let memory = load_nestest();
+    let mut cpu = Cpu::with_variant(CpuVariant::Ricoh2A03);
+    cpu.pc = 0xC000;
+    cpu.sp = 0xFD;
+    cpu.status = StatusFlags::from_byte(0x24);
+    let mut buffer = StackBuffer { bytes: [0; 128], len: 0 };
+    cpu.write_trace_line(&mut buffer, &memory, 7).unwrap();
+    assert_eq!(std::str::from_utf8(&buffer.bytes[..buffer.len]).unwrap(), cpu.trace_line(&memory, 7));
+
+    let mut buffer = StackBuffer { bytes: [0; 128], len: 0 };
+    assert_eq!(m6502::write_disassembly(&mut buffer, &memory, 0xC000), Ok(3));
+    assert_eq!(std::str::from_utf8(&buffer.bytes[..buffer.len]).unwrap(), "JMP $C5F5");
+}
@@ -0,0 +1,291 @@
+//! o65 relocatable modules, laid out the way xa writes them.
+
+use m6502::{Cpu, CpuVariant, LoadError, Memory, O65Cpu, O65Header, O65Layout, Symbols, load_o65};
+
+const WORD: u8 = 0x80;
+const HIGH: u8 = 0x40;
+const LOW: u8 = 0x20;
+const UNDEFINED: u8 = 0;
+const TEXT: u8 = 2;
+const DATA: u8 = 3;
+const BSS: u8 = 4;
+const ZERO: u8 = 5;
+
+const CPU_65C02: u16 = 0x0010;
+const BSSZERO: u16 = 0x0200;
+const PAGEWISE: u16 = 0x4000;
+
+/// A relocation entry: offset in its segment, type byte, and the bytes that
+/// follow the type byte.
+struct Reloc(usize, u8, Vec<u8>);
+
+struct O65File {
+    mode: u16,
+    layout: O65Layout,
+    text: Vec<u8>,
+    data: Vec<u8>,
+    bss_len: u16,
+    zero_len: u16,
+    undefined: Vec<&'static str>,
+    text_relocs: Vec<Reloc>,
+    data_relocs: Vec<Reloc>,
+    exports: Vec<(&'static str, u8, u16)>,
+}
+
+fn push16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_relocs(out: &mut Vec<u8>, relocs: &[Reloc]) {
+    let mut position = -1isize;
+    for Reloc(offset, kind, extra) in relocs {
+        let mut step = *offset as isize - position;
+        while step > 254 {
+            out.push(255);
+            step -= 254;
+        }
+        out.push(step as u8);
+        out.push(*kind);
+        out.extend_from_slice(extra);
+        position = *offset as isize;
+    }
+    out.push(0);
+}
+
+impl O65File {
+    fn build(&self) -> Vec<u8> {
+        let mut out = vec![0x01, 0x00, b'o', b'6', b'5', 0x00];
+        push16(&mut out, self.mode);
+        for (base, len) in [
+            (self.layout.text, self.text.len() as u16),
+            (self.layout.data, self.data.len() as u16),
+            (self.layout.bss, self.bss_len),
+            (self.layout.zero, self.zero_len),
+        ] {
+            push16(&mut out, base);
+            push16(&mut out, len);
+        }
+        push16(&mut out, 0); // stack
+        out.extend_from_slice(b"\x0B\x00test.o65\x00"); // file name option
+        out.push(0);
+        out.extend_from_slice(&self.text);
+        out.extend_from_slice(&self.data);
+        push16(&mut out, self.undefined.len() as u16);
+        for name in &self.undefined {
+            out.extend_from_slice(name.as_bytes());
+            out.push(0);
+        }
+        push_relocs(&mut out, &self.text_relocs);
+        push_relocs(&mut out, &self.data_relocs);
+        push16(&mut out, self.exports.len() as u16);
+        for (name, segment, value) in &self.exports {
+            out.extend_from_slice(name.as_bytes());
+            out.push(0);
+            out.push(*segment);
+            push16(&mut out, *value);
+        }
+        out
+    }
+}
+
+/// A module assembled at text $1000, data $2000, bss $3000 and zero page $10.
+fn module() -> O65File {
+    let mut text = vec![
+        0xAD, 0x00, 0x20, // $1000 LDA message
+        0x85, 0x10,       // $1003 STA ptr
+        0xA9, 0x30,       // $1005 LDA #>buffer
+        0xA2, 0x02,       // $1007 LDX #<buffer+2
+        0x20, 0x00, 0x00, // $1009 JSR print
+    ];
+    text.resize(300, 0xEA);
+    text.extend_from_slice(&[0x4C, 0x00, 0x10]); // $112C JMP $1000
+    O65File {
+        mode: BSSZERO,
+        layout: O65Layout { text: 0x1000, data: 0x2000, bss: 0x3000, zero: 0x0010 },
+        text,
+        data: vec![b'H', b'I', 0x09, 0x10], // message, then a pointer to the JSR
+        bss_len: 4,
+        zero_len: 2,
+        undefined: vec!["print"],
+        text_relocs: vec![
+            Reloc(1, WORD | DATA, vec![]),
+            Reloc(4, LOW | ZERO, vec![]),
+            Reloc(6, HIGH | BSS, vec![0x02]),
+            Reloc(8, LOW | BSS, vec![]),
+            Reloc(10, WORD | UNDEFINED, vec![0, 0]),
+            Reloc(301, WORD | TEXT, vec![]),
+        ],
+        data_relocs: vec![Reloc(2, WORD | TEXT, vec![])],
+        exports: vec![("start", TEXT, 0x1000), ("message", DATA, 0x2000), ("ptr", ZERO, 0x0010)],
+    }
+}
+
+const LAYOUT: O65Layout = O65Layout { text: 0xC000, data: 0x0400, bss: 0x05FF, zero: 0x0080 };
+
+fn memory() -> Memory {
+    Memory { data: [0xFF; 1024 * 64] }
+}
+
+fn kernal() -> Symbols {
+    let mut symbols = Symbols::new();
+    symbols.insert("print", 0xFFD2);
+    symbols
+}
+
+#[test]
+fn relocates_every_segment() {
+    let mut memory = memory();
+    let mut symbols = kernal();
+    let header = load_o65(&module().build(), &LAYOUT, &mut memory, &mut symbols).unwrap();
+    assert_eq!(header.layout, module().layout);
+    assert_eq!(header.options, [(0, b"test.o65\0".to_vec())]);
+
+    assert_eq!(
+        memory.data[0xC000..0xC00C],
+        [0xAD, 0x00, 0x04, 0x85, 0x80, 0xA9, 0x06, 0xA2, 0x01, 0x20, 0xD2, 0xFF]
+    );
+    // An entry more than 254 bytes after the previous one
+    assert_eq!(memory.data[0xC12C..0xC12F], [0x4C, 0x00, 0xC0]);
+    assert_eq!(memory.data[0x0400..0x0404], [b'H', b'I', 0x09, 0xC0]);
+    // BSS is cleared, the zero page is not
+    assert_eq!(memory.data[0x05FF..0x0604], [0, 0, 0, 0, 0xFF]);
+    assert_eq!(memory.data[0x0080], 0xFF);
+
+    let names: Vec<_> = symbols.iter().collect();
+    assert_eq!(names, [("message", 0x0400), ("print", 0xFFD2), ("ptr", 0x0080), ("start", 0xC000)]);
+}
+
+#[test]
+fn loads_where_assembled() {
+    let file = module().build();
+    let header = O65Header::parse(&file).unwrap();
+    assert_eq!((header.text_len, header.data_len, header.bss_len, header.zero_len), (303, 4, 4, 2));
+    let mut memory = memory();
+    load_o65(&file, &header.layout, &mut memory, &mut kernal()).unwrap();
+    assert_eq!(memory.data[0x1000..0x1003], [0xAD, 0x00, 0x20]);
+    assert_eq!(memory.data[0x1009..0x100C], [0x20, 0xD2, 0xFF]);
+}
+
+#[test]
+fn modules_import_what_earlier_ones_export() {
+    let mut memory = memory();
+    let mut symbols = Symbols::new();
+    let library = O65File {
+        text: vec![0x60], // RTS
+        data: vec![],
+        bss_len: 0,
+        zero_len: 0,
+        undefined: vec![],
+        text_relocs: vec![],
+        data_relocs: vec![],
+        exports: vec![("print", TEXT, 0x1000)],
+        ..module()
+    };
+    let library_layout = O65Layout { text: 0xE000, ..LAYOUT };
+    load_o65(&library.build(), &library_layout, &mut memory, &mut symbols).unwrap();
+    load_o65(&module().build(), &LAYOUT, &mut memory, &mut symbols).unwrap();
+    assert_eq!(memory.data[0xC009..0xC00C], [0x20, 0x00, 0xE0]);
+}
+
+/// A module of just `text`, storing to a one-byte data segment at offset 1.
+fn counter_module(mode: u16, text: Vec<u8>) -> O65File {
+    O65File {
+        mode,
+        text,
+        data: vec![0xAA],
+        bss_len: 0,
+        zero_len: 0,
+        undefined: vec![],
+        text_relocs: vec![Reloc(3, WORD | DATA, vec![])],
+        data_relocs: vec![],
+        exports: vec![],
+        ..module()
+    }
+}
+
+#[test]
+fn reads_the_cpu_from_the_mode() {
+    let nmos = O65Header::parse(&counter_module(0, vec![0; 6]).build()).unwrap();
+    assert_eq!(nmos.cpu, O65Cpu::Mos6502);
+    assert!(nmos.cpu.runs_on(CpuVariant::Nmos6502) && nmos.cpu.runs_on(CpuVariant::Wdc65C02));
+
+    let cmos = O65Header::parse(&counter_module(CPU_65C02, vec![0; 6]).build()).unwrap();
+    assert_eq!(cmos.cpu, O65Cpu::Cmos65C02);
+    assert!(cmos.cpu.runs_on(CpuVariant::Cmos65C02) && cmos.cpu.runs_on(CpuVariant::Rockwell65C02));
+    assert!(!cmos.cpu.runs_on(CpuVariant::Nmos6502) && !cmos.cpu.runs_on(CpuVariant::Ricoh2A03));
+
+    let native = O65Header::parse(&counter_module(0x8000, vec![0; 6]).build()).unwrap();
+    assert_eq!(native.cpu, O65Cpu::W65816);
+    assert!(!native.cpu.runs_on(CpuVariant::Wdc65C02));
+}
+
+#[test]
+fn runs_relocated_6502_and_65c02_code() {
+    // LDA #1 / STA counter / BRK
+    let nmos = counter_module(0, vec![0xA9, 0x01, 0x8D, 0x00, 0x20, 0x00]);
+    // BRA +0 / STZ counter / BRK, which need a 65C02
+    let cmos = counter_module(CPU_65C02, vec![0x80, 0x00, 0x9C, 0x00, 0x20, 0x00]);
+    for (file, variant, expected) in [(nmos, CpuVariant::Nmos6502, 1), (cmos, CpuVariant::Cmos65C02, 0)] {
+        let mut memory = memory();
+        let header = load_o65(&file.build(), &LAYOUT, &mut memory, &mut Symbols::new()).unwrap();
+        assert!(header.cpu.runs_on(variant));
+        let mut cpu = Cpu::with_variant(variant);
+        cpu.pc = LAYOUT.text;
+        cpu.execute(6, &mut memory);
+        assert_eq!(cpu.pc, LAYOUT.text + 5, "{:?}", header.cpu);
+        assert_eq!(memory.data[LAYOUT.data as usize], expected, "{:?}", header.cpu);
+    }
+}
+
+#[test]
+fn rejects_undefined_symbols_without_loading() {
+    let mut memory = memory();
+    let mut symbols = Symbols::new();
+    let error = load_o65(&module().build(), &LAYOUT, &mut memory, &mut symbols).unwrap_err();
+    assert_eq!(error, LoadError::UndefinedSymbol("print".to_string()));
+    assert_eq!(error.to_string(), "undefined symbol 'print'");
+    assert!(memory.data.iter().all(|&b| b == 0xFF));
+    assert!(symbols.is_empty());
+}
+
+#[test]
+fn relocates_pagewise_modules_by_whole_pages() {
+    // HIGH entries of pagewise modules have no low byte
+    let mut file = module();
+    file.mode = PAGEWISE;
+    file.text_relocs[2] = Reloc(6, HIGH | BSS, vec![]);
+    let layout = O65Layout { text: 0xC000, data: 0x0400, bss: 0x0600, zero: 0x0080 };
+    let mut memory = memory();
+    load_o65(&file.build(), &layout, &mut memory, &mut kernal()).unwrap();
+    assert_eq!(memory.data[0xC005..0xC009], [0xA9, 0x06, 0xA2, 0x02]);
+
+    let error = load_o65(&file.build(), &LAYOUT, &mut memory, &mut kernal()).unwrap_err();
+    assert_eq!(error.to_string(), "bss segment can only move by whole pages");
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let mut memory = memory();
+    let layout = O65Layout { zero: 0x00FF, ..LAYOUT };
+    let error = load_o65(&module().build(), &layout, &mut memory, &mut kernal()).unwrap_err();
+    assert_eq!(error.to_string(), "zero page segment of 2 bytes at $00FF runs past $FF");
+
+    let mut file = module();
+    file.mode = 1; // word aligned
+    let error = load_o65(&file.build(), &LAYOUT, &mut memory, &mut kernal()).unwrap_err();
+    assert_eq!(error.to_string(), "bss segment at $05FF is not aligned to 2 bytes");
+
+    let mut file = module();
+    file.text_relocs.push(Reloc(302, WORD | TEXT, vec![]));
+    let error = load_o65(&file.build(), &LAYOUT, &mut memory, &mut kernal()).unwrap_err();
+    assert_eq!(error.to_string(), "relocation at offset 302 is outside the text segment");
+
+    let bytes = module().build();
+    for len in [3, 20, 40, 400] {
+        let result = load_o65(&bytes[..len], &LAYOUT, &mut memory, &mut kernal());
+        assert_eq!(result, Err(LoadError::Truncated { offset: len }), "{} bytes", len);
+    }
+    let error = O65Header::parse(b"\x01\x00o65\x00\x00\x20").unwrap_err();
+    assert_eq!(error.to_string(), "only o65 files with 16-bit addresses are supported");
+    assert!(memory.data.iter().all(|&b| b == 0xFF));
+}
@@ -0,0 +1,367 @@
+//! Independent, table-driven model of the documented NMOS 6502 instruction set,
+//! used as the oracle for differential testing. It deliberately shares no code
+//! with the crate: every opcode is one row of `TABLE` and the operations work on
+//! a packed P byte.
+
+const C: u8 = 0x01;
+const Z: u8 = 0x02;
+const I: u8 = 0x04;
+const D: u8 = 0x08;
+const B: u8 = 0x10;
+const U: u8 = 0x20;
+const V: u8 = 0x40;
+const N: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Brk, Bvc, Bvs, Clc, Cld, Cli, Clv,
+    Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp, Jsr, Lda, Ldx, Ldy, Lsr, Nop,
+    Ora, Pha, Php, Pla, Plp, Rol, Ror, Rti, Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax,
+    Tay, Tsx, Txa, Txs, Tya,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode { Imp, Acc, Imm, Zp, Zpx, Zpy, Abs, Abx, Aby, Ind, Izx, Izy, Rel }
+
+use Mode::*;
+use Op::*;
+
+/// Opcode, operation, addressing mode and base cycle count. Reads through
+/// `abs,X`, `abs,Y` and `(zp),Y` take one more cycle when indexing crosses a page;
+/// taken branches one more, plus one when the target is on another page.
+const TABLE: &[(u8, Op, Mode, u64)] = &[
+    (0x69, Adc, Imm, 2), (0x65, Adc, Zp, 3), (0x75, Adc, Zpx, 4), (0x6D, Adc, Abs, 4),
+    (0x7D, Adc, Abx, 4), (0x79, Adc, Aby, 4), (0x61, Adc, Izx, 6), (0x71, Adc, Izy, 5),
+    (0x29, And, Imm, 2), (0x25, And, Zp, 3), (0x35, And, Zpx, 4), (0x2D, And, Abs, 4),
+    (0x3D, And, Abx, 4), (0x39, And, Aby, 4), (0x21, And, Izx, 6), (0x31, And, Izy, 5),
+    (0x0A, Asl, Acc, 2), (0x06, Asl, Zp, 5), (0x16, Asl, Zpx, 6), (0x0E, Asl, Abs, 6), (0x1E, Asl, Abx, 7),
+    (0x90, Bcc, Rel, 2), (0xB0, Bcs, Rel, 2), (0xF0, Beq, Rel, 2), (0x30, Bmi, Rel, 2),
+    (0xD0, Bne, Rel, 2), (0x10, Bpl, Rel, 2), (0x50, Bvc, Rel, 2), (0x70, Bvs, Rel, 2),
+    (0x24, Bit, Zp, 3), (0x2C, Bit, Abs, 4),
+    (0x00, Brk, Imp, 7),
+    (0x18, Clc, Imp, 2), (0xD8, Cld, Imp, 2), (0x58, Cli, Imp, 2), (0xB8, Clv, Imp, 2),
+    (0xC9, Cmp, Imm, 2), (0xC5, Cmp, Zp, 3), (0xD5, Cmp, Zpx, 4), (0xCD, Cmp, Abs, 4),
+    (0xDD, Cmp, Abx, 4), (0xD9, Cmp, Aby, 4), (0xC1, Cmp, Izx, 6), (0xD1, Cmp, Izy, 5),
+    (0xE0, Cpx, Imm, 2), (0xE4, Cpx, Zp, 3), (0xEC, Cpx, Abs, 4),
+    (0xC0, Cpy, Imm, 2), (0xC4, Cpy, Zp, 3), (0xCC, Cpy, Abs, 4),
+    (0xC6, Dec, Zp, 5), (0xD6, Dec, Zpx, 6), (0xCE, Dec, Abs, 6), (0xDE, Dec, Abx, 7),
+    (0xCA, Dex, Imp, 2), (0x88, Dey, Imp, 2),
+    (0x49, Eor, Imm, 2), (0x45, Eor, Zp, 3), (0x55, Eor, Zpx, 4), (0x4D, Eor, Abs, 4),
+    (0x5D, Eor, Abx, 4), (0x59, Eor, Aby, 4), (0x41, Eor, Izx, 6), (0x51, Eor, Izy, 5),
+    (0xE6, Inc, Zp, 5), (0xF6, Inc, Zpx, 6), (0xEE, Inc, Abs, 6), (0xFE, Inc, Abx, 7),
+    (0xE8, Inx, Imp, 2), (0xC8, Iny, Imp, 2),
+    (0x4C, Jmp, Abs, 3), (0x6C, Jmp, Ind, 5), (0x20, Jsr, Abs, 6),
+    (0xA9, Lda, Imm, 2), (0xA5, Lda, Zp, 3), (0xB5, Lda, Zpx, 4), (0xAD, Lda, Abs, 4),
+    (0xBD, Lda, Abx, 4), (0xB9, Lda, Aby, 4), (0xA1, Lda, Izx, 6), (0xB1, Lda, Izy, 5),
+    (0xA2, Ldx, Imm, 2), (0xA6, Ldx, Zp, 3), (0xB6, Ldx, Zpy, 4), (0xAE, Ldx, Abs, 4), (0xBE, Ldx, Aby, 4),
+    (0xA0, Ldy, Imm, 2), (0xA4, Ldy, Zp, 3), (0xB4, Ldy, Zpx, 4), (0xAC, Ldy, Abs, 4), (0xBC, Ldy, Abx, 4),
+    (0x4A, Lsr, Acc, 2), (0x46, Lsr, Zp, 5), (0x56, Lsr, Zpx, 6), (0x4E, Lsr, Abs, 6), (0x5E, Lsr, Abx, 7),
+    (0xEA, Nop, Imp, 2),
+    (0x09, Ora, Imm, 2), (0x05, Ora, Zp, 3), (0x15, Ora, Zpx, 4), (0x0D, Ora, Abs, 4),
+    (0x1D, Ora, Abx, 4), (0x19, Ora, Aby, 4), (0x01, Ora, Izx, 6), (0x11, Ora, Izy, 5),
+    (0x48, Pha, Imp, 3), (0x08, Php, Imp, 3), (0x68, Pla, Imp, 4), (0x28, Plp, Imp, 4),
+    (0x2A, Rol, Acc, 2), (0x26, Rol, Zp, 5), (0x36, Rol, Zpx, 6), (0x2E, Rol, Abs, 6), (0x3E, Rol, Abx, 7),
+    (0x6A, Ror, Acc, 2), (0x66, Ror, Zp, 5), (0x76, Ror, Zpx, 6), (0x6E, Ror, Abs, 6), (0x7E, Ror, Abx, 7),
+    (0x40, Rti, Imp, 6), (0x60, Rts, Imp, 6),
+    (0xE9, Sbc, Imm, 2), (0xE5, Sbc, Zp, 3), (0xF5, Sbc, Zpx, 4), (0xED, Sbc, Abs, 4),
+    (0xFD, Sbc, Abx, 4), (0xF9, Sbc, Aby, 4), (0xE1, Sbc, Izx, 6), (0xF1, Sbc, Izy, 5),
+    (0x38, Sec, Imp, 2), (0xF8, Sed, Imp, 2), (0x78, Sei, Imp, 2),
+    (0x85, Sta, Zp, 3), (0x95, Sta, Zpx, 4), (0x8D, Sta, Abs, 4), (0x9D, Sta, Abx, 5),
+    (0x99, Sta, Aby, 5), (0x81, Sta, Izx, 6), (0x91, Sta, Izy, 6),
+    (0x86, Stx, Zp, 3), (0x96, Stx, Zpy, 4), (0x8E, Stx, Abs, 4),
+    (0x84, Sty, Zp, 3), (0x94, Sty, Zpx, 4), (0x8C, Sty, Abs, 4),
+    (0xAA, Tax, Imp, 2), (0xA8, Tay, Imp, 2), (0xBA, Tsx, Imp, 2),
+    (0x8A, Txa, Imp, 2), (0x9A, Txs, Imp, 2), (0x98, Tya, Imp, 2),
+];
+
+fn lookup(opcode: u8) -> Option<(Op, Mode, u64)> {
+    TABLE.iter().find(|row| row.0 == opcode).map(|&(_, op, mode, cycles)| (op, mode, cycles))
+}
+
+/// Every documented opcode.
+pub fn documented() -> Vec<u8> {
+    TABLE.iter().map(|row| row.0).collect()
+}
+
+pub fn is_documented(opcode: u8) -> bool {
+    lookup(opcode).is_some()
+}
+
+/// Instruction length in bytes of a documented opcode.
+pub fn length(opcode: u8) -> u16 {
+    match lookup(opcode).map(|row| row.1) {
+        Some(Imp | Acc) => 1,
+        Some(Abs | Abx | Aby | Ind) => 3,
+        _ => 2,
+    }
+}
+
+/// Registers and the packed P byte. B and bit 5 are kept clear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub s: u8,
+    pub p: u8,
+}
+
+pub struct Model {
+    pub regs: Registers,
+    pub mem: Box<[u8; 0x10000]>,
+    /// Every write of the last instruction, in order.
+    pub writes: Vec<(u16, u8)>,
+}
+
+impl Model {
+    pub fn new(regs: Registers, mem: Box<[u8; 0x10000]>) -> Self {
+        let mut regs = regs;
+        regs.p &= !(B | U);
+        Model { regs, mem, writes: Vec::new() }
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+
+    fn read_word(&self, lo: u16, hi: u16) -> u16 {
+        u16::from_le_bytes([self.read(lo), self.read(hi)])
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.mem[addr as usize] = value;
+        self.writes.push((addr, value));
+    }
+
+    fn push(&mut self, value: u8) {
+        self.write(0x0100 | self.regs.s as u16, value);
+        self.regs.s = self.regs.s.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.regs.s = self.regs.s.wrapping_add(1);
+        self.read(0x0100 | self.regs.s as u16)
+    }
+
+    fn flag(&self, mask: u8) -> bool {
+        self.regs.p & mask != 0
+    }
+
+    fn set_flag(&mut self, mask: u8, on: bool) {
+        if on { self.regs.p |= mask } else { self.regs.p &= !mask }
+    }
+
+    fn set_nz(&mut self, value: u8) {
+        self.set_flag(Z, value == 0);
+        self.set_flag(N, value & 0x80 != 0);
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(C, register >= value);
+        self.set_nz(register.wrapping_sub(value));
+    }
+
+    /// NMOS decimal mode: Z comes from the binary sum, N and V from the sum
+    /// before the high nibble is adjusted.
+    fn adc(&mut self, value: u8) {
+        let (a, b, c) = (self.regs.a as u16, value as u16, self.flag(C) as u16);
+        let binary = a + b + c;
+        if !self.flag(D) {
+            self.set_flag(V, (!(a ^ b) & (a ^ binary) & 0x80) != 0);
+            self.set_flag(C, binary > 0xFF);
+            self.regs.a = binary as u8;
+            self.set_nz(self.regs.a);
+            return;
+        }
+        let mut lo = (a & 0x0F) + (b & 0x0F) + c;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (a & 0xF0) + (b & 0xF0) + lo;
+        self.set_flag(Z, binary & 0xFF == 0);
+        self.set_flag(N, sum & 0x80 != 0);
+        self.set_flag(V, (!(a ^ b) & (a ^ sum) & 0x80) != 0);
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+        self.set_flag(C, sum >= 0x100);
+        self.regs.a = sum as u8;
+    }
+
+    /// NMOS decimal mode: all flags come from the binary difference.
+    fn sbc(&mut self, value: u8) {
+        let (a, b, borrow) = (self.regs.a as i16, value as i16, 1 - self.flag(C) as i16);
+        let binary = a - b - borrow;
+        self.set_flag(V, ((a ^ b) & (a ^ binary) & 0x80) != 0);
+        self.set_flag(C, binary >= 0);
+        self.set_nz(binary as u8);
+        if !self.flag(D) {
+            self.regs.a = binary as u8;
+            return;
+        }
+        let mut lo = (a & 0x0F) - (b & 0x0F) - borrow;
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+        let mut diff = (a & 0xF0) - (b & 0xF0) + lo;
+        if diff < 0 {
+            diff -= 0x60;
+        }
+        self.regs.a = diff as u8;
+    }
+
+    fn shift(&mut self, op: Op, value: u8) -> u8 {
+        let carry_in = self.flag(C) as u8;
+        let (result, carry) = match op {
+            Asl => (value << 1, value & 0x80 != 0),
+            Lsr => (value >> 1, value & 0x01 != 0),
+            Rol => (value << 1 | carry_in, value & 0x80 != 0),
+            _ => (value >> 1 | carry_in << 7, value & 0x01 != 0),
+        };
+        self.set_flag(C, carry);
+        self.set_nz(result);
+        result
+    }
+
+    /// Executes one instruction and returns its cycle count, or `None` without
+    /// touching anything if the opcode at PC is undocumented.
+    pub fn step(&mut self) -> Option<u64> {
+        let pc = self.regs.pc;
+        let (op, mode, mut cycles) = lookup(self.read(pc))?;
+        self.writes.clear();
+        let byte = self.read(pc.wrapping_add(1));
+        let word = self.read_word(pc.wrapping_add(1), pc.wrapping_add(2));
+        self.regs.pc = pc.wrapping_add(length(self.read(pc)));
+
+        let crossed = |base: u16, addr: u16| base & 0xFF00 != addr & 0xFF00;
+        let (addr, page_crossed) = match mode {
+            Zp => (byte as u16, false),
+            Zpx => (byte.wrapping_add(self.regs.x) as u16, false),
+            Zpy => (byte.wrapping_add(self.regs.y) as u16, false),
+            Abs => (word, false),
+            Abx => (word.wrapping_add(self.regs.x as u16), crossed(word, word.wrapping_add(self.regs.x as u16))),
+            Aby => (word.wrapping_add(self.regs.y as u16), crossed(word, word.wrapping_add(self.regs.y as u16))),
+            Ind => (self.read_word(word, (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF)), false),
+            Izx => {
+                let ptr = byte.wrapping_add(self.regs.x);
+                (self.read_word(ptr as u16, ptr.wrapping_add(1) as u16), false)
+            }
+            Izy => {
+                let base = self.read_word(byte as u16, byte.wrapping_add(1) as u16);
+                let addr = base.wrapping_add(self.regs.y as u16);
+                (addr, crossed(base, addr))
+            }
+            Imm => (pc.wrapping_add(1), false),
+            Rel => (self.regs.pc.wrapping_add(byte as i8 as u16), false),
+            Imp | Acc => (0, false),
+        };
+        if page_crossed && matches!(op, Adc | And | Cmp | Eor | Lda | Ldx | Ldy | Ora | Sbc) {
+            cycles += 1;
+        }
+
+        let branch = match op {
+            Bcc => Some(!self.flag(C)),
+            Bcs => Some(self.flag(C)),
+            Beq => Some(self.flag(Z)),
+            Bne => Some(!self.flag(Z)),
+            Bmi => Some(self.flag(N)),
+            Bpl => Some(!self.flag(N)),
+            Bvs => Some(self.flag(V)),
+            Bvc => Some(!self.flag(V)),
+            _ => None,
+        };
+        if let Some(taken) = branch {
+            if taken {
+                cycles += if crossed(self.regs.pc, addr) { 2 } else { 1 };
+                self.regs.pc = addr;
+            }
+            return Some(cycles);
+        }
+
+        match op {
+            Lda => { self.regs.a = self.read(addr); self.set_nz(self.regs.a); }
+            Ldx => { self.regs.x = self.read(addr); self.set_nz(self.regs.x); }
+            Ldy => { self.regs.y = self.read(addr); self.set_nz(self.regs.y); }
+            Sta => self.write(addr, self.regs.a),
+            Stx => self.write(addr, self.regs.x),
+            Sty => self.write(addr, self.regs.y),
+            Adc => self.adc(self.read(addr)),
+            Sbc => self.sbc(self.read(addr)),
+            And => { self.regs.a &= self.read(addr); self.set_nz(self.regs.a); }
+            Ora => { self.regs.a |= self.read(addr); self.set_nz(self.regs.a); }
+            Eor => { self.regs.a ^= self.read(addr); self.set_nz(self.regs.a); }
+            Cmp => self.compare(self.regs.a, self.read(addr)),
+            Cpx => self.compare(self.regs.x, self.read(addr)),
+            Cpy => self.compare(self.regs.y, self.read(addr)),
+            Bit => {
+                let value = self.read(addr);
+                self.set_flag(Z, self.regs.a & value == 0);
+                self.set_flag(N, value & N != 0);
+                self.set_flag(V, value & V != 0);
+            }
+            Asl | Lsr | Rol | Ror if mode == Acc => self.regs.a = self.shift(op, self.regs.a),
+            Asl | Lsr | Rol | Ror | Inc | Dec => {
+                let value = self.read(addr);
+                // NMOS read-modify-write stores the unmodified value first
+                self.write(addr, value);
+                let result = match op {
+                    Inc => { let r = value.wrapping_add(1); self.set_nz(r); r }
+                    Dec => { let r = value.wrapping_sub(1); self.set_nz(r); r }
+                    _ => self.shift(op, value),
+                };
+                self.write(addr, result);
+            }
+            Inx => { self.regs.x = self.regs.x.wrapping_add(1); self.set_nz(self.regs.x); }
+            Iny => { self.regs.y = self.regs.y.wrapping_add(1); self.set_nz(self.regs.y); }
+            Dex => { self.regs.x = self.regs.x.wrapping_sub(1); self.set_nz(self.regs.x); }
+            Dey => { self.regs.y = self.regs.y.wrapping_sub(1); self.set_nz(self.regs.y); }
+            Tax => { self.regs.x = self.regs.a; self.set_nz(self.regs.x); }
+            Tay => { self.regs.y = self.regs.a; self.set_nz(self.regs.y); }
+            Txa => { self.regs.a = self.regs.x; self.set_nz(self.regs.a); }
+            Tya => { self.regs.a = self.regs.y; self.set_nz(self.regs.a); }
+            Tsx => { self.regs.x = self.regs.s; self.set_nz(self.regs.x); }
+            Txs => self.regs.s = self.regs.x,
+            Clc => self.set_flag(C, false),
+            Sec => self.set_flag(C, true),
+            Cli => self.set_flag(I, false),
+            Sei => self.set_flag(I, true),
+            Cld => self.set_flag(D, false),
+            Sed => self.set_flag(D, true),
+            Clv => self.set_flag(V, false),
+            Pha => self.push(self.regs.a),
+            Php => self.push(self.regs.p | B | U),
+            Pla => { self.regs.a = self.pull(); self.set_nz(self.regs.a); }
+            Plp => self.regs.p = self.pull() & !(B | U),
+            Jmp => self.regs.pc = addr,
+            Jsr => {
+                let ret = pc.wrapping_add(2);
+                self.push((ret >> 8) as u8);
+                self.push(ret as u8);
+                self.regs.pc = addr;
+            }
+            Rts => {
+                let lo = self.pull();
+                let hi = self.pull();
+                self.regs.pc = u16::from_le_bytes([lo, hi]).wrapping_add(1);
+            }
+            Brk => {
+                let ret = pc.wrapping_add(2);
+                self.push((ret >> 8) as u8);
+                self.push(ret as u8);
+                self.push(self.regs.p | B | U);
+                self.set_flag(I, true);
+                self.regs.pc = self.read_word(0xFFFE, 0xFFFF);
+            }
+            Rti => {
+                self.regs.p = self.pull() & !(B | U);
+                let lo = self.pull();
+                let hi = self.pull();
+                self.regs.pc = u16::from_le_bytes([lo, hi]);
+            }
+            Nop => {}
+            _ => unreachable!("branches are handled above"),
+        }
+        Some(cycles)
+    }
+}
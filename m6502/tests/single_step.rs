@@ -0,0 +1,190 @@
+//! Per-opcode conformance tests in the SingleStepTests/ProcessorTests JSON format:
+//! one file per opcode, each case giving the initial and final CPU state, the RAM it
+//! touches and every bus access cycle by cycle.
+//!
+//! The vendored subset in test-fixtures/single-step runs by default. Set
+//! `M6502_SINGLE_STEP_TESTS` to a local copy of the full `6502/v1` directory to run
+//! all 256 opcodes against it.
+
+use m6502::{BusAccess, Byte, Cpu, Memory, StatusFlags, Word};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FULL_DATASET_VAR: &str = "M6502_SINGLE_STEP_TESTS";
+
+/// Failures printed before giving up on a file.
+const MAX_REPORTED: usize = 10;
+
+#[derive(Deserialize)]
+struct TestCase {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    cycles: Vec<(Word, Byte, String)>,
+}
+
+#[derive(Deserialize)]
+struct State {
+    pc: Word,
+    s: Byte,
+    a: Byte,
+    x: Byte,
+    y: Byte,
+    p: Byte,
+    ram: Vec<(Word, Byte)>,
+}
+
+impl State {
+    fn load(&self, cpu: &mut Cpu, memory: &mut Memory) {
+        cpu.pc = self.pc;
+        cpu.sp = self.s;
+        cpu.reg_a = self.a;
+        cpu.reg_x = self.x;
+        cpu.reg_y = self.y;
+        cpu.status = StatusFlags::from_byte(self.p);
+        for &(addr, value) in &self.ram {
+            memory.data[addr as usize] = value;
+        }
+    }
+
+    /// Compares against the CPU and memory, ignoring B and bit 5 of P which only
+    /// exist on the stack.
+    fn check(&self, cpu: &Cpu, memory: &Memory) -> Result<(), String> {
+        let registers = |pc: Word, s: Byte, a: Byte, x: Byte, y: Byte, p: Byte| {
+            format!("pc={:04X} s={:02X} a={:02X} x={:02X} y={:02X} p={:02X}", pc, s, a, x, y, p | 0x30)
+        };
+        let expected = registers(self.pc, self.s, self.a, self.x, self.y, self.p);
+        let actual = registers(cpu.pc, cpu.sp, cpu.reg_a, cpu.reg_x, cpu.reg_y, cpu.status.to_byte(true));
+        if expected != actual {
+            return Err(format!("expected {}, got {}", expected, actual));
+        }
+        for &(addr, value) in &self.ram {
+            if memory.data[addr as usize] != value {
+                return Err(format!(
+                    "expected {:02X} at {:04X}, got {:02X}",
+                    value, addr, memory.data[addr as usize]
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TestCase {
+    fn bus_accesses(&self) -> Vec<BusAccess> {
+        self.cycles
+            .iter()
+            .map(|(addr, value, kind)| match kind.as_str() {
+                "read" => BusAccess::Read { addr: *addr, value: *value },
+                "write" => BusAccess::Write { addr: *addr, value: *value },
+                _ => panic!("{}: unknown cycle type {:?}", self.name, kind),
+            })
+            .collect()
+    }
+
+    fn clear(&self, memory: &mut Memory) {
+        for &(addr, _) in self.initial.ram.iter().chain(&self.expected.ram) {
+            memory.data[addr as usize] = 0;
+        }
+    }
+
+    /// Runs the case through `execute()`: final state and cycle count.
+    fn run_execute(&self, memory: &mut Memory) -> Result<(), String> {
+        let mut cpu = Cpu::new();
+        cpu.illegal_opcodes = true;
+        self.initial.load(&mut cpu, memory);
+        let cycles = cpu.execute(1, memory);
+        let result = self.expected.check(&cpu, memory).and_then(|_| {
+            if cycles as usize == self.cycles.len() {
+                Ok(())
+            } else {
+                Err(format!("expected {} cycles, took {}", self.cycles.len(), cycles))
+            }
+        });
+        self.clear(memory);
+        result
+    }
+
+    /// Runs the case through `tick()`: final state and every bus access.
+    fn run_tick(&self, memory: &mut Memory) -> Result<(), String> {
+        let mut cpu = Cpu::new();
+        self.initial.load(&mut cpu, memory);
+        let mut accesses = vec![cpu.tick(memory)];
+        while !cpu.at_instruction_boundary() && accesses.len() <= self.cycles.len() {
+            accesses.push(cpu.tick(memory));
+        }
+        let expected = self.bus_accesses();
+        let result = self.expected.check(&cpu, memory).and_then(|_| {
+            if accesses == expected {
+                Ok(())
+            } else {
+                Err(format!("expected bus accesses {:?}, got {:?}", expected, accesses))
+            }
+        });
+        self.clear(memory);
+        result
+    }
+}
+
+/// Runs every case in `file` on both engines and returns the failures.
+fn run_file(file: &Path, memory: &mut Memory) -> Vec<String> {
+    let text = fs::read_to_string(file).unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+    let cases: Vec<TestCase> =
+        serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+    let mut failures = Vec::new();
+    for case in &cases {
+        for (engine, result) in [("execute", case.run_execute(memory)), ("tick", case.run_tick(memory))] {
+            if let Err(message) = result {
+                failures.push(format!("{} [{}]: {}", case.name, engine, message));
+            }
+        }
+        if failures.len() >= MAX_REPORTED {
+            break;
+        }
+    }
+    failures
+}
+
+/// Runs each `xx.json` in `dir` for the given opcodes, panicking with a summary
+/// of every failing opcode.
+fn run_dir(dir: &Path, opcodes: impl Iterator<Item = Byte>) {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    let mut failed = Vec::new();
+    let mut files = 0;
+    for opcode in opcodes {
+        let file = dir.join(format!("{:02x}.json", opcode));
+        if !file.exists() {
+            continue;
+        }
+        files += 1;
+        let failures = run_file(&file, &mut memory);
+        if !failures.is_empty() {
+            eprintln!("{:02x}:\n  {}", opcode, failures.join("\n  "));
+            failed.push(format!("{:02x}", opcode));
+        }
+    }
+    assert!(files > 0, "no test files found in {}", dir.display());
+    assert!(failed.is_empty(), "{} of {} opcodes failed: {}", failed.len(), files, failed.join(" "));
+}
+
+/// JAM opcodes lock up the bus; the dataset's traces for them are not meaningful.
+fn is_jam(opcode: Byte) -> bool {
+    opcode & 0x0F == 0x02 && !matches!(opcode, 0x82 | 0xA2 | 0xC2 | 0xE2)
+}
+
+#[test]
+fn vendored_subset() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-fixtures/single-step");
+    run_dir(&dir, 0..=0xFF);
+}
+
+#[test]
+fn full_dataset() {
+    let Ok(dir) = std::env::var(FULL_DATASET_VAR) else {
+        eprintln!("{} not set, skipping the full single-step dataset", FULL_DATASET_VAR);
+        return;
+    };
+    run_dir(Path::new(&dir), (0..=0xFF).filter(|&op| !is_jam(op)));
+}
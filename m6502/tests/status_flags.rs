@@ -0,0 +1,84 @@
+use m6502::instructions::*;
+use m6502::{Cpu, Memory, StatusFlags};
+
+#[test]
+fn from_byte_then_to_byte_round_trips_every_value() {
+    for value in 0..=255u8 {
+        let flags = StatusFlags::from_byte(value);
+        let break_bit = (value & BREAK_FLAG_BIT) != 0;
+        assert_eq!(flags.to_byte(break_bit), value | UNUSED_FLAG_BIT, "value {:08b}", value);
+    }
+}
+
+#[test]
+fn to_byte_then_from_byte_round_trips_every_flag_combination() {
+    for bits in 0..64u8 {
+        let flags = StatusFlags {
+            carry: bits & 0x01 != 0,
+            zero: bits & 0x02 != 0,
+            interrupt_disable: bits & 0x04 != 0,
+            decimal_mode: bits & 0x08 != 0,
+            overflow: bits & 0x10 != 0,
+            negative: bits & 0x20 != 0,
+            ..StatusFlags::default()
+        };
+        for break_bit in [false, true] {
+            assert_eq!(StatusFlags::from_byte(flags.to_byte(break_bit)), flags);
+        }
+    }
+}
+
+#[test]
+fn to_byte_uses_hardware_bit_positions() {
+    let single = |f: fn(&mut StatusFlags)| {
+        let mut flags = StatusFlags::default();
+        f(&mut flags);
+        flags.to_byte(false)
+    };
+    assert_eq!(single(|f| f.carry = true), 0x21);
+    assert_eq!(single(|f| f.zero = true), 0x22);
+    assert_eq!(single(|f| f.interrupt_disable = true), 0x24);
+    assert_eq!(single(|f| f.decimal_mode = true), 0x28);
+    assert_eq!(single(|f| f.overflow = true), 0x60);
+    assert_eq!(single(|f| f.negative = true), 0xA0);
+    assert_eq!(StatusFlags::default().to_byte(true), 0x30);
+}
+
+#[test]
+fn display_formats_as_nv_bdizc() {
+    assert_eq!(StatusFlags::default().to_string(), "nv-bdizc");
+    assert_eq!(StatusFlags::from_byte(0xFF).to_string(), "NV-bDIZC");
+    let flags = StatusFlags {
+        negative: true,
+        break_command: true,
+        carry: true,
+        ..StatusFlags::default()
+    };
+    assert_eq!(flags.to_string(), "Nv-BdizC");
+}
+
+#[test]
+fn php_plp_round_trip_through_the_stack() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    memory.data[0xFFFC] = 0x00;
+    memory.data[0xFFFD] = 0x80;
+    memory.data[0x8000] = INS_SEC;
+    memory.data[0x8001] = INS_SED;
+    memory.data[0x8002] = INS_PHP;
+    memory.data[0x8003] = INS_CLC;
+    memory.data[0x8004] = INS_CLD;
+    memory.data[0x8005] = INS_PLP;
+
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut memory);
+    let sp = cpu.sp;
+    assert_eq!(cpu.execute(2 + 2 + 3, &mut memory), 7);
+    assert_eq!(memory.data[0x100 | sp as usize], 0x39);
+    assert_eq!(cpu.sp, sp.wrapping_sub(1));
+
+    assert_eq!(cpu.execute(2 + 2 + 4, &mut memory), 8);
+    assert!(cpu.status.carry);
+    assert!(cpu.status.decimal_mode);
+    assert!(!cpu.status.break_command);
+    assert_eq!(cpu.sp, sp);
+}
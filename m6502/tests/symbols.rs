@@ -0,0 +1,118 @@
+//! The symbol table, symbol files and symbolic disassembly.
+
+use m6502::{Cpu, LoadError, Memory, Symbols, disassemble_with_symbols};
+
+#[test]
+fn looks_up_both_ways() {
+    let mut symbols = Symbols::new();
+    symbols.insert("reset", 0x8000);
+    symbols.insert("main_loop", 0x8010);
+    assert_eq!(symbols.addr("main_loop"), Some(0x8010));
+    assert_eq!(symbols.name(0x8000), Some("reset"));
+    assert_eq!(symbols.addr("nmi"), None);
+    assert_eq!(symbols.name(0x8001), None);
+    assert_eq!(symbols.len(), 2);
+}
+
+#[test]
+fn first_name_at_an_address_wins() {
+    let mut symbols = Symbols::new();
+    symbols.insert("start", 0x0600);
+    symbols.insert("_start", 0x0600);
+    assert_eq!(symbols.name(0x0600), Some("start"));
+    assert_eq!(symbols.addr("_start"), Some(0x0600));
+}
+
+#[test]
+fn moving_a_name_updates_both_directions() {
+    let mut symbols = Symbols::new();
+    symbols.insert("loop", 0x0600);
+    symbols.insert("again", 0x0600);
+    symbols.insert("loop", 0x0610);
+    assert_eq!(symbols.addr("loop"), Some(0x0610));
+    assert_eq!(symbols.name(0x0610), Some("loop"));
+    assert_eq!(symbols.name(0x0600), Some("again"));
+    symbols.insert("again", 0x0620);
+    assert_eq!(symbols.name(0x0600), None);
+    assert_eq!(symbols.len(), 2);
+}
+
+#[test]
+fn reads_vice_label_files() {
+    let mut symbols = Symbols::new();
+    symbols.read("al C:0810 .start\nal C:0830 .main_loop\n\nal 00FB .ptr\n").unwrap();
+    assert_eq!(symbols.addr("start"), Some(0x0810));
+    assert_eq!(symbols.addr("main_loop"), Some(0x0830));
+    assert_eq!(symbols.name(0x00FB), Some("ptr"));
+}
+
+#[test]
+fn reads_ld65_debug_files() {
+    let text = "\
+version\tmajor=2,minor=0
+file\tid=0,name=\"main.s\",size=120,mtime=0x5F000000,mod=0
+sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0x8000,seg=0,type=lab
+sym\tid=1,name=\"SCREEN\",addrsize=absolute,scope=0,def=2,val=0x400,type=equ
+sym\tid=2,name=\"loop\",addrsize=absolute,size=3,scope=0,def=3,val=0x8005,seg=0,type=lab
+";
+    let mut symbols = Symbols::new();
+    symbols.read(text).unwrap();
+    let names: Vec<_> = symbols.iter().collect();
+    assert_eq!(names, [("loop", 0x8005), ("reset", 0x8000)]);
+}
+
+#[test]
+fn reads_label_lists() {
+    let text = "; zero page\nptr = $FB\ntable=0x0300 # data\nlimit = 4096\n";
+    let mut symbols = Symbols::new();
+    symbols.read(text).unwrap();
+    let names: Vec<_> = symbols.iter().collect();
+    assert_eq!(names, [("limit", 4096), ("ptr", 0x00FB), ("table", 0x0300)]);
+}
+
+#[test]
+fn rejects_malformed_files_without_adding_anything() {
+    let mut symbols = Symbols::new();
+    symbols.insert("keep", 0x1000);
+    let error = symbols.read("first = $0600\nsecond $0610\n").unwrap_err();
+    assert_eq!(error, LoadError::Malformed { line: 2, reason: "expected 'name = $addr'" });
+    let error = symbols.read("al C:0810 .start\nal C:XYZ .bad\n").unwrap_err();
+    assert_eq!(error.to_string(), "line 2: invalid label address");
+    let names: Vec<_> = symbols.iter().collect();
+    assert_eq!(names, [("keep", 0x1000)]);
+}
+
+#[test]
+fn resolves_addresses_and_names() {
+    let mut symbols = Symbols::new();
+    symbols.insert("main_loop", 0x8010);
+    assert_eq!(symbols.resolve("main_loop"), Some(0x8010));
+    assert_eq!(symbols.resolve("$C000"), Some(0xC000));
+    assert_eq!(symbols.resolve("0x0600"), Some(0x0600));
+    assert_eq!(symbols.resolve("512"), Some(0x0200));
+    assert_eq!(symbols.resolve("missing"), None);
+}
+
+#[test]
+fn names_operands_in_disassembly_and_traces() {
+    let mut memory = Memory { data: [0; 1024 * 64] };
+    // LDA $0300,X / BNE $0600 / LDA #$10
+    memory.data[0x0600..0x0607].copy_from_slice(&[0xBD, 0x00, 0x03, 0xD0, 0xFB, 0xA9, 0x10]);
+    let mut symbols = Symbols::new();
+    symbols.insert("loop", 0x0600);
+    symbols.insert("table", 0x0300);
+    symbols.insert("sixteen", 0x0010);
+    assert_eq!(disassemble_with_symbols(&memory, 0x0600, &symbols), ("LDA table,X".to_string(), 3));
+    assert_eq!(disassemble_with_symbols(&memory, 0x0603, &symbols), ("BNE loop".to_string(), 2));
+    // Immediate values are not addresses
+    assert_eq!(disassemble_with_symbols(&memory, 0x0605, &symbols), ("LDA #$10".to_string(), 2));
+
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0603;
+    let line = cpu.trace_line_with_symbols(&memory, 7, &symbols);
+    assert!(line.starts_with("0603  D0 FB     BNE loop"), "{}", line);
+    // The register columns stay where they were
+    let plain = cpu.trace_line(&memory, 7);
+    assert_eq!(line[line.find("A:").unwrap()..], plain[plain.find("A:").unwrap()..]);
+    assert_eq!(line.find("A:"), plain.find("A:"));
+}
@@ -0,0 +1,99 @@
+//! Atari DOS executables, with init routines run on the CPU.
+
+use m6502::{Cpu, LoadError, Memory, load_xex};
+
+/// A segment header and its bytes, without the optional $FFFF.
+fn segment(start: u16, data: &[u8]) -> Vec<u8> {
+    let mut out = start.to_le_bytes().to_vec();
+    out.extend_from_slice(&(start + data.len() as u16 - 1).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+fn xex(segments: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![0xFF, 0xFF];
+    for segment in segments {
+        out.extend_from_slice(segment);
+    }
+    out
+}
+
+fn memory() -> Memory {
+    Memory { data: [0xEE; 1024 * 64] }
+}
+
+const INC_COUNTER: [u8; 4] = [0xEE, 0x00, 0x06, 0x60]; // INC $0600 / RTS
+
+#[test]
+fn loads_segments_and_starts_at_the_first() {
+    let mut bytes = xex(&[segment(0x2000, &[0xA9, 0x01]), segment(0x3000, &[0x60])]);
+    // A repeated $FFFF header before a segment is allowed
+    bytes.extend_from_slice(&[0xFF, 0xFF]);
+    bytes.extend_from_slice(&segment(0x4000, &[1, 2, 3]));
+    let mut memory = memory();
+    assert_eq!(load_xex(&bytes, &mut Cpu::new(), &mut memory, 1000), Ok(0x2000));
+    assert_eq!(memory.data[0x2000..0x2003], [0xA9, 0x01, 0xEE]);
+    assert_eq!(memory.data[0x3000], 0x60);
+    assert_eq!(memory.data[0x4000..0x4003], [1, 2, 3]);
+}
+
+#[test]
+fn runs_init_routines_between_segments() {
+    let bytes = xex(&[
+        segment(0x0600, &[0]),
+        segment(0x2000, &INC_COUNTER),
+        segment(0x02E2, &[0x00, 0x20]),
+        // The counter is read back after the first init call
+        segment(0x0601, &[0x55]),
+        segment(0x02E2, &[0x00, 0x20]),
+        segment(0x02E0, &[0x00, 0x30]),
+    ]);
+    let mut memory = memory();
+    let mut cpu = Cpu::new();
+    assert_eq!(load_xex(&bytes, &mut cpu, &mut memory, 1000), Ok(0x3000));
+    assert_eq!(memory.data[0x0600..0x0602], [2, 0x55]);
+    assert_eq!(cpu.sp, 0xFD);
+}
+
+#[test]
+fn init_routines_see_earlier_segments() {
+    // LDA $0700 / STA $0701 / RTS, with INITAD and RUNAD in one segment
+    let bytes = xex(&[
+        segment(0x0700, &[0x42]),
+        segment(0x2000, &[0xAD, 0x00, 0x07, 0x8D, 0x01, 0x07, 0x60]),
+        segment(0x02E0, &[0x00, 0x21, 0x00, 0x20]),
+    ]);
+    let mut memory = memory();
+    assert_eq!(load_xex(&bytes, &mut Cpu::new(), &mut memory, 1000), Ok(0x2100));
+    assert_eq!(memory.data[0x0701], 0x42);
+}
+
+#[test]
+fn rejects_truncated_segments_without_loading() {
+    let bytes = xex(&[segment(0x2000, &[1, 2, 3, 4]), segment(0x3000, &[5, 6, 7, 8])]);
+    let mut memory = memory();
+    for len in [1, 3, 5, 9, 13, bytes.len() - 1] {
+        let result = load_xex(&bytes[..len], &mut Cpu::new(), &mut memory, 1000);
+        assert_eq!(result, Err(LoadError::Truncated { offset: len }), "{} bytes", len);
+    }
+    assert_eq!(load_xex(&[0xFF, 0xFF], &mut Cpu::new(), &mut memory, 1000), Err(LoadError::Truncated { offset: 2 }));
+    assert!(memory.data.iter().all(|&b| b == 0xEE));
+
+    let error = load_xex(&[0xFE, 0xFF, 0, 0x20, 0, 0x20, 0], &mut Cpu::new(), &mut memory, 1000).unwrap_err();
+    assert_eq!(error.to_string(), "not an Atari executable");
+    let error = load_xex(&[0xFF, 0xFF, 0x01, 0x20, 0x00, 0x20, 0], &mut Cpu::new(), &mut memory, 1000).unwrap_err();
+    assert_eq!(error.to_string(), "segment ends at $2000, before its start $2001");
+}
+
+#[test]
+fn reports_init_routines_that_do_not_return() {
+    let hang = xex(&[segment(0x2000, &[0x4C, 0x00, 0x20]), segment(0x02E2, &[0x00, 0x20])]);
+    let error = load_xex(&hang, &mut Cpu::new(), &mut memory(), 1000).unwrap_err();
+    assert_eq!(error.to_string(), "init routine at $2000 did not return within 1000 cycles");
+
+    let jam = xex(&[segment(0x2000, &[0xEA, 0x02]), segment(0x02E2, &[0x00, 0x20])]);
+    let mut cpu = Cpu::new();
+    cpu.illegal_opcodes = true;
+    let error = load_xex(&jam, &mut cpu, &mut memory(), 1000).unwrap_err();
+    assert_eq!(error.to_string(), "init routine at $2000 halted the CPU at $2001");
+}
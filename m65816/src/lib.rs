@@ -0,0 +1,1042 @@
+pub mod opcodes;
+mod trace;
+pub use crate::trace::{disassemble, write_disassembly};
+use crate::opcodes::*;
+
+use m6502::instructions::{
+    CARRY_FLAG_BIT, DECIMAL_FLAG_BIT, INTERRUPT_DISABLE_FLAG_BIT, NEGATIVE_FLAG_BIT,
+    OVERFLOW_FLAG_BIT, ZERO_FLAG_BIT,
+};
+use m6502::{Bus, Byte, Word};
+
+/// 24-bit address: bank in bits 16-23.
+pub type Long = u32;
+
+// Native mode bits that replace B and the unused bit of the 6502
+pub const INDEX_8BIT_FLAG_BIT: Byte = 0b00010000;
+pub const ACCUMULATOR_8BIT_FLAG_BIT: Byte = 0b00100000;
+
+/// The 65C816 address bus. Every m6502 `Bus` is also a `LongBus` that ignores the
+/// bank, so 6502 memory maps can be reused as long as code stays in bank 0.
+pub trait LongBus {
+    fn read(&mut self, addr: Long) -> Byte;
+    fn write(&mut self, addr: Long, value: Byte);
+}
+
+impl<B: Bus> LongBus for B {
+    fn read(&mut self, addr: Long) -> Byte {
+        Bus::read(self, addr as Word)
+    }
+
+    fn write(&mut self, addr: Long, value: Byte) {
+        Bus::write(self, addr as Word, value)
+    }
+}
+
+/// Flat 16 MiB memory covering the whole 24-bit address space.
+pub struct LongMemory {
+    pub data: Vec<Byte>,
+}
+
+impl LongMemory {
+    pub fn new() -> Self {
+        LongMemory { data: vec![0; 1 << 24] }
+    }
+}
+
+impl Default for LongMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LongBus for LongMemory {
+    fn read(&mut self, addr: Long) -> Byte {
+        self.data[(addr & 0xFF_FFFF) as usize]
+    }
+
+    fn write(&mut self, addr: Long, value: Byte) {
+        self.data[(addr & 0xFF_FFFF) as usize] = value;
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatusFlags {
+    pub carry: bool,
+    pub zero: bool,
+    pub interrupt_disable: bool,
+    pub decimal_mode: bool,
+    /// X: index registers are 8 bits wide
+    pub index_8bit: bool,
+    /// M: accumulator and memory accesses are 8 bits wide
+    pub accumulator_8bit: bool,
+    pub overflow: bool,
+    pub negative: bool,
+}
+
+impl StatusFlags {
+    /// Packs the flags into the P register. In emulation mode bit 5 is always set
+    /// and bit 4 is the B flag of the pushed copy, as on the 6502.
+    pub fn to_byte(&self, emulation: bool, break_bit: bool) -> Byte {
+        let mut status = 0;
+        if self.carry { status |= CARRY_FLAG_BIT; }
+        if self.zero { status |= ZERO_FLAG_BIT; }
+        if self.interrupt_disable { status |= INTERRUPT_DISABLE_FLAG_BIT; }
+        if self.decimal_mode { status |= DECIMAL_FLAG_BIT; }
+        if self.overflow { status |= OVERFLOW_FLAG_BIT; }
+        if self.negative { status |= NEGATIVE_FLAG_BIT; }
+        if emulation {
+            status |= ACCUMULATOR_8BIT_FLAG_BIT;
+            if break_bit { status |= INDEX_8BIT_FLAG_BIT; }
+        } else {
+            if self.accumulator_8bit { status |= ACCUMULATOR_8BIT_FLAG_BIT; }
+            if self.index_8bit { status |= INDEX_8BIT_FLAG_BIT; }
+        }
+        status
+    }
+
+    pub fn from_byte(status: Byte) -> Self {
+        StatusFlags {
+            carry: (status & CARRY_FLAG_BIT) != 0,
+            zero: (status & ZERO_FLAG_BIT) != 0,
+            interrupt_disable: (status & INTERRUPT_DISABLE_FLAG_BIT) != 0,
+            decimal_mode: (status & DECIMAL_FLAG_BIT) != 0,
+            index_8bit: (status & INDEX_8BIT_FLAG_BIT) != 0,
+            accumulator_8bit: (status & ACCUMULATOR_8BIT_FLAG_BIT) != 0,
+            overflow: (status & OVERFLOW_FLAG_BIT) != 0,
+            negative: (status & NEGATIVE_FLAG_BIT) != 0,
+        }
+    }
+}
+
+/// WDC 65C816. Starts in 6502 emulation mode after reset; XCE switches to native
+/// mode with 16-bit registers, 24-bit addressing and a relocatable direct page.
+pub struct Cpu {
+    /// C: the 16-bit accumulator, A in the low byte and B in the high byte
+    pub reg_a: Word,
+    pub reg_x: Word,
+    pub reg_y: Word,
+    pub sp: Word,
+    /// D: direct page base
+    pub dp: Word,
+    pub pc: Word,
+    /// PBR (K): bank of the program counter
+    pub pbr: Byte,
+    /// DBR (B): bank used by absolute data addressing
+    pub dbr: Byte,
+    pub status: StatusFlags,
+    /// E: 6502 emulation mode
+    pub emulation: bool,
+    /// Set by WAI until irq() or nmi() is signalled.
+    pub waiting: bool,
+    /// Set by STP until reset.
+    pub stopped: bool,
+    /// Total cycles executed since reset.
+    pub cycles: u64,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cpu {
+    pub fn new() -> Self {
+        Cpu {
+            reg_a: 0,
+            reg_x: 0,
+            reg_y: 0,
+            sp: 0x01FD,
+            dp: 0,
+            pc: 0xFFFC,
+            pbr: 0,
+            dbr: 0,
+            status: StatusFlags { accumulator_8bit: true, index_8bit: true, ..StatusFlags::default() },
+            emulation: true,
+            waiting: false,
+            stopped: false,
+            cycles: 0,
+        }
+    }
+
+    pub fn reset(&mut self, bus: &mut impl LongBus) {
+        self.emulation = true;
+        self.status = StatusFlags {
+            accumulator_8bit: true,
+            index_8bit: true,
+            interrupt_disable: true,
+            ..self.status
+        };
+        self.status.decimal_mode = false;
+        self.reg_x &= 0xFF;
+        self.reg_y &= 0xFF;
+        self.sp = 0x0100 | (self.sp & 0xFF);
+        self.dp = 0;
+        self.pbr = 0;
+        self.dbr = 0;
+        self.waiting = false;
+        self.stopped = false;
+        self.cycles = 0;
+        self.pc = self.read_word(bus, 0xFFFC, true);
+    }
+
+    /// Signals a maskable interrupt; see m6502::Cpu::irq for the WAI behaviour.
+    /// Returns the number of cycles consumed.
+    pub fn irq(&mut self, bus: &mut impl LongBus) -> i32 {
+        if self.stopped {
+            return 0;
+        }
+        self.waiting = false;
+        if self.status.interrupt_disable {
+            return 0;
+        }
+        let start = self.cycles;
+        self.io();
+        self.io();
+        let vector = if self.emulation { 0xFFFE } else { 0xFFEE };
+        self.interrupt(bus, vector, false);
+        (self.cycles - start) as i32
+    }
+
+    /// Signals a non-maskable interrupt. Returns the number of cycles consumed.
+    pub fn nmi(&mut self, bus: &mut impl LongBus) -> i32 {
+        if self.stopped {
+            return 0;
+        }
+        self.waiting = false;
+        let start = self.cycles;
+        self.io();
+        self.io();
+        let vector = if self.emulation { 0xFFFA } else { 0xFFEA };
+        self.interrupt(bus, vector, false);
+        (self.cycles - start) as i32
+    }
+
+    /// Runs whole instructions until at least `cycles` cycles have been used.
+    /// Returns the number of cycles consumed.
+    pub fn execute(&mut self, cycles: i32, bus: &mut impl LongBus) -> i32 {
+        let start = self.cycles;
+        while ((self.cycles - start) as i32) < cycles && !self.waiting && !self.stopped {
+            self.step(bus);
+        }
+        (self.cycles - start) as i32
+    }
+
+    /// Executes a single instruction and returns the cycles it took.
+    pub fn step(&mut self, bus: &mut impl LongBus) -> i32 {
+        let start = self.cycles;
+        let opcode = self.fetch_byte(bus);
+        let (op, mode) = OPCODES[opcode as usize];
+        self.dispatch(op, mode, bus);
+        (self.cycles - start) as i32
+    }
+
+    /// Full 24-bit program counter.
+    pub fn program_address(&self) -> Long {
+        ((self.pbr as Long) << 16) | self.pc as Long
+    }
+
+    fn m8(&self) -> bool {
+        self.emulation || self.status.accumulator_8bit
+    }
+
+    fn x8(&self) -> bool {
+        self.emulation || self.status.index_8bit
+    }
+}
+
+// Bus access; every access is one cycle
+impl Cpu {
+    fn io(&mut self) {
+        self.cycles += 1;
+    }
+
+    fn read(&mut self, bus: &mut impl LongBus, addr: Long) -> Byte {
+        self.cycles += 1;
+        bus.read(addr & 0xFF_FFFF)
+    }
+
+    fn write(&mut self, bus: &mut impl LongBus, addr: Long, value: Byte) {
+        self.cycles += 1;
+        bus.write(addr & 0xFF_FFFF, value);
+    }
+
+    /// Address of the next byte: within bank 0 for direct page and stack accesses,
+    /// carrying into the next bank otherwise.
+    fn next_address(addr: Long, bank0: bool) -> Long {
+        if bank0 {
+            (addr + 1) & 0xFFFF
+        } else {
+            (addr + 1) & 0xFF_FFFF
+        }
+    }
+
+    fn read_word(&mut self, bus: &mut impl LongBus, addr: Long, bank0: bool) -> Word {
+        let lo = self.read(bus, addr) as Word;
+        let hi = self.read(bus, Self::next_address(addr, bank0)) as Word;
+        (hi << 8) | lo
+    }
+
+    fn read_long(&mut self, bus: &mut impl LongBus, addr: Long, bank0: bool) -> Long {
+        let lo = self.read_word(bus, addr, bank0) as Long;
+        let bank = self.read(bus, Self::next_address(Self::next_address(addr, bank0), bank0)) as Long;
+        (bank << 16) | lo
+    }
+
+    /// Reads one or two bytes depending on `byte`.
+    fn read_data(&mut self, bus: &mut impl LongBus, addr: Long, bank0: bool, byte: bool) -> Word {
+        if byte {
+            self.read(bus, addr) as Word
+        } else {
+            self.read_word(bus, addr, bank0)
+        }
+    }
+
+    fn write_data(&mut self, bus: &mut impl LongBus, addr: Long, bank0: bool, byte: bool, value: Word) {
+        self.write(bus, addr, value as Byte);
+        if !byte {
+            self.write(bus, Self::next_address(addr, bank0), (value >> 8) as Byte);
+        }
+    }
+
+    fn fetch_byte(&mut self, bus: &mut impl LongBus) -> Byte {
+        let value = self.read(bus, self.program_address());
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    fn fetch_word(&mut self, bus: &mut impl LongBus) -> Word {
+        let lo = self.fetch_byte(bus) as Word;
+        let hi = self.fetch_byte(bus) as Word;
+        (hi << 8) | lo
+    }
+
+    fn fetch_long(&mut self, bus: &mut impl LongBus) -> Long {
+        let lo = self.fetch_word(bus) as Long;
+        let bank = self.fetch_byte(bus) as Long;
+        (bank << 16) | lo
+    }
+
+    // Stack: confined to page 1 in emulation mode
+    fn push_byte(&mut self, bus: &mut impl LongBus, value: Byte) {
+        self.write(bus, self.sp as Long, value);
+        self.sp = if self.emulation {
+            0x0100 | (self.sp.wrapping_sub(1) & 0xFF)
+        } else {
+            self.sp.wrapping_sub(1)
+        };
+    }
+
+    fn push_word(&mut self, bus: &mut impl LongBus, value: Word) {
+        self.push_byte(bus, (value >> 8) as Byte);
+        self.push_byte(bus, value as Byte);
+    }
+
+    fn pull_byte(&mut self, bus: &mut impl LongBus) -> Byte {
+        self.sp = if self.emulation {
+            0x0100 | (self.sp.wrapping_add(1) & 0xFF)
+        } else {
+            self.sp.wrapping_add(1)
+        };
+        self.read(bus, self.sp as Long)
+    }
+
+    fn pull_word(&mut self, bus: &mut impl LongBus) -> Word {
+        let lo = self.pull_byte(bus) as Word;
+        let hi = self.pull_byte(bus) as Word;
+        (hi << 8) | lo
+    }
+
+    fn push_data(&mut self, bus: &mut impl LongBus, value: Word, byte: bool) {
+        if byte {
+            self.push_byte(bus, value as Byte);
+        } else {
+            self.push_word(bus, value);
+        }
+    }
+
+    fn pull_data(&mut self, bus: &mut impl LongBus, byte: bool) -> Word {
+        if byte {
+            self.pull_byte(bus) as Word
+        } else {
+            self.pull_word(bus)
+        }
+    }
+
+    fn interrupt(&mut self, bus: &mut impl LongBus, vector: Word, break_bit: bool) {
+        if !self.emulation {
+            self.push_byte(bus, self.pbr);
+        }
+        self.push_word(bus, self.pc);
+        let status = self.status.to_byte(self.emulation, break_bit);
+        self.push_byte(bus, status);
+        self.status.interrupt_disable = true;
+        self.status.decimal_mode = false;
+        self.pbr = 0;
+        self.pc = self.read_word(bus, vector as Long, true);
+    }
+}
+
+// Addressing modes
+impl Cpu {
+    fn direct_penalty(&mut self) {
+        if self.dp & 0xFF != 0 {
+            self.io();
+        }
+    }
+
+    /// Direct page address of `offset + index`. In emulation mode with DL = 0 the
+    /// index wraps within the direct page, like the 6502 zero page.
+    fn direct_indexed(&self, offset: Byte, index: Word) -> Long {
+        if self.emulation && self.dp & 0xFF == 0 {
+            (self.dp | (offset.wrapping_add(index as Byte)) as Word) as Long
+        } else {
+            self.dp.wrapping_add(offset as Word).wrapping_add(index) as Long
+        }
+    }
+
+    fn data_address(&self, addr: Word) -> Long {
+        ((self.dbr as Long) << 16) | addr as Long
+    }
+
+    /// Adds an index to a 24-bit base. Reads take an extra cycle when the index is
+    /// 16 bits wide or a page is crossed; writes and read-modify-writes always do.
+    fn indexed(&mut self, base: Long, index: Word, always: bool) -> Long {
+        let addr = (base + index as Long) & 0xFF_FFFF;
+        if always || !self.x8() || (base & 0xFFFF00) != (addr & 0xFFFF00) {
+            self.io();
+        }
+        addr
+    }
+
+    /// Effective address of a memory operand and whether it wraps within bank 0.
+    fn address(&mut self, mode: Mode, bus: &mut impl LongBus, write: bool) -> (Long, bool) {
+        match mode {
+            Mode::Direct => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                (self.direct_indexed(offset, 0), true)
+            }
+            Mode::DirectX | Mode::DirectY => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                self.io();
+                let index = if mode == Mode::DirectX { self.reg_x } else { self.reg_y };
+                (self.direct_indexed(offset, index), true)
+            }
+            Mode::DirectIndirect => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                let ptr = self.direct_indexed(offset, 0);
+                let addr = self.read_word(bus, ptr, true);
+                (self.data_address(addr), false)
+            }
+            Mode::DirectIndirectLong => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                let ptr = self.direct_indexed(offset, 0);
+                (self.read_long(bus, ptr, true), false)
+            }
+            Mode::DirectXIndirect => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                self.io();
+                let ptr = self.direct_indexed(offset, self.reg_x);
+                let addr = self.read_word(bus, ptr, true);
+                (self.data_address(addr), false)
+            }
+            Mode::DirectIndirectY => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                let ptr = self.direct_indexed(offset, 0);
+                let base = self.read_word(bus, ptr, true);
+                let base = self.data_address(base);
+                (self.indexed(base, self.reg_y, write), false)
+            }
+            Mode::DirectIndirectLongY => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                let ptr = self.direct_indexed(offset, 0);
+                let base = self.read_long(bus, ptr, true);
+                ((base + self.reg_y as Long) & 0xFF_FFFF, false)
+            }
+            Mode::Absolute => {
+                let addr = self.fetch_word(bus);
+                (self.data_address(addr), false)
+            }
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let addr = self.fetch_word(bus);
+                let index = if mode == Mode::AbsoluteX { self.reg_x } else { self.reg_y };
+                let base = self.data_address(addr);
+                (self.indexed(base, index, write), false)
+            }
+            Mode::AbsoluteLong => (self.fetch_long(bus), false),
+            Mode::AbsoluteLongX => {
+                let base = self.fetch_long(bus);
+                ((base + self.reg_x as Long) & 0xFF_FFFF, false)
+            }
+            Mode::StackRelative => {
+                let offset = self.fetch_byte(bus);
+                self.io();
+                (self.sp.wrapping_add(offset as Word) as Long, true)
+            }
+            Mode::StackRelativeIndirectY => {
+                let offset = self.fetch_byte(bus);
+                self.io();
+                let ptr = self.sp.wrapping_add(offset as Word) as Long;
+                let base = self.read_word(bus, ptr, true);
+                self.io();
+                ((self.data_address(base) + self.reg_y as Long) & 0xFF_FFFF, false)
+            }
+            _ => panic!("Addressing mode {:?} has no memory operand", mode),
+        }
+    }
+
+    /// Reads an operand of the given width: from the instruction stream for
+    /// immediate mode, otherwise from the effective address.
+    fn operand(&mut self, mode: Mode, bus: &mut impl LongBus, byte: bool) -> Word {
+        if mode == Mode::Immediate {
+            if byte {
+                self.fetch_byte(bus) as Word
+            } else {
+                self.fetch_word(bus)
+            }
+        } else {
+            let (addr, bank0) = self.address(mode, bus, false);
+            self.read_data(bus, addr, bank0, byte)
+        }
+    }
+}
+
+// Register and flag helpers
+impl Cpu {
+    fn set_nz(&mut self, value: Word, byte: bool) {
+        if byte {
+            self.status.zero = value as Byte == 0;
+            self.status.negative = value & 0x80 != 0;
+        } else {
+            self.status.zero = value == 0;
+            self.status.negative = value & 0x8000 != 0;
+        }
+    }
+
+    /// Writes the low byte only when `byte` is set, keeping the high byte (B for the
+    /// accumulator).
+    fn merge(old: Word, value: Word, byte: bool) -> Word {
+        if byte { (old & 0xFF00) | (value & 0xFF) } else { value }
+    }
+
+    fn set_a(&mut self, value: Word) {
+        let byte = self.m8();
+        self.reg_a = Self::merge(self.reg_a, value, byte);
+        self.set_nz(value, byte);
+    }
+
+    fn set_x(&mut self, value: Word) {
+        self.reg_x = if self.x8() { value & 0xFF } else { value };
+        self.set_nz(value, self.x8());
+    }
+
+    fn set_y(&mut self, value: Word) {
+        self.reg_y = if self.x8() { value & 0xFF } else { value };
+        self.set_nz(value, self.x8());
+    }
+
+    /// Applies P after PLP, RTI, REP, SEP or XCE: emulation mode forces 8-bit
+    /// registers and 8-bit index registers lose their high bytes.
+    fn update_widths(&mut self) {
+        if self.emulation {
+            self.status.accumulator_8bit = true;
+            self.status.index_8bit = true;
+        }
+        if self.status.index_8bit {
+            self.reg_x &= 0xFF;
+            self.reg_y &= 0xFF;
+        }
+    }
+
+    fn compare(&mut self, reg: Word, operand: Word, byte: bool) {
+        let (reg, operand) = if byte { (reg & 0xFF, operand & 0xFF) } else { (reg, operand) };
+        self.status.carry = reg >= operand;
+        self.set_nz(reg.wrapping_sub(operand), byte);
+    }
+
+    fn adc(&mut self, operand: Word) {
+        let byte = self.m8();
+        let (mask, sign) = if byte { (0xFFu32, 0x80u32) } else { (0xFFFF, 0x8000) };
+        let a = self.reg_a as u32 & mask;
+        let b = operand as u32 & mask;
+        let carry = self.status.carry as u32;
+        let result = if self.status.decimal_mode {
+            let digits = if byte { 2 } else { 4 };
+            let mut result = 0;
+            let mut c = carry;
+            for digit in 0..digits {
+                let shift = digit * 4;
+                let mut d = ((a >> shift) & 0xF) + ((b >> shift) & 0xF) + c;
+                if digit == digits - 1 {
+                    // V comes from the top digit before decimal adjustment
+                    let unadjusted = result | ((d & 0xF) << shift);
+                    self.status.overflow = ((a ^ unadjusted) & (b ^ unadjusted) & sign) != 0;
+                }
+                if d > 9 {
+                    d += 6;
+                }
+                c = (d > 0xF) as u32;
+                result |= (d & 0xF) << shift;
+            }
+            self.status.carry = c != 0;
+            result
+        } else {
+            let sum = a + b + carry;
+            self.status.carry = sum > mask;
+            self.status.overflow = ((a ^ sum) & (b ^ sum) & sign) != 0;
+            sum & mask
+        };
+        self.set_a(result as Word);
+    }
+
+    fn sbc(&mut self, operand: Word) {
+        if !self.status.decimal_mode {
+            self.adc(!operand);
+            return;
+        }
+        let byte = self.m8();
+        let (mask, sign) = if byte { (0xFFi32, 0x80i32) } else { (0xFFFF, 0x8000) };
+        let a = self.reg_a as i32 & mask;
+        let b = operand as i32 & mask;
+        let borrow_in = (!self.status.carry) as i32;
+        let binary = (a - b - borrow_in) & mask;
+        self.status.overflow = ((a ^ b) & (a ^ binary) & sign) != 0;
+
+        let digits = if byte { 2 } else { 4 };
+        let mut result = 0;
+        let mut borrow = borrow_in;
+        for digit in 0..digits {
+            let shift = digit * 4;
+            let mut d = ((a >> shift) & 0xF) - ((b >> shift) & 0xF) - borrow;
+            borrow = (d < 0) as i32;
+            if d < 0 {
+                d += 10;
+            }
+            result |= (d & 0xF) << shift;
+        }
+        self.status.carry = borrow == 0;
+        self.set_a(result as Word);
+    }
+
+    /// Shift, rotate, increment or decrement a value of the given width.
+    fn modify(&mut self, op: Op, value: Word, byte: bool) -> Word {
+        let sign: Word = if byte { 0x80 } else { 0x8000 };
+        let mask: Word = if byte { 0xFF } else { 0xFFFF };
+        let result = match op {
+            Op::Asl => {
+                self.status.carry = value & sign != 0;
+                value << 1
+            }
+            Op::Lsr => {
+                self.status.carry = value & 1 != 0;
+                (value & mask) >> 1
+            }
+            Op::Rol => {
+                let carry_in = self.status.carry as Word;
+                self.status.carry = value & sign != 0;
+                (value << 1) | carry_in
+            }
+            Op::Ror => {
+                let carry_in = if self.status.carry { sign } else { 0 };
+                self.status.carry = value & 1 != 0;
+                ((value & mask) >> 1) | carry_in
+            }
+            Op::Inc => value.wrapping_add(1),
+            _ => value.wrapping_sub(1),
+        } & mask;
+        self.set_nz(result, byte);
+        result
+    }
+
+    fn branch(&mut self, bus: &mut impl LongBus, taken: bool) {
+        let offset = self.fetch_byte(bus) as i8;
+        if taken {
+            self.io();
+            let old_pc = self.pc;
+            self.pc = self.pc.wrapping_add(offset as Word);
+            if self.emulation && (old_pc & 0xFF00) != (self.pc & 0xFF00) {
+                self.io();
+            }
+        }
+    }
+
+    /// MVN/MVP: moves one byte per execution and repeats the instruction until
+    /// C wraps to 0xFFFF, so interrupts can be taken in the middle of a block.
+    fn block_move(&mut self, bus: &mut impl LongBus, step: Word) {
+        let dst = self.fetch_byte(bus);
+        let src = self.fetch_byte(bus);
+        self.dbr = dst;
+        let value = self.read(bus, ((src as Long) << 16) | self.reg_x as Long);
+        self.write(bus, ((dst as Long) << 16) | self.reg_y as Long, value);
+        self.io();
+        self.io();
+        let (x, y) = (self.reg_x.wrapping_add(step), self.reg_y.wrapping_add(step));
+        if self.x8() {
+            self.reg_x = x & 0xFF;
+            self.reg_y = y & 0xFF;
+        } else {
+            self.reg_x = x;
+            self.reg_y = y;
+        }
+        self.reg_a = self.reg_a.wrapping_sub(1);
+        if self.reg_a != 0xFFFF {
+            self.pc = self.pc.wrapping_sub(3);
+        }
+    }
+}
+
+impl Cpu {
+    fn dispatch(&mut self, op: Op, mode: Mode, bus: &mut impl LongBus) {
+        let m8 = self.m8();
+        let x8 = self.x8();
+        match op {
+            // --- Loads and stores ---
+            Op::Lda => {
+                let value = self.operand(mode, bus, m8);
+                self.set_a(value);
+            }
+            Op::Ldx => {
+                let value = self.operand(mode, bus, x8);
+                self.set_x(value);
+            }
+            Op::Ldy => {
+                let value = self.operand(mode, bus, x8);
+                self.set_y(value);
+            }
+            Op::Sta | Op::Stx | Op::Sty | Op::Stz => {
+                let (value, byte) = match op {
+                    Op::Sta => (self.reg_a, m8),
+                    Op::Stx => (self.reg_x, x8),
+                    Op::Sty => (self.reg_y, x8),
+                    _ => (0, m8),
+                };
+                let (addr, bank0) = self.address(mode, bus, true);
+                self.write_data(bus, addr, bank0, byte, value);
+            }
+
+            // --- ALU ---
+            Op::Ora => {
+                let value = self.operand(mode, bus, m8);
+                self.set_a(self.reg_a | value);
+            }
+            Op::And => {
+                let value = self.operand(mode, bus, m8);
+                self.set_a(self.reg_a & value);
+            }
+            Op::Eor => {
+                let value = self.operand(mode, bus, m8);
+                self.set_a(self.reg_a ^ value);
+            }
+            Op::Adc => {
+                let value = self.operand(mode, bus, m8);
+                self.adc(value);
+            }
+            Op::Sbc => {
+                let value = self.operand(mode, bus, m8);
+                self.sbc(value);
+            }
+            Op::Cmp => {
+                let value = self.operand(mode, bus, m8);
+                self.compare(self.reg_a, value, m8);
+            }
+            Op::Cpx => {
+                let value = self.operand(mode, bus, x8);
+                self.compare(self.reg_x, value, x8);
+            }
+            Op::Cpy => {
+                let value = self.operand(mode, bus, x8);
+                self.compare(self.reg_y, value, x8);
+            }
+            Op::Bit => {
+                let value = self.operand(mode, bus, m8);
+                let (mask, sign) = if m8 { (0xFF, 0x80) } else { (0xFFFF, 0x8000) };
+                self.status.zero = (self.reg_a & value & mask) == 0;
+                if mode != Mode::Immediate {
+                    self.status.negative = value & sign != 0;
+                    self.status.overflow = value & (sign >> 1) != 0;
+                }
+            }
+
+            // --- Read-modify-write ---
+            Op::Asl | Op::Lsr | Op::Rol | Op::Ror | Op::Inc | Op::Dec if mode == Mode::Accumulator => {
+                self.io();
+                let result = self.modify(op, self.reg_a, m8);
+                self.reg_a = Self::merge(self.reg_a, result, m8);
+            }
+            Op::Asl | Op::Lsr | Op::Rol | Op::Ror | Op::Inc | Op::Dec => {
+                let (addr, bank0) = self.address(mode, bus, true);
+                let value = self.read_data(bus, addr, bank0, m8);
+                self.io();
+                let result = self.modify(op, value, m8);
+                self.write_data(bus, addr, bank0, m8, result);
+            }
+            Op::Tsb | Op::Trb => {
+                let (addr, bank0) = self.address(mode, bus, true);
+                let value = self.read_data(bus, addr, bank0, m8);
+                self.io();
+                let mask = if m8 { 0xFF } else { 0xFFFF };
+                self.status.zero = (self.reg_a & value & mask) == 0;
+                let result = if op == Op::Tsb { value | self.reg_a } else { value & !self.reg_a };
+                self.write_data(bus, addr, bank0, m8, result);
+            }
+
+            // --- Index register increments ---
+            Op::Inx | Op::Dex | Op::Iny | Op::Dey => {
+                self.io();
+                match op {
+                    Op::Inx => self.set_x(self.reg_x.wrapping_add(1)),
+                    Op::Dex => self.set_x(self.reg_x.wrapping_sub(1)),
+                    Op::Iny => self.set_y(self.reg_y.wrapping_add(1)),
+                    _ => self.set_y(self.reg_y.wrapping_sub(1)),
+                }
+            }
+
+            // --- Branches ---
+            Op::Bpl => { let taken = !self.status.negative; self.branch(bus, taken); }
+            Op::Bmi => { let taken = self.status.negative; self.branch(bus, taken); }
+            Op::Bvc => { let taken = !self.status.overflow; self.branch(bus, taken); }
+            Op::Bvs => { let taken = self.status.overflow; self.branch(bus, taken); }
+            Op::Bcc => { let taken = !self.status.carry; self.branch(bus, taken); }
+            Op::Bcs => { let taken = self.status.carry; self.branch(bus, taken); }
+            Op::Bne => { let taken = !self.status.zero; self.branch(bus, taken); }
+            Op::Beq => { let taken = self.status.zero; self.branch(bus, taken); }
+            Op::Bra => self.branch(bus, true),
+            Op::Brl => {
+                let offset = self.fetch_word(bus);
+                self.io();
+                self.pc = self.pc.wrapping_add(offset);
+            }
+
+            // --- Jumps and calls ---
+            Op::Jmp => {
+                self.pc = match mode {
+                    Mode::Absolute => self.fetch_word(bus),
+                    Mode::AbsoluteIndirect => {
+                        let ptr = self.fetch_word(bus);
+                        self.read_word(bus, ptr as Long, true)
+                    }
+                    _ => {
+                        let ptr = self.fetch_word(bus).wrapping_add(self.reg_x);
+                        self.io();
+                        let ptr = ((self.pbr as Long) << 16) | ptr as Long;
+                        self.read_word(bus, ptr, false)
+                    }
+                };
+            }
+            Op::Jml => {
+                let target = if mode == Mode::AbsoluteLong {
+                    self.fetch_long(bus)
+                } else {
+                    let ptr = self.fetch_word(bus);
+                    self.read_long(bus, ptr as Long, true)
+                };
+                self.pbr = (target >> 16) as Byte;
+                self.pc = target as Word;
+            }
+            Op::Jsr => {
+                if mode == Mode::Absolute {
+                    let target = self.fetch_word(bus);
+                    self.io();
+                    self.push_word(bus, self.pc.wrapping_sub(1));
+                    self.pc = target;
+                } else {
+                    let lo = self.fetch_byte(bus) as Word;
+                    self.push_word(bus, self.pc);
+                    let hi = self.fetch_byte(bus) as Word;
+                    self.io();
+                    let ptr = ((hi << 8) | lo).wrapping_add(self.reg_x);
+                    let ptr = ((self.pbr as Long) << 16) | ptr as Long;
+                    self.pc = self.read_word(bus, ptr, false);
+                }
+            }
+            Op::Jsl => {
+                let lo = self.fetch_word(bus);
+                self.push_byte(bus, self.pbr);
+                self.io();
+                let bank = self.fetch_byte(bus);
+                self.push_word(bus, self.pc.wrapping_sub(1));
+                self.pbr = bank;
+                self.pc = lo;
+            }
+            Op::Rts => {
+                self.io();
+                self.io();
+                self.pc = self.pull_word(bus).wrapping_add(1);
+                self.io();
+            }
+            Op::Rtl => {
+                self.io();
+                self.io();
+                self.pc = self.pull_word(bus).wrapping_add(1);
+                self.pbr = self.pull_byte(bus);
+            }
+            Op::Rti => {
+                self.io();
+                self.io();
+                let status = self.pull_byte(bus);
+                self.status = StatusFlags::from_byte(status);
+                self.update_widths();
+                self.pc = self.pull_word(bus);
+                if !self.emulation {
+                    self.pbr = self.pull_byte(bus);
+                }
+            }
+            Op::Brk | Op::Cop => {
+                // The signature byte is skipped
+                self.fetch_byte(bus);
+                let vector = match (op, self.emulation) {
+                    (Op::Brk, true) => 0xFFFE,
+                    (Op::Brk, false) => 0xFFE6,
+                    (_, true) => 0xFFF4,
+                    (_, false) => 0xFFE4,
+                };
+                self.interrupt(bus, vector, true);
+            }
+
+            // --- Stack ---
+            Op::Pha => { self.io(); self.push_data(bus, self.reg_a, m8); }
+            Op::Phx => { self.io(); self.push_data(bus, self.reg_x, x8); }
+            Op::Phy => { self.io(); self.push_data(bus, self.reg_y, x8); }
+            Op::Php => {
+                self.io();
+                let status = self.status.to_byte(self.emulation, true);
+                self.push_byte(bus, status);
+            }
+            Op::Phb => { self.io(); self.push_byte(bus, self.dbr); }
+            Op::Phk => { self.io(); self.push_byte(bus, self.pbr); }
+            Op::Phd => { self.io(); self.push_word(bus, self.dp); }
+            Op::Pla => {
+                self.io();
+                self.io();
+                let value = self.pull_data(bus, m8);
+                self.set_a(value);
+            }
+            Op::Plx => {
+                self.io();
+                self.io();
+                let value = self.pull_data(bus, x8);
+                self.set_x(value);
+            }
+            Op::Ply => {
+                self.io();
+                self.io();
+                let value = self.pull_data(bus, x8);
+                self.set_y(value);
+            }
+            Op::Plp => {
+                self.io();
+                self.io();
+                let status = self.pull_byte(bus);
+                self.status = StatusFlags::from_byte(status);
+                self.update_widths();
+            }
+            Op::Plb => {
+                self.io();
+                self.io();
+                self.dbr = self.pull_byte(bus);
+                self.set_nz(self.dbr as Word, true);
+            }
+            Op::Pld => {
+                self.io();
+                self.io();
+                self.dp = self.pull_word(bus);
+                self.set_nz(self.dp, false);
+            }
+            Op::Pea => {
+                let value = self.fetch_word(bus);
+                self.push_word(bus, value);
+            }
+            Op::Pei => {
+                let offset = self.fetch_byte(bus);
+                self.direct_penalty();
+                let ptr = self.direct_indexed(offset, 0);
+                let value = self.read_word(bus, ptr, true);
+                self.push_word(bus, value);
+            }
+            Op::Per => {
+                let offset = self.fetch_word(bus);
+                self.io();
+                self.push_word(bus, self.pc.wrapping_add(offset));
+            }
+
+            // --- Flags ---
+            Op::Clc => { self.io(); self.status.carry = false; }
+            Op::Sec => { self.io(); self.status.carry = true; }
+            Op::Cli => { self.io(); self.status.interrupt_disable = false; }
+            Op::Sei => { self.io(); self.status.interrupt_disable = true; }
+            Op::Cld => { self.io(); self.status.decimal_mode = false; }
+            Op::Sed => { self.io(); self.status.decimal_mode = true; }
+            Op::Clv => { self.io(); self.status.overflow = false; }
+            Op::Rep | Op::Sep => {
+                let mask = self.fetch_byte(bus);
+                self.io();
+                let status = self.status.to_byte(false, false);
+                let status = if op == Op::Rep { status & !mask } else { status | mask };
+                self.status = StatusFlags::from_byte(status);
+                self.update_widths();
+            }
+            Op::Xce => {
+                self.io();
+                std::mem::swap(&mut self.status.carry, &mut self.emulation);
+                if self.emulation {
+                    self.sp = 0x0100 | (self.sp & 0xFF);
+                }
+                self.update_widths();
+            }
+
+            // --- Transfers ---
+            Op::Tax => { self.io(); self.set_x(self.reg_a); }
+            Op::Tay => { self.io(); self.set_y(self.reg_a); }
+            Op::Txa => { self.io(); self.set_a(self.reg_x); }
+            Op::Tya => { self.io(); self.set_a(self.reg_y); }
+            Op::Txy => { self.io(); self.set_y(self.reg_x); }
+            Op::Tyx => { self.io(); self.set_x(self.reg_y); }
+            Op::Tsx => { self.io(); self.set_x(self.sp); }
+            Op::Txs => {
+                self.io();
+                self.sp = if self.emulation { 0x0100 | (self.reg_x & 0xFF) } else { self.reg_x };
+            }
+            Op::Tcs => {
+                self.io();
+                self.sp = if self.emulation { 0x0100 | (self.reg_a & 0xFF) } else { self.reg_a };
+            }
+            Op::Tsc => { self.io(); self.reg_a = self.sp; self.set_nz(self.sp, false); }
+            Op::Tcd => { self.io(); self.dp = self.reg_a; self.set_nz(self.dp, false); }
+            Op::Tdc => { self.io(); self.reg_a = self.dp; self.set_nz(self.dp, false); }
+            Op::Xba => {
+                self.io();
+                self.io();
+                self.reg_a = self.reg_a.rotate_left(8);
+                self.set_nz(self.reg_a, true);
+            }
+
+            // --- Block moves ---
+            Op::Mvn => self.block_move(bus, 1),
+            Op::Mvp => self.block_move(bus, 0xFFFF),
+
+            // --- Misc ---
+            Op::Nop => self.io(),
+            Op::Wdm => {
+                self.fetch_byte(bus);
+            }
+            Op::Wai => {
+                self.io();
+                self.io();
+                self.waiting = true;
+            }
+            Op::Stp => {
+                self.io();
+                self.io();
+                self.stopped = true;
+            }
+        }
+    }
+}
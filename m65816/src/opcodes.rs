@@ -0,0 +1,155 @@
+/// 65C816 addressing modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    /// Operand width follows M or X depending on the instruction
+    Immediate,
+    /// Always an 8-bit operand (REP, SEP, BRK, COP, WDM)
+    Immediate8,
+    Direct,
+    DirectX,
+    DirectY,
+    /// (dp)
+    DirectIndirect,
+    /// [dp]
+    DirectIndirectLong,
+    /// (dp,X)
+    DirectXIndirect,
+    /// (dp),Y
+    DirectIndirectY,
+    /// [dp],Y
+    DirectIndirectLongY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    AbsoluteLong,
+    AbsoluteLongX,
+    /// sr,S
+    StackRelative,
+    /// (sr,S),Y
+    StackRelativeIndirectY,
+    Relative,
+    RelativeLong,
+    /// JMP (a)
+    AbsoluteIndirect,
+    /// JML [a]
+    AbsoluteIndirectLong,
+    /// JMP/JSR (a,X)
+    AbsoluteXIndirect,
+    /// MVN/MVP dst,src
+    BlockMove,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Bra, Brk, Brl, Bvc, Bvs, Clc,
+    Cld, Cli, Clv, Cmp, Cop, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jml, Jmp,
+    Jsl, Jsr, Lda, Ldx, Ldy, Lsr, Mvn, Mvp, Nop, Ora, Pea, Pei, Per, Pha, Phb, Phd,
+    Phk, Php, Phx, Phy, Pla, Plb, Pld, Plp, Plx, Ply, Rep, Rol, Ror, Rti, Rtl, Rts,
+    Sbc, Sec, Sed, Sei, Sep, Sta, Stp, Stx, Sty, Stz, Tax, Tay, Tcd, Tcs, Tdc, Trb,
+    Tsb, Tsc, Tsx, Txa, Txs, Txy, Tya, Tyx, Wai, Wdm, Xba, Xce,
+}
+
+impl Op {
+    pub fn mnemonic(&self) -> &'static str {
+        const NAMES: [&str; 92] = [
+            "ADC", "AND", "ASL", "BCC", "BCS", "BEQ", "BIT", "BMI", "BNE", "BPL", "BRA", "BRK",
+            "BRL", "BVC", "BVS", "CLC", "CLD", "CLI", "CLV", "CMP", "COP", "CPX", "CPY", "DEC",
+            "DEX", "DEY", "EOR", "INC", "INX", "INY", "JML", "JMP", "JSL", "JSR", "LDA", "LDX",
+            "LDY", "LSR", "MVN", "MVP", "NOP", "ORA", "PEA", "PEI", "PER", "PHA", "PHB", "PHD",
+            "PHK", "PHP", "PHX", "PHY", "PLA", "PLB", "PLD", "PLP", "PLX", "PLY", "REP", "ROL",
+            "ROR", "RTI", "RTL", "RTS", "SBC", "SEC", "SED", "SEI", "SEP", "STA", "STP", "STX",
+            "STY", "STZ", "TAX", "TAY", "TCD", "TCS", "TDC", "TRB", "TSB", "TSC", "TSX", "TXA",
+            "TXS", "TXY", "TYA", "TYX", "WAI", "WDM", "XBA", "XCE",
+        ];
+        NAMES[*self as usize]
+    }
+}
+
+use Mode::*;
+use Op::*;
+
+/// Instruction and addressing mode for every opcode, indexed by opcode.
+pub const OPCODES: [(Op, Mode); 256] = [
+    // 0x00
+    (Brk, Immediate8), (Ora, DirectXIndirect), (Cop, Immediate8), (Ora, StackRelative),
+    (Tsb, Direct), (Ora, Direct), (Asl, Direct), (Ora, DirectIndirectLong),
+    (Php, Implied), (Ora, Immediate), (Asl, Accumulator), (Phd, Implied),
+    (Tsb, Absolute), (Ora, Absolute), (Asl, Absolute), (Ora, AbsoluteLong),
+    // 0x10
+    (Bpl, Relative), (Ora, DirectIndirectY), (Ora, DirectIndirect), (Ora, StackRelativeIndirectY),
+    (Trb, Direct), (Ora, DirectX), (Asl, DirectX), (Ora, DirectIndirectLongY),
+    (Clc, Implied), (Ora, AbsoluteY), (Inc, Accumulator), (Tcs, Implied),
+    (Trb, Absolute), (Ora, AbsoluteX), (Asl, AbsoluteX), (Ora, AbsoluteLongX),
+    // 0x20
+    (Jsr, Absolute), (And, DirectXIndirect), (Jsl, AbsoluteLong), (And, StackRelative),
+    (Bit, Direct), (And, Direct), (Rol, Direct), (And, DirectIndirectLong),
+    (Plp, Implied), (And, Immediate), (Rol, Accumulator), (Pld, Implied),
+    (Bit, Absolute), (And, Absolute), (Rol, Absolute), (And, AbsoluteLong),
+    // 0x30
+    (Bmi, Relative), (And, DirectIndirectY), (And, DirectIndirect), (And, StackRelativeIndirectY),
+    (Bit, DirectX), (And, DirectX), (Rol, DirectX), (And, DirectIndirectLongY),
+    (Sec, Implied), (And, AbsoluteY), (Dec, Accumulator), (Tsc, Implied),
+    (Bit, AbsoluteX), (And, AbsoluteX), (Rol, AbsoluteX), (And, AbsoluteLongX),
+    // 0x40
+    (Rti, Implied), (Eor, DirectXIndirect), (Wdm, Immediate8), (Eor, StackRelative),
+    (Mvp, BlockMove), (Eor, Direct), (Lsr, Direct), (Eor, DirectIndirectLong),
+    (Pha, Implied), (Eor, Immediate), (Lsr, Accumulator), (Phk, Implied),
+    (Jmp, Absolute), (Eor, Absolute), (Lsr, Absolute), (Eor, AbsoluteLong),
+    // 0x50
+    (Bvc, Relative), (Eor, DirectIndirectY), (Eor, DirectIndirect), (Eor, StackRelativeIndirectY),
+    (Mvn, BlockMove), (Eor, DirectX), (Lsr, DirectX), (Eor, DirectIndirectLongY),
+    (Cli, Implied), (Eor, AbsoluteY), (Phy, Implied), (Tcd, Implied),
+    (Jml, AbsoluteLong), (Eor, AbsoluteX), (Lsr, AbsoluteX), (Eor, AbsoluteLongX),
+    // 0x60
+    (Rts, Implied), (Adc, DirectXIndirect), (Per, RelativeLong), (Adc, StackRelative),
+    (Stz, Direct), (Adc, Direct), (Ror, Direct), (Adc, DirectIndirectLong),
+    (Pla, Implied), (Adc, Immediate), (Ror, Accumulator), (Rtl, Implied),
+    (Jmp, AbsoluteIndirect), (Adc, Absolute), (Ror, Absolute), (Adc, AbsoluteLong),
+    // 0x70
+    (Bvs, Relative), (Adc, DirectIndirectY), (Adc, DirectIndirect), (Adc, StackRelativeIndirectY),
+    (Stz, DirectX), (Adc, DirectX), (Ror, DirectX), (Adc, DirectIndirectLongY),
+    (Sei, Implied), (Adc, AbsoluteY), (Ply, Implied), (Tdc, Implied),
+    (Jmp, AbsoluteXIndirect), (Adc, AbsoluteX), (Ror, AbsoluteX), (Adc, AbsoluteLongX),
+    // 0x80
+    (Bra, Relative), (Sta, DirectXIndirect), (Brl, RelativeLong), (Sta, StackRelative),
+    (Sty, Direct), (Sta, Direct), (Stx, Direct), (Sta, DirectIndirectLong),
+    (Dey, Implied), (Bit, Immediate), (Txa, Implied), (Phb, Implied),
+    (Sty, Absolute), (Sta, Absolute), (Stx, Absolute), (Sta, AbsoluteLong),
+    // 0x90
+    (Bcc, Relative), (Sta, DirectIndirectY), (Sta, DirectIndirect), (Sta, StackRelativeIndirectY),
+    (Sty, DirectX), (Sta, DirectX), (Stx, DirectY), (Sta, DirectIndirectLongY),
+    (Tya, Implied), (Sta, AbsoluteY), (Txs, Implied), (Txy, Implied),
+    (Stz, Absolute), (Sta, AbsoluteX), (Stz, AbsoluteX), (Sta, AbsoluteLongX),
+    // 0xA0
+    (Ldy, Immediate), (Lda, DirectXIndirect), (Ldx, Immediate), (Lda, StackRelative),
+    (Ldy, Direct), (Lda, Direct), (Ldx, Direct), (Lda, DirectIndirectLong),
+    (Tay, Implied), (Lda, Immediate), (Tax, Implied), (Plb, Implied),
+    (Ldy, Absolute), (Lda, Absolute), (Ldx, Absolute), (Lda, AbsoluteLong),
+    // 0xB0
+    (Bcs, Relative), (Lda, DirectIndirectY), (Lda, DirectIndirect), (Lda, StackRelativeIndirectY),
+    (Ldy, DirectX), (Lda, DirectX), (Ldx, DirectY), (Lda, DirectIndirectLongY),
+    (Clv, Implied), (Lda, AbsoluteY), (Tsx, Implied), (Tyx, Implied),
+    (Ldy, AbsoluteX), (Lda, AbsoluteX), (Ldx, AbsoluteY), (Lda, AbsoluteLongX),
+    // 0xC0
+    (Cpy, Immediate), (Cmp, DirectXIndirect), (Rep, Immediate8), (Cmp, StackRelative),
+    (Cpy, Direct), (Cmp, Direct), (Dec, Direct), (Cmp, DirectIndirectLong),
+    (Iny, Implied), (Cmp, Immediate), (Dex, Implied), (Wai, Implied),
+    (Cpy, Absolute), (Cmp, Absolute), (Dec, Absolute), (Cmp, AbsoluteLong),
+    // 0xD0
+    (Bne, Relative), (Cmp, DirectIndirectY), (Cmp, DirectIndirect), (Cmp, StackRelativeIndirectY),
+    (Pei, DirectIndirect), (Cmp, DirectX), (Dec, DirectX), (Cmp, DirectIndirectLongY),
+    (Cld, Implied), (Cmp, AbsoluteY), (Phx, Implied), (Stp, Implied),
+    (Jml, AbsoluteIndirectLong), (Cmp, AbsoluteX), (Dec, AbsoluteX), (Cmp, AbsoluteLongX),
+    // 0xE0
+    (Cpx, Immediate), (Sbc, DirectXIndirect), (Sep, Immediate8), (Sbc, StackRelative),
+    (Cpx, Direct), (Sbc, Direct), (Inc, Direct), (Sbc, DirectIndirectLong),
+    (Inx, Implied), (Sbc, Immediate), (Nop, Implied), (Xba, Implied),
+    (Cpx, Absolute), (Sbc, Absolute), (Inc, Absolute), (Sbc, AbsoluteLong),
+    // 0xF0
+    (Beq, Relative), (Sbc, DirectIndirectY), (Sbc, DirectIndirect), (Sbc, StackRelativeIndirectY),
+    (Pea, Absolute), (Sbc, DirectX), (Inc, DirectX), (Sbc, DirectIndirectLongY),
+    (Sed, Implied), (Sbc, AbsoluteY), (Plx, Implied), (Xce, Implied),
+    (Jsr, AbsoluteXIndirect), (Sbc, AbsoluteX), (Inc, AbsoluteX), (Sbc, AbsoluteLongX),
+];
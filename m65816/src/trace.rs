@@ -0,0 +1,139 @@
+use crate::opcodes::{Mode, OPCODES, Op};
+use crate::{Cpu, Long, LongMemory};
+use core::fmt::{self, Write};
+use m6502::{Byte, Word, write_trace_columns};
+
+use Mode::*;
+
+/// Whether an immediate operand of `op` follows the X flag rather than M.
+fn index_immediate(op: Op) -> bool {
+    matches!(op, Op::Cpx | Op::Cpy | Op::Ldx | Op::Ldy)
+}
+
+/// Length in bytes of an instruction in `mode`, with 8-bit immediates if `byte`.
+fn len(mode: Mode, byte: bool) -> Long {
+    match mode {
+        Implied | Accumulator => 1,
+        Immediate if byte => 2,
+        Immediate => 3,
+        Absolute | AbsoluteX | AbsoluteY | AbsoluteIndirect | AbsoluteIndirectLong | AbsoluteXIndirect => 3,
+        RelativeLong | BlockMove => 3,
+        AbsoluteLong | AbsoluteLongX => 4,
+        _ => 2,
+    }
+}
+
+/// `addr + offset`, wrapping within the bank as the program counter does.
+fn in_bank(addr: Long, offset: Long) -> Long {
+    (addr & 0xFF_0000) | (addr.wrapping_add(offset) & 0xFFFF)
+}
+
+/// The instruction at `addr`: its opcode table entry, its bytes and its length.
+/// `accumulator_8bit` and `index_8bit` give the width of immediate operands.
+fn decode(memory: &LongMemory, addr: Long, accumulator_8bit: bool, index_8bit: bool) -> (Op, Mode, [Byte; 4], Long) {
+    let (op, mode) = OPCODES[memory.data[(addr & 0xFF_FFFF) as usize] as usize];
+    let byte = if index_immediate(op) { index_8bit } else { accumulator_8bit };
+    let len = len(mode, byte);
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate().take(len as usize) {
+        *byte = memory.data[in_bank(addr, i as Long) as usize];
+    }
+    (op, mode, bytes, len)
+}
+
+/// Writes the instruction at `addr` to `out`, e.g. `LDA [$10],Y`, without
+/// allocating. Immediate operands are 8 or 16 bits wide as `accumulator_8bit`
+/// and `index_8bit` say. Returns the instruction length in bytes.
+pub fn write_disassembly(
+    out: &mut impl Write,
+    memory: &LongMemory,
+    addr: Long,
+    accumulator_8bit: bool,
+    index_8bit: bool,
+) -> Result<Long, fmt::Error> {
+    let (op, mode, bytes, len) = decode(memory, addr, accumulator_8bit, index_8bit);
+    let byte = bytes[1];
+    let word = Word::from_le_bytes([bytes[1], bytes[2]]);
+    let long = Long::from_le_bytes([bytes[1], bytes[2], bytes[3], 0]);
+    out.write_str(op.mnemonic())?;
+    match mode {
+        Implied => {}
+        Accumulator => out.write_str(" A")?,
+        Immediate if len == 3 => write!(out, " #${:04X}", word)?,
+        Immediate | Immediate8 => write!(out, " #${:02X}", byte)?,
+        Direct => write!(out, " ${:02X}", byte)?,
+        DirectX => write!(out, " ${:02X},X", byte)?,
+        DirectY => write!(out, " ${:02X},Y", byte)?,
+        DirectIndirect => write!(out, " (${:02X})", byte)?,
+        DirectIndirectLong => write!(out, " [${:02X}]", byte)?,
+        DirectXIndirect => write!(out, " (${:02X},X)", byte)?,
+        DirectIndirectY => write!(out, " (${:02X}),Y", byte)?,
+        DirectIndirectLongY => write!(out, " [${:02X}],Y", byte)?,
+        Absolute => write!(out, " ${:04X}", word)?,
+        AbsoluteX => write!(out, " ${:04X},X", word)?,
+        AbsoluteY => write!(out, " ${:04X},Y", word)?,
+        AbsoluteLong => write!(out, " ${:06X}", long)?,
+        AbsoluteLongX => write!(out, " ${:06X},X", long)?,
+        StackRelative => write!(out, " ${:02X},S", byte)?,
+        StackRelativeIndirectY => write!(out, " (${:02X},S),Y", byte)?,
+        Relative => write!(out, " ${:04X}", (addr as Word).wrapping_add(2).wrapping_add(byte as i8 as Word))?,
+        RelativeLong => write!(out, " ${:04X}", (addr as Word).wrapping_add(3).wrapping_add(word))?,
+        AbsoluteIndirect => write!(out, " (${:04X})", word)?,
+        AbsoluteIndirectLong => write!(out, " [${:04X}]", word)?,
+        AbsoluteXIndirect => write!(out, " (${:04X},X)", word)?,
+        // Source bank first, as assemblers write it; the bytes are the other way round
+        BlockMove => write!(out, " ${:02X},${:02X}", bytes[2], bytes[1])?,
+    }
+    Ok(len)
+}
+
+/// Disassembles the instruction at `addr`, e.g. `LDA [$10],Y`.
+/// Returns the text and the instruction length in bytes.
+pub fn disassemble(memory: &LongMemory, addr: Long, accumulator_8bit: bool, index_8bit: bool) -> (String, Long) {
+    let mut text = String::new();
+    let len = write_disassembly(&mut text, memory, addr, accumulator_8bit, index_8bit)
+        .expect("formatting into a String cannot fail");
+    (text, len)
+}
+
+impl Cpu {
+    /// Writes the instruction at PBR:PC and the registers before it runs to
+    /// `out`, in the columns of `m6502::Cpu::write_trace_line`:
+    ///
+    /// `008004  A9 34 12     LDA #$1234                      A:0000 X:0000 Y:0000 P:05 SP:01FD D:0000 DB:00 E:0 CYC:9`
+    ///
+    /// Immediate operands are shown as wide as the M and X flags make them.
+    pub fn write_trace_line(&self, out: &mut impl Write, memory: &LongMemory) -> fmt::Result {
+        let addr = self.program_address();
+        let mut text = String::new();
+        write_disassembly(&mut text, memory, addr, self.m8(), self.x8())?;
+        let (_, _, bytes, len) = decode(memory, addr, self.m8(), self.x8());
+        write_trace_columns(
+            out,
+            format_args!("{:06X}", addr),
+            &bytes[..len as usize],
+            11,
+            true,
+            &text,
+            format_args!(
+                "A:{:04X} X:{:04X} Y:{:04X} P:{:02X} SP:{:04X} D:{:04X} DB:{:02X} E:{} CYC:{}",
+                self.reg_a,
+                self.reg_x,
+                self.reg_y,
+                self.status.to_byte(self.emulation, false),
+                self.sp,
+                self.dp,
+                self.dbr,
+                self.emulation as u8,
+                self.cycles
+            ),
+        )
+    }
+
+    /// `write_trace_line` into a new `String`.
+    pub fn trace_line(&self, memory: &LongMemory) -> String {
+        let mut line = String::new();
+        self.write_trace_line(&mut line, memory).expect("formatting into a String cannot fail");
+        line
+    }
+}
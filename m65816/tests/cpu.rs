@@ -0,0 +1,136 @@
+use m65816::opcodes::OPCODES;
+use m65816::{Cpu, LongMemory};
+
+fn setup(program: &[u8]) -> (Cpu, LongMemory) {
+    let mut memory = LongMemory::new();
+    memory.data[0xFFFC] = 0x00;
+    memory.data[0xFFFD] = 0x80;
+    memory.data[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut memory);
+    (cpu, memory)
+}
+
+// CLC; XCE; REP #$30
+const NATIVE_16: [u8; 4] = [0x18, 0xFB, 0xC2, 0x30];
+
+fn native(program: &[u8]) -> (Cpu, LongMemory) {
+    let (mut cpu, mut memory) = setup(&[&NATIVE_16[..], program].concat());
+    assert_eq!(cpu.execute(2 + 2 + 3, &mut memory), 7);
+    assert!(!cpu.emulation);
+    (cpu, memory)
+}
+
+#[test]
+fn opcode_table_mnemonics_match_variants() {
+    for (op, _) in OPCODES {
+        assert_eq!(format!("{:?}", op).to_uppercase(), op.mnemonic());
+    }
+}
+
+#[test]
+fn runs_6502_code_in_emulation_mode() {
+    // LDA #$42; STA $10
+    let (mut cpu, mut memory) = setup(&[0xA9, 0x42, 0x85, 0x10]);
+    assert!(cpu.emulation);
+    assert_eq!(cpu.execute(2 + 3, &mut memory), 5);
+    assert_eq!(memory.data[0x10], 0x42);
+    assert_eq!(cpu.sp, 0x01FD);
+}
+
+#[test]
+fn rep_cannot_widen_registers_in_emulation_mode() {
+    let (mut cpu, mut memory) = setup(&[0xC2, 0x30, 0xA9, 0x34, 0x12]);
+    cpu.execute(3 + 2, &mut memory);
+    assert!(cpu.status.accumulator_8bit);
+    assert!(cpu.status.index_8bit);
+    assert_eq!(cpu.reg_a, 0x34);
+    assert_eq!(cpu.pc, 0x8004);
+}
+
+#[test]
+fn sixteen_bit_load_and_store() {
+    // LDA #$1234; STA $2000
+    let (mut cpu, mut memory) = native(&[0xA9, 0x34, 0x12, 0x8D, 0x00, 0x20]);
+    assert_eq!(cpu.execute(3 + 5, &mut memory), 8);
+    assert_eq!(memory.data[0x2000], 0x34);
+    assert_eq!(memory.data[0x2001], 0x12);
+    assert!(!cpu.status.negative);
+}
+
+#[test]
+fn narrowing_the_index_registers_clears_their_high_bytes() {
+    // LDX #$1234; SEP #$10
+    let (mut cpu, mut memory) = native(&[0xA2, 0x34, 0x12, 0xE2, 0x10]);
+    cpu.execute(3 + 3, &mut memory);
+    assert_eq!(cpu.reg_x, 0x0034);
+}
+
+#[test]
+fn long_addressing_reaches_other_banks() {
+    // LDA $123456; STA $7E0000
+    let (mut cpu, mut memory) = setup(&[0xAF, 0x56, 0x34, 0x12, 0x8F, 0x00, 0x00, 0x7E]);
+    memory.data[0x123456] = 0x99;
+    assert_eq!(cpu.execute(5 + 5, &mut memory), 10);
+    assert_eq!(memory.data[0x7E0000], 0x99);
+    assert!(cpu.status.negative);
+}
+
+#[test]
+fn mvn_copies_a_block_between_banks() {
+    // MVN $01,$00
+    let (mut cpu, mut memory) = native(&[0x54, 0x01, 0x00]);
+    cpu.reg_a = 2;
+    cpu.reg_x = 0x1000;
+    cpu.reg_y = 0x2000;
+    memory.data[0x1000..0x1003].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(cpu.execute(3 * 7, &mut memory), 21);
+    assert_eq!(&memory.data[0x012000..0x012003], &[1, 2, 3]);
+    assert_eq!(cpu.reg_a, 0xFFFF);
+    assert_eq!(cpu.reg_x, 0x1003);
+    assert_eq!(cpu.dbr, 0x01);
+    assert_eq!(cpu.pc, 0x8007);
+}
+
+#[test]
+fn sixteen_bit_decimal_adc() {
+    // SED; CLC; ADC #$0001
+    let (mut cpu, mut memory) = native(&[0xF8, 0x18, 0x69, 0x01, 0x00]);
+    cpu.reg_a = 0x1999;
+    cpu.execute(2 + 2 + 3, &mut memory);
+    assert_eq!(cpu.reg_a, 0x2000);
+    assert!(!cpu.status.carry);
+}
+
+#[test]
+fn xba_swaps_accumulator_halves() {
+    let (mut cpu, mut memory) = native(&[0xEB]);
+    cpu.reg_a = 0x80FF;
+    assert_eq!(cpu.step(&mut memory), 3);
+    assert_eq!(cpu.reg_a, 0xFF80);
+    assert!(cpu.status.negative);
+}
+
+#[test]
+fn native_brk_pushes_the_program_bank() {
+    let (mut cpu, mut memory) = native(&[0x00, 0xEA]);
+    memory.data[0xFFE6] = 0x00;
+    memory.data[0xFFE7] = 0x90;
+    assert_eq!(cpu.step(&mut memory), 8);
+    assert_eq!(cpu.pc, 0x9000);
+    assert_eq!(cpu.sp, 0x01F9);
+    assert_eq!(memory.data[0x01FD], 0x00);
+    assert_eq!(memory.data[0x01FC], 0x80);
+    assert_eq!(memory.data[0x01FB], 0x06);
+}
+
+#[test]
+fn jsl_and_rtl_cross_banks() {
+    // JSL $018000
+    let (mut cpu, mut memory) = setup(&[0x22, 0x00, 0x80, 0x01, 0xEA]);
+    memory.data[0x018000] = 0x6B;
+    assert_eq!(cpu.step(&mut memory), 8);
+    assert_eq!(cpu.program_address(), 0x018000);
+    assert_eq!(cpu.step(&mut memory), 6);
+    assert_eq!(cpu.program_address(), 0x008004);
+}
@@ -0,0 +1,72 @@
+use m65816::{Cpu, LongMemory, disassemble};
+
+fn setup(program: &[u8]) -> (Cpu, LongMemory) {
+    let mut memory = LongMemory::new();
+    memory.data[0xFFFC] = 0x00;
+    memory.data[0xFFFD] = 0x80;
+    memory.data[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut memory);
+    (cpu, memory)
+}
+
+#[test]
+fn trace_lines_follow_the_m6502_columns() {
+    // CLC; XCE; REP #$30; LDA #$1234
+    let (mut cpu, mut memory) = setup(&[0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x34, 0x12]);
+    assert_eq!(
+        cpu.trace_line(&memory),
+        "008000  18           CLC                             A:0000 X:0000 Y:0000 P:24 SP:01FD D:0000 DB:00 E:1 CYC:2"
+    );
+    cpu.execute(2 + 2 + 3, &mut memory);
+    assert_eq!(
+        cpu.trace_line(&memory),
+        "008004  A9 34 12     LDA #$1234                      A:0000 X:0000 Y:0000 P:05 SP:01FD D:0000 DB:00 E:0 CYC:9"
+    );
+}
+
+#[test]
+fn immediate_operands_follow_the_m_and_x_flags() {
+    let mut memory = LongMemory::new();
+    // LDA #$1234; LDX #$1234
+    memory.data[0x8000..0x8006].copy_from_slice(&[0xA9, 0x34, 0x12, 0xA2, 0x34, 0x12]);
+    assert_eq!(disassemble(&memory, 0x8000, true, false), ("LDA #$34".to_string(), 2));
+    assert_eq!(disassemble(&memory, 0x8000, false, true), ("LDA #$1234".to_string(), 3));
+    assert_eq!(disassemble(&memory, 0x8003, false, true), ("LDX #$34".to_string(), 2));
+    assert_eq!(disassemble(&memory, 0x8003, true, false), ("LDX #$1234".to_string(), 3));
+}
+
+#[test]
+fn disassembles_65816_addressing_modes() {
+    let cases: [(&[u8], &str); 12] = [
+        (&[0xB7, 0x10], "LDA [$10],Y"),
+        (&[0xA7, 0x10], "LDA [$10]"),
+        (&[0xB2, 0x10], "LDA ($10)"),
+        (&[0xA3, 0x03], "LDA $03,S"),
+        (&[0xB3, 0x03], "LDA ($03,S),Y"),
+        (&[0xAF, 0x56, 0x34, 0x12], "LDA $123456"),
+        (&[0xBF, 0x56, 0x34, 0x12], "LDA $123456,X"),
+        (&[0xDC, 0x34, 0x12], "JML [$1234]"),
+        (&[0xFC, 0x34, 0x12], "JSR ($1234,X)"),
+        (&[0x82, 0xFD, 0xFF], "BRL $8000"),
+        (&[0x80, 0xFE], "BRA $8000"),
+        // Destination bank first in memory, source bank first in the text
+        (&[0x54, 0x01, 0x02], "MVN $02,$01"),
+    ];
+    for (bytes, text) in cases {
+        let mut memory = LongMemory::new();
+        memory.data[0x8000..0x8000 + bytes.len()].copy_from_slice(bytes);
+        assert_eq!(disassemble(&memory, 0x8000, true, true), (text.to_string(), bytes.len() as u32));
+    }
+}
+
+#[test]
+fn operands_wrap_within_the_program_bank() {
+    let mut memory = LongMemory::new();
+    // LDA $123456 split across the end of bank $01
+    memory.data[0x01_FFFE] = 0xAF;
+    memory.data[0x01_FFFF] = 0x56;
+    memory.data[0x01_0000] = 0x34;
+    memory.data[0x01_0001] = 0x12;
+    assert_eq!(disassemble(&memory, 0x01_FFFE, true, true), ("LDA $123456".to_string(), 4));
+}
@@ -0,0 +1,62 @@
+use m6502::{Byte, Memory, Word};
+use std::io::{self, Write};
+
+/// How `--dump` writes memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Address, hex bytes and ASCII, 16 bytes to a line.
+    #[default]
+    Hex,
+    /// The bytes themselves.
+    Raw,
+    /// Intel HEX data records and an end-of-file record, which `--format ihex`
+    /// loads back.
+    IntelHex,
+}
+
+/// Writes the inclusive address ranges of `memory` one after another.
+pub fn write_dumps(out: &mut impl Write, memory: &Memory, ranges: &[(Word, Word)], format: DumpFormat) -> io::Result<()> {
+    for &(start, end) in ranges {
+        let bytes = &memory.data[start as usize..=end as usize];
+        match format {
+            DumpFormat::Hex => write_hex_dump(out, start, bytes)?,
+            DumpFormat::Raw => out.write_all(bytes)?,
+            DumpFormat::IntelHex => write_intel_hex_records(out, start, bytes)?,
+        }
+    }
+    if format == DumpFormat::IntelHex {
+        writeln!(out, ":00000001FF")?;
+    }
+    out.flush()
+}
+
+fn write_hex_dump(out: &mut impl Write, start: Word, bytes: &[Byte]) -> io::Result<()> {
+    for (index, row) in bytes.chunks(16).enumerate() {
+        write!(out, "{:04X} ", start as usize + index * 16)?;
+        for column in 0..16 {
+            match row.get(column) {
+                Some(byte) => write!(out, " {:02X}", byte)?,
+                None => write!(out, "   ")?,
+            }
+        }
+        let text: String = row.iter().map(|&b| if b == b' ' || b.is_ascii_graphic() { b as char } else { '.' }).collect();
+        writeln!(out, "  {}", text)?;
+    }
+    Ok(())
+}
+
+fn write_intel_hex_records(out: &mut impl Write, start: Word, bytes: &[Byte]) -> io::Result<()> {
+    for (index, data) in bytes.chunks(16).enumerate() {
+        let addr = start as usize + index * 16;
+        let mut record = vec![data.len() as Byte, (addr >> 8) as Byte, addr as Byte, 0x00];
+        record.extend_from_slice(data);
+        let checksum = record.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)).wrapping_neg();
+        record.push(checksum);
+        write!(out, ":")?;
+        for byte in record {
+            write!(out, "{:02X}", byte)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -1,69 +1,284 @@
-use m6502::{Cpu, Memory, Word};
+mod dump;
+mod options;
+
+use m6502::nes::{INesRom, NesBus, TestRomOutcome, run_test_rom};
+use m6502::{
+    Cpu, CpuVariant, Format, Memory, Symbols, Word, load_elf, load_intel_hex, load_prg, load_raw, load_srecord, load_xex,
+};
+use dump::write_dumps;
+use options::{Options, USAGE};
 use std::env;
 use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Cycles an NES test ROM gets to report its result, unless --cycles says otherwise.
+const NES_MAX_CYCLES: u64 = 100_000_000;
+
+/// Cycles each init routine of an Atari executable gets to return.
+const XEX_INIT_CYCLES: u64 = 10_000_000;
+
+/// Prints a line of status text to stdout, or to stderr when stdout carries a
+/// dump that other tools will read.
+macro_rules! status {
+    ($to_stderr:expr, $($arg:tt)*) => {
+        if $to_stderr {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}\nRun with --help for usage.", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    // Keep stdout clean for raw and Intel HEX dumps written there.
+    let to_stderr = options.dumps_to_stdout();
 
-fn main() {
     // Create memory with all zeros.
     let mut memory = Memory { data: [0; 1024 * 64] };
 
-    // Set the reset vector so that the CPU starts execution at 0x8000.
-    memory.data[0xFFFC] = 0x00; // low byte
-    memory.data[0xFFFD] = 0x80; // high byte
-
     // Determine start address.
-    let start: Word = 0x8000;
+    let start: Word = options.load_addr.unwrap_or(0x8000);
+
+    // Set the reset vector so that the CPU starts execution at the start address.
+    memory.data[0xFFFC..0xFFFE].copy_from_slice(&start.to_le_bytes());
+
+    // Start address from the program file, if it has one.
+    let mut entry: Option<Word> = None;
+
+    // Names from the program file, if it has any.
+    let mut symbols = Symbols::new();
+
+    // Whether the program's machine decodes the undocumented opcodes.
+    let mut undocumented_opcodes = false;
 
     // Check if a program file was passed as an argument.
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
+    if let Some(filename) = &options.program {
         // Read the program file as binary.
-        let filename = &args[1];
-        match fs::read(filename) {
-            Ok(program_bytes) => {
-                if start as usize + program_bytes.len() > memory.data.len() {
-                    eprintln!("Error: Program too large to fit in memory");
-                    return;
+        let program_bytes = match fs::read(filename) {
+            Ok(program_bytes) => program_bytes,
+            Err(e) => {
+                eprintln!("Error reading '{}': {}", filename, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        // Intel HEX, S-record and ELF files carry their own addresses.
+        let is_prg = Path::new(filename).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("prg"));
+        let format = match options.format {
+            Some(format) => format,
+            None if is_prg => Format::Prg,
+            None => Format::detect(&program_bytes),
+        };
+        if format == Format::INes {
+            if let Some(option) = options.flat_memory_option() {
+                eprintln!("Error: {} cannot be used with NES test ROMs", option);
+                return ExitCode::FAILURE;
+            }
+            return run_nes_test_rom(filename, &program_bytes, &options);
+        }
+        let loaded = match format {
+            Format::IntelHex => load_intel_hex(&program_bytes, &mut memory),
+            Format::SRecord => load_srecord(&program_bytes, &mut memory),
+            Format::Elf => load_elf(&program_bytes, &mut memory, &mut symbols).map(Some),
+            Format::Prg => load_prg(&program_bytes, options.load_addr, &mut memory).map(|addr| {
+                // Start at the payload, unless it brings its own vectors.
+                if addr as usize + program_bytes.len() - 2 <= 0xFFFC {
+                    memory.data[0xFFFC..0xFFFE].copy_from_slice(&addr.to_le_bytes());
                 }
-                memory.data[start as usize..start as usize + program_bytes.len()]
-                    .copy_from_slice(&program_bytes);
-                println!("Program loaded from '{}'", filename);
+                None
+            }),
+            Format::Raw => load_raw(&program_bytes, start, &mut memory).map(|()| None),
+            Format::Xex => {
+                // The Atari's 6502 decodes the undocumented opcodes, for init
+                // routines on a CPU of their own and for the program after them
+                undocumented_opcodes = true;
+                let mut init_cpu = Cpu::new();
+                init_cpu.illegal_opcodes = undocumented_opcodes;
+                load_xex(&program_bytes, &mut init_cpu, &mut memory, XEX_INIT_CYCLES).map(Some)
             }
+            Format::INes => unreachable!("iNES files run on the NES memory map"),
+        };
+        match loaded {
+            Ok(start_address) => entry = start_address,
             Err(e) => {
-                eprintln!("Error reading '{}': {}", filename, e);
-                return;
+                eprintln!("Error loading '{}': {}", filename, e);
+                return ExitCode::FAILURE;
             }
         }
+        if symbols.is_empty() {
+            status!(to_stderr, "Program loaded from '{}' ({:?})", filename, format);
+        } else {
+            status!(to_stderr, "Program loaded from '{}' ({:?}, {} symbols)", filename, format, symbols.len());
+        }
     } else {
         // Load default program (INS_LDA_IM 0x42, INS_NOP, INS_BRK).
         memory.data[start as usize]     = 0xA9; // INS_LDA_IM
         memory.data[start as usize + 1] = 0x42; // Value to load into A
         memory.data[start as usize + 2] = 0xEA; // INS_NOP
         memory.data[start as usize + 3] = 0x00; // INS_BRK
-        println!("Default test program loaded");
+        status!(to_stderr, "Default test program loaded");
+    }
+
+    // Data files and bytes to put in memory before the run.
+    for (filename, addr) in &options.loads {
+        let bytes = match fs::read(filename) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("Error reading '{}': {}", filename, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = load_raw(&bytes, *addr, &mut memory) {
+            eprintln!("Error loading '{}': {}", filename, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    for &(addr, value) in &options.pokes {
+        memory.data[addr as usize] = value;
+    }
+
+    // Labels from symbol files, for the trace and breakpoints.
+    for filename in &options.symbol_files {
+        let text = match fs::read_to_string(filename) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Error reading '{}': {}", filename, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = symbols.read(&text) {
+            eprintln!("Error loading symbols from '{}': {}", filename, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    let mut breakpoints = Vec::new();
+    for spec in &options.breakpoints {
+        match symbols.resolve(spec) {
+            Some(addr) => breakpoints.push(addr),
+            None => {
+                eprintln!("Error: breakpoint '{}' is neither an address nor a known label", spec);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(vector) = options.reset_vector {
+        memory.data[0xFFFC..0xFFFE].copy_from_slice(&vector.to_le_bytes());
     }
 
     // Create a CPU instance and reset it.
     let mut cpu = Cpu::new();
+    cpu.illegal_opcodes = undocumented_opcodes;
     cpu.reset(&mut memory);
+    if let Some(entry) = options.entry.or(entry) {
+        cpu.pc = entry;
+    }
 
     // Execute the program for a limited number of cycles.
-    let cycles = 20;
-    let cycles_consumed = cpu.execute(cycles, &mut memory);
+    let cycles = options.cycles.unwrap_or(20);
+    let mut cycles_consumed = 0;
+    if options.trace || !breakpoints.is_empty() {
+        // One instruction at a time, so that each can be traced and checked.
+        while cycles_consumed < cycles {
+            if breakpoints.contains(&cpu.pc) {
+                match symbols.name(cpu.pc) {
+                    Some(name) => status!(to_stderr, "Breakpoint at ${:04X} ({})", cpu.pc, name),
+                    None => status!(to_stderr, "Breakpoint at ${:04X}", cpu.pc),
+                }
+                break;
+            }
+            if options.trace {
+                status!(to_stderr, "{}", cpu.trace_line_with_symbols(&memory, cycles_consumed as u64, &symbols));
+            }
+            let used = cpu.execute(1, &mut memory);
+            // Jammed, stopped or waiting for an interrupt
+            if used == 0 {
+                break;
+            }
+            cycles_consumed += used;
+        }
+    } else {
+        cycles_consumed = cpu.execute(cycles, &mut memory);
+    }
 
     // Print CPU state after execution.
-    println!("After execution:");
-    println!("Accumulator (A): {:#X}", cpu.reg_a);
-    println!("Register X:      {:#X}", cpu.reg_x);
-    println!("Register Y:      {:#X}", cpu.reg_y);
-    println!("Program Counter: {:#X}", cpu.pc);
-    println!("Status Flags:");
-    println!("  Carry:              {}", cpu.status.carry);
-    println!("  Zero:               {}", cpu.status.zero);
-    println!("  Interrupt Disable:  {}", cpu.status.interrupt_disable);
-    println!("  Decimal Mode:       {}", cpu.status.decimal_mode);
-    println!("  Break:              {}", cpu.status.break_command);
-    println!("  Overflow:           {}", cpu.status.overflow);
-    println!("  Negative:           {}", cpu.status.negative);
-    println!("Cycles consumed: {}", cycles_consumed);
+    status!(to_stderr, "After execution:");
+    status!(to_stderr, "Accumulator (A): {:#X}", cpu.reg_a);
+    status!(to_stderr, "Register X:      {:#X}", cpu.reg_x);
+    status!(to_stderr, "Register Y:      {:#X}", cpu.reg_y);
+    status!(to_stderr, "Program Counter: {:#X}", cpu.pc);
+    status!(to_stderr, "Status Flags:");
+    status!(to_stderr, "  Carry:              {}", cpu.status.carry);
+    status!(to_stderr, "  Zero:               {}", cpu.status.zero);
+    status!(to_stderr, "  Interrupt Disable:  {}", cpu.status.interrupt_disable);
+    status!(to_stderr, "  Decimal Mode:       {}", cpu.status.decimal_mode);
+    status!(to_stderr, "  Break:              {}", cpu.status.break_command);
+    status!(to_stderr, "  Overflow:           {}", cpu.status.overflow);
+    status!(to_stderr, "  Negative:           {}", cpu.status.negative);
+    status!(to_stderr, "Cycles consumed: {}", cycles_consumed);
+    if cpu.jammed {
+        status!(to_stderr, "CPU jammed at {:#X}", cpu.pc);
+    }
+    if cpu.stopped {
+        status!(to_stderr, "CPU stopped at {:#X}", cpu.pc);
+    }
+
+    if !options.dumps.is_empty() {
+        let written = match &options.dump_file {
+            Some(filename) => {
+                fs::File::create(filename).and_then(|mut file| write_dumps(&mut file, &memory, &options.dumps, options.dump_format))
+            }
+            None => write_dumps(&mut io::stdout().lock(), &memory, &options.dumps, options.dump_format),
+        };
+        if let Err(e) = written {
+            eprintln!("Error writing memory dump: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
 }
 
+
+/// Runs an iNES test ROM on the NES memory map and prints what it reports at $6000.
+/// Only a ROM that finishes with result 0 exits successfully.
+fn run_nes_test_rom(filename: &str, bytes: &[u8], options: &Options) -> ExitCode {
+    let (number, mapper) = match INesRom::parse(bytes).and_then(|rom| Ok((rom.mapper, rom.mapper()?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Error loading '{}': {}", filename, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Program loaded from '{}' (INes, mapper {})", filename, number);
+    let mut bus = NesBus::new(mapper);
+    let mut cpu = Cpu::with_variant(CpuVariant::Ricoh2A03);
+    cpu.illegal_opcodes = true;
+    let max_cycles = options.cycles.map_or(NES_MAX_CYCLES, |cycles| cycles as u64);
+    let report = run_test_rom(&mut cpu, &mut bus, max_cycles);
+    let text = report.text.trim();
+    if !text.is_empty() {
+        println!("{}", text);
+    }
+    match report.outcome {
+        TestRomOutcome::Finished(0) => {
+            println!("Passed after {} cycles", report.cycles);
+            return ExitCode::SUCCESS;
+        }
+        TestRomOutcome::Finished(code) => println!("Failed with code {} after {} cycles", code, report.cycles),
+        TestRomOutcome::TimedOut => println!("No result after {} cycles", report.cycles),
+        TestRomOutcome::Jammed => println!("CPU jammed at {:#X}", cpu.pc),
+    }
+    ExitCode::FAILURE
+}
@@ -0,0 +1,226 @@
+use crate::dump::DumpFormat;
+use m6502::{Byte, Format, Word};
+
+pub const USAGE: &str = "\
+Usage: emulate_cpu_6502 [options] [program]
+
+Runs the program file, or a small built-in test program, and prints the CPU state.
+Intel HEX, S-record, ELF, iNES and Atari XEX files are recognised by their
+contents and .prg files by their extension; anything else is loaded as a raw
+image. iNES files run as NES test ROMs until they report a result at $6000,
+which is printed; of the options below only --format and --cycles apply
+to them.
+
+Options:
+  --format <raw|prg|ihex|srec|elf|nes|xex>
+                                Load the program as this format
+  --load-addr <addr>            Where a raw image goes, $8000 by default; also
+                                overrides the load address of a PRG file
+  --entry <addr>                Start at this address instead of the reset vector
+                                or the file's start address
+  --reset-vector <addr>         Store this address at $FFFC after loading
+  --load <file>@<addr>          Copy a file into memory after the program; can
+                                be repeated
+  --poke <addr>=<value>         Store a byte after loading; can be repeated
+  --symbols <file>              Read labels from a VICE label file, an ld65 debug
+                                file or a 'name = $addr' list; can be repeated
+  --cycles <n>                  Run for at least this many cycles, 20 by default;
+                                the limit for NES test ROMs, 100000000 by default
+  --break <addr|label>          Stop when PC reaches this address; can be repeated
+  --trace                       Print each instruction and the registers before it
+  --dump <start>-<end>          Print this memory range after the run; can be
+                                repeated
+  --dump-format <hex|ihex|raw>  A hex dump with ASCII, Intel HEX records or the
+                                bytes themselves; hex by default
+  --dump-file <file>            Write the dumps to a file instead of stdout;
+                                without one, ihex and raw dumps have stdout to
+                                themselves and everything else goes to stderr
+  -h, --help                    Show this help
+
+Addresses are hex with a $ or 0x prefix, or decimal.";
+
+const OPTIONS_WITH_VALUES: &[&str] = &[
+    "--format",
+    "--load-addr",
+    "--entry",
+    "--reset-vector",
+    "--load",
+    "--poke",
+    "--symbols",
+    "--cycles",
+    "--break",
+    "--dump",
+    "--dump-format",
+    "--dump-file",
+];
+
+/// Command line options.
+#[derive(Default)]
+pub struct Options {
+    pub program: Option<String>,
+    pub format: Option<Format>,
+    pub load_addr: Option<Word>,
+    pub entry: Option<Word>,
+    pub reset_vector: Option<Word>,
+    /// Files to copy into memory, and where.
+    pub loads: Vec<(String, Word)>,
+    pub pokes: Vec<(Word, Byte)>,
+    pub symbol_files: Vec<String>,
+    pub cycles: Option<i32>,
+    /// Breakpoints as given, since labels can only be resolved once symbols are loaded.
+    pub breakpoints: Vec<String>,
+    pub trace: bool,
+    /// Inclusive address ranges.
+    pub dumps: Vec<(Word, Word)>,
+    pub dump_format: DumpFormat,
+    pub dump_file: Option<String>,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parses the arguments after the program name. Options take their value
+    /// either as the next argument or after `=`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                if options.program.replace(arg).is_some() {
+                    return Err("only one program file can be given".to_string());
+                }
+                continue;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            match name.as_str() {
+                "-h" | "--help" => {
+                    options.help = true;
+                    continue;
+                }
+                "--trace" => {
+                    options.trace = true;
+                    continue;
+                }
+                _ => {}
+            }
+            if !OPTIONS_WITH_VALUES.contains(&name.as_str()) {
+                return Err(format!("unknown option {}", name));
+            }
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(format!("{} needs a value", name)),
+            };
+            match name.as_str() {
+                "--format" => options.format = Some(parse_format(&value)?),
+                "--load-addr" => options.load_addr = Some(parse_word(&value)?),
+                "--entry" => options.entry = Some(parse_word(&value)?),
+                "--reset-vector" => options.reset_vector = Some(parse_word(&value)?),
+                "--load" => options.loads.push(parse_load(&value)?),
+                "--poke" => options.pokes.push(parse_poke(&value)?),
+                "--symbols" => options.symbol_files.push(value),
+                "--cycles" => options.cycles = Some(parse_cycles(&value)?),
+                "--break" => options.breakpoints.push(value),
+                "--dump" => options.dumps.push(parse_range(&value)?),
+                "--dump-format" => options.dump_format = parse_dump_format(&value)?,
+                "--dump-file" => options.dump_file = Some(value),
+                _ => unreachable!("{} is in OPTIONS_WITH_VALUES", name),
+            }
+        }
+        Ok(options)
+    }
+
+    /// The first option given that works on the flat 64K memory of a plain
+    /// program, which NES test ROMs do not run in.
+    pub fn flat_memory_option(&self) -> Option<&'static str> {
+        [
+            ("--load-addr", self.load_addr.is_some()),
+            ("--entry", self.entry.is_some()),
+            ("--reset-vector", self.reset_vector.is_some()),
+            ("--load", !self.loads.is_empty()),
+            ("--poke", !self.pokes.is_empty()),
+            ("--symbols", !self.symbol_files.is_empty()),
+            ("--break", !self.breakpoints.is_empty()),
+            ("--trace", self.trace),
+            ("--dump", !self.dumps.is_empty()),
+            ("--dump-file", self.dump_file.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(name, given)| given.then_some(name))
+    }
+
+    /// Whether the dumps go to stdout in a format meant for other tools rather
+    /// than people, so that nothing else may be printed there.
+    pub fn dumps_to_stdout(&self) -> bool {
+        !self.dumps.is_empty() && self.dump_file.is_none() && self.dump_format != DumpFormat::Hex
+    }
+}
+
+fn parse_format(name: &str) -> Result<Format, String> {
+    match name {
+        "raw" => Ok(Format::Raw),
+        "prg" => Ok(Format::Prg),
+        "ihex" | "hex" => Ok(Format::IntelHex),
+        "srec" => Ok(Format::SRecord),
+        "elf" => Ok(Format::Elf),
+        "nes" => Ok(Format::INes),
+        "xex" => Ok(Format::Xex),
+        _ => Err(format!("unknown format '{}', expected raw, prg, ihex, srec, elf, nes or xex", name)),
+    }
+}
+
+fn parse_dump_format(name: &str) -> Result<DumpFormat, String> {
+    match name {
+        "hex" => Ok(DumpFormat::Hex),
+        "ihex" => Ok(DumpFormat::IntelHex),
+        "raw" => Ok(DumpFormat::Raw),
+        _ => Err(format!("unknown dump format '{}', expected hex, ihex or raw", name)),
+    }
+}
+
+/// Parses `file@addr`.
+fn parse_load(text: &str) -> Result<(String, Word), String> {
+    match text.rsplit_once('@') {
+        Some((file, addr)) if !file.is_empty() => Ok((file.to_string(), parse_word(addr)?)),
+        _ => Err(format!("'{}' is not <file>@<addr>", text)),
+    }
+}
+
+/// Parses `addr=value`.
+fn parse_poke(text: &str) -> Result<(Word, Byte), String> {
+    let Some((addr, value)) = text.split_once('=') else {
+        return Err(format!("'{}' is not <addr>=<value>", text));
+    };
+    let byte = parse_word(value).ok().and_then(|byte| Byte::try_from(byte).ok());
+    let byte = byte.ok_or_else(|| format!("'{}' is not a byte", value))?;
+    Ok((parse_word(addr)?, byte))
+}
+
+/// Parses `start-end`, both inclusive.
+fn parse_range(text: &str) -> Result<(Word, Word), String> {
+    let Some((start, end)) = text.split_once('-') else {
+        return Err(format!("'{}' is not <start>-<end>", text));
+    };
+    let (start, end) = (parse_word(start)?, parse_word(end)?);
+    if end < start {
+        return Err(format!("range '{}' ends before it starts", text));
+    }
+    Ok((start, end))
+}
+
+fn parse_cycles(text: &str) -> Result<i32, String> {
+    match text.parse() {
+        Ok(cycles) if cycles > 0 => Ok(cycles),
+        _ => Err(format!("'{}' is not a positive number of cycles", text)),
+    }
+}
+
+/// Parses `$C000`, `0xC000` or `49152`.
+pub fn parse_word(text: &str) -> Result<Word, String> {
+    let parsed = match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => Word::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("'{}' is not a 16-bit address", text))
+}
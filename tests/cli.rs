@@ -0,0 +1,289 @@
+//! Runs the emulator binary on small program files.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes `bytes` to a file named `name` in a scratch directory and returns its path.
+fn program_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_emulate_cpu_6502")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn runs_the_default_program() {
+    assert!(stdout(&run(&[])).contains("Accumulator (A): 0x42"));
+}
+
+#[test]
+fn loads_intel_hex_at_its_start_address() {
+    // LDX #$07 at $0600, start address $0600
+    let path = program_file("start.hex", b":02060000A2074F\n:0400000500000600F1\n:00000001FF\n");
+    let output = stdout(&run(&[path.to_str().unwrap()]));
+    assert!(output.contains("(IntelHex)"), "{}", output);
+    assert!(output.contains("Register X:      0x7"), "{}", output);
+}
+
+#[test]
+fn reports_load_errors() {
+    let path = program_file("bad.hex", b":02060000A2074E\n:00000001FF\n");
+    let output = run(&[path.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 1: checksum is $4E, expected $4F"), "{}", stderr);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn loads_prg_files_at_their_header_address() {
+    // LDX #$07 at $C000
+    let path = program_file("start.prg", &[0x00, 0xC0, 0xA2, 0x07, 0x00]);
+    assert!(stdout(&run(&[path.to_str().unwrap()])).contains("Register X:      0x7"));
+    // Without the extension, --format picks the loader
+    let path = program_file("prg.bin", &[0x00, 0xC0, 0xA2, 0x07, 0x00]);
+    let output = stdout(&run(&["--format", "prg", path.to_str().unwrap()]));
+    assert!(output.contains("(Prg)") && output.contains("Register X:      0x7"), "{}", output);
+}
+
+#[test]
+fn places_raw_images_with_load_addr_and_entry() {
+    // INX, BRK, LDY #$05, BRK
+    let path = program_file("raw.bin", &[0xE8, 0x00, 0xA0, 0x05, 0x00]);
+    let output = stdout(&run(&["--load-addr", "$0600", path.to_str().unwrap()]));
+    assert!(output.contains("Register X:      0x1") && output.contains("Register Y:      0x0"), "{}", output);
+
+    let output = stdout(&run(&["--load-addr=0x0600", "--entry", "$0602", path.to_str().unwrap()]));
+    assert!(output.contains("Register Y:      0x5") && output.contains("Register X:      0x0"), "{}", output);
+
+    let output = stdout(&run(&["--load-addr", "1536", "--reset-vector", "$0602", path.to_str().unwrap()]));
+    assert!(output.contains("Register Y:      0x5"), "{}", output);
+}
+
+#[test]
+fn rejects_bad_options() {
+    let output = run(&["--load-addr", "$10000"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'$10000' is not a 16-bit address"));
+    let output = run(&["--bogus"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown option --bogus"));
+    assert!(!output.status.success());
+}
+
+#[test]
+fn stops_at_a_labelled_breakpoint() {
+    // loop: INX / JMP loop, at $0600
+    let path = program_file("loop.bin", &[0xE8, 0x4C, 0x00, 0x06]);
+    let labels = program_file("loop.lbl", b"al C:0600 .start\nal C:0601 .again\n");
+    let output = stdout(&run(&[
+        "--load-addr=$0600",
+        "--symbols",
+        labels.to_str().unwrap(),
+        "--break",
+        "again",
+        "--cycles=100",
+        path.to_str().unwrap(),
+    ]));
+    assert!(output.contains("Breakpoint at $0601 (again)"), "{}", output);
+    assert!(output.contains("Register X:      0x1"), "{}", output);
+
+    let output = run(&["--break", "nowhere"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("breakpoint 'nowhere' is neither an address nor a known label"), "{}", stderr);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn traces_instructions_with_labels() {
+    let path = program_file("trace.bin", &[0xE8, 0x4C, 0x00, 0x06]);
+    let labels = program_file("trace.labels", b"start = $0600 ; entry\n");
+    let output = stdout(&run(&[
+        "--load-addr",
+        "$0600",
+        "--symbols",
+        labels.to_str().unwrap(),
+        "--trace",
+        "--cycles",
+        "6",
+        path.to_str().unwrap(),
+    ]));
+    assert!(output.contains("0601  4C 00 06  JMP start"), "{}", output);
+    assert_eq!(output.matches("INX").count(), 2, "{}", output);
+}
+
+/// An NROM test ROM that writes "ok" at $6004 and `result` at $6000.
+fn nes_test_rom(result: u8) -> Vec<u8> {
+    let mut file = b"NES\x1A\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    let mut prg = vec![0xEA; 0x4000];
+    let code = [
+        0xA9, 0x80, 0x8D, 0x00, 0x60, // running
+        0xA9, b'o', 0x8D, 0x04, 0x60, // text
+        0xA9, b'k', 0x8D, 0x05, 0x60,
+        0xA9, 0xDE, 0x8D, 0x01, 0x60, // signature
+        0xA9, 0xB0, 0x8D, 0x02, 0x60,
+        0xA9, 0x61, 0x8D, 0x03, 0x60,
+        0xA9, result, 0x8D, 0x00, 0x60, // result
+        0x4C, 0x23, 0x80, // JMP *
+    ];
+    prg[..code.len()].copy_from_slice(&code);
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    file.extend_from_slice(&prg);
+    file
+}
+
+#[test]
+fn runs_nes_test_roms() {
+    let path = program_file("pass.nes", &nes_test_rom(0));
+    let output = stdout(&run(&[path.to_str().unwrap()]));
+    assert!(output.contains("(INes, mapper 0)") && output.contains("\nok\nPassed after"), "{}", output);
+
+    let path = program_file("fail.nes", &nes_test_rom(2));
+    let output = run(&[path.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Failed with code 2"));
+    assert!(!output.status.success());
+
+    let output = run(&["--cycles", "20", path.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No result after 2"));
+    assert!(!output.status.success());
+
+    // Options for the flat memory map are refused rather than ignored
+    for option in ["--trace", "--break=$8000", "--poke=$0600=1", "--dump=$0600-$0601"] {
+        let output = run(&[option, path.to_str().unwrap()]);
+        let name = option.split('=').next().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(&format!("{} cannot be used with NES test ROMs", name)), "{}", stderr);
+        assert!(output.stdout.is_empty());
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+fn runs_xex_files_from_runad() {
+    // Init: LDA #5 / STA $0700 / RTS; run: LDX $0700 / BRK
+    let mut bytes = vec![0xFF, 0xFF, 0x00, 0x20, 0x05, 0x20, 0xA9, 0x05, 0x8D, 0x00, 0x07, 0x60];
+    bytes.extend_from_slice(&[0xE2, 0x02, 0xE3, 0x02, 0x00, 0x20]);
+    bytes.extend_from_slice(&[0x00, 0x30, 0x03, 0x30, 0xAE, 0x00, 0x07, 0x00]);
+    bytes.extend_from_slice(&[0xE0, 0x02, 0xE1, 0x02, 0x00, 0x30]);
+    let path = program_file("init.xex", &bytes);
+    let output = stdout(&run(&[path.to_str().unwrap()]));
+    assert!(output.contains("(Xex)") && output.contains("Register X:      0x5"), "{}", output);
+}
+
+#[test]
+fn runs_xex_files_with_undocumented_opcodes() {
+    // Init: LDA #$0D / LDX #$07 / SAX $0700 / RTS; run: LAX $0700 / BRK
+    let mut bytes = vec![0xFF, 0xFF, 0x00, 0x20, 0x07, 0x20, 0xA9, 0x0D, 0xA2, 0x07, 0x8F, 0x00, 0x07, 0x60];
+    bytes.extend_from_slice(&[0xE2, 0x02, 0xE3, 0x02, 0x00, 0x20]);
+    bytes.extend_from_slice(&[0x00, 0x30, 0x03, 0x30, 0xAF, 0x00, 0x07, 0x00]);
+    bytes.extend_from_slice(&[0xE0, 0x02, 0xE1, 0x02, 0x00, 0x30]);
+    let path = program_file("lax.xex", &bytes);
+    let output = stdout(&run(&[path.to_str().unwrap()]));
+    assert!(output.contains("Accumulator (A): 0x5") && output.contains("Register X:      0x5"), "{}", output);
+}
+
+#[test]
+fn dumps_memory_after_the_run() {
+    // LDA #$42 / STA $0600 / BRK
+    let path = program_file("store.bin", &[0xA9, 0x42, 0x8D, 0x00, 0x06, 0x00]);
+    let data = program_file("data.bin", b"Hello");
+    let data_arg = format!("{}@$0611", data.to_str().unwrap());
+    let output = stdout(&run(&[
+        "--load-addr=$0200",
+        "--load",
+        &data_arg,
+        "--poke",
+        "$0601=$20",
+        "--poke=$0616=0x7F",
+        "--dump",
+        "$0600-$0617",
+        path.to_str().unwrap(),
+    ]));
+    let expected = "\
+0600  42 20 00 00 00 00 00 00 00 00 00 00 00 00 00 00  B ..............
+0610  00 48 65 6C 6C 6F 7F 00                          .Hello..
+";
+    assert!(output.ends_with(expected), "{}", output);
+}
+
+#[test]
+fn dumps_intel_hex_and_raw_files() {
+    let path = program_file("store2.bin", &[0xA9, 0x42, 0x8D, 0x00, 0x06, 0x00]);
+    let dump = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dump.hex");
+    stdout(&run(&[
+        "--load-addr=$0200",
+        "--dump=$0200-$0205",
+        "--dump=$05F0-$0610",
+        "--dump-format=ihex",
+        "--dump-file",
+        dump.to_str().unwrap(),
+        path.to_str().unwrap(),
+    ]));
+    let text = fs::read_to_string(&dump).unwrap();
+    assert!(text.starts_with(":06020000A9428D0006007A\n:1005F000"), "{}", text);
+    assert_eq!(text.matches(":00000001FF").count(), 1, "{}", text);
+    // The loader reads it back
+    let mut memory = m6502::Memory { data: [0xFF; 1024 * 64] };
+    assert_eq!(m6502::load_intel_hex(text.as_bytes(), &mut memory), Ok(None));
+    assert_eq!(memory.data[0x0200..0x0206], [0xA9, 0x42, 0x8D, 0x00, 0x06, 0x00]);
+    assert_eq!(memory.data[0x05FF..0x0602], [0x00, 0x42, 0x00]);
+    assert_eq!(memory.data[0x0611], 0xFF);
+
+    let dump = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dump.bin");
+    stdout(&run(&["--dump", "$0600-$0601", "--dump-format", "raw", "--dump-file", dump.to_str().unwrap()]));
+    assert_eq!(fs::read(&dump).unwrap(), [0x00, 0x00]);
+}
+
+#[test]
+fn keeps_stdout_for_intel_hex_dumps() {
+    let path = program_file("store3.bin", &[0xA9, 0x42, 0x8D, 0x00, 0x06, 0x00]);
+    let output = run(&["--load-addr=$0200", "--dump=$0200-$0205", "--dump=$0600-$0600", "--dump-format=ihex", path.to_str().unwrap()]);
+    let text = stdout(&output);
+    assert_eq!(text, ":06020000A9428D0006007A\n:0106000042B7\n:00000001FF\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Accumulator (A): 0x42"));
+
+    // The dump runs again as a program
+    let dump = program_file("store3.hex", text.as_bytes());
+    let output = stdout(&run(&["--format", "ihex", "--entry=$0200", "--dump=$0600-$0600", dump.to_str().unwrap()]));
+    assert!(output.contains("Accumulator (A): 0x42"), "{}", output);
+    assert!(output.ends_with(&format!("0600  42{}B\n", " ".repeat(47))), "{}", output);
+}
+
+#[test]
+fn rejects_bad_dump_and_poke_arguments() {
+    for (args, message) in [
+        (&["--poke", "$0600=$100"][..], "'$100' is not a byte"),
+        (&["--poke", "$0600"], "'$0600' is not <addr>=<value>"),
+        (&["--dump", "$0610-$0600"], "range '$0610-$0600' ends before it starts"),
+        (&["--dump-format", "bin"], "unknown dump format 'bin'"),
+        (&["--load", "data.bin"], "'data.bin' is not <file>@<addr>"),
+    ] {
+        let output = run(args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{:?}: {}", args, stderr);
+        assert!(!output.status.success(), "{:?}", args);
+    }
+}
+
+#[test]
+fn exits_with_failure_on_errors() {
+    let output = run(&["--dump", "$0610-$0600"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("missing.bin");
+    let output = run(&["--load", &format!("{}@$0600", missing.display())]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error reading"));
+    assert!(!output.status.success());
+
+    let dump = missing.join("dump.bin");
+    let output = run(&["--dump", "$0600-$0601", "--dump-file", dump.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error writing memory dump"));
+    assert!(!output.status.success());
+}